@@ -0,0 +1,345 @@
+//! Compares `SeqLock` with `std::sync::RwLock`, `parking_lot::RwLock` and
+//! `std::sync::Mutex` under varying payload sizes and contention.
+//!
+//! ```sh
+//! cargo bench --bench contention
+//! # A single scenario, e.g. only reads racing with a writer:
+//! cargo bench --bench contention -- read_with_writer
+//! ```
+//!
+//! Benchmark ids are `scenario/lock/parameter`. The scenarios are:
+//!
+//! - `uncontended_read`: a single `read` of an 8-byte payload with no other
+//!   threads, in CPU cycles on x86_64 and in wall time elsewhere.
+//! - `read`: uncontended reads of payloads from 8 bytes to 64 KiB.
+//! - `read_contended`: reads of a 64-byte payload while other threads read
+//!   it too, for 1 to 8 readers in total.
+//! - `read_with_writer`: reads of a 64-byte payload while another thread
+//!   writes it every 100µs, 10µs or 1µs, or continuously.
+//! - `write`: writer throughput for a 64-byte payload while 0 to 4 other
+//!   threads continuously read it.
+//!
+//! Background threads are started, and have all begun looping, before a
+//! benchmark is measured, and are stopped after it. Payloads are arrays, so
+//! nothing is allocated in the measured region. Contended scenarios use flat
+//! sampling, so that every sample runs the same number of iterations.
+//! Writers with a period busy-wait between writes, so each of them uses up a
+//! core: compare results on the same machine only, with the thread counts
+//! below the number of cores.
+
+#[cfg(feature = "critical-section")]
+#[path = "../tests/common/mod.rs"]
+mod common;
+
+#[cfg(not(any(loom, shuttle, target_family = "wasm")))]
+mod suite {
+    use criterion::measurement::{Measurement, ValueFormatter};
+    use criterion::{BenchmarkGroup, BenchmarkId, Criterion, SamplingMode, Throughput};
+    use seqlock::SeqLock;
+    use std::hint::{black_box, spin_loop};
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::{Barrier, Mutex, RwLock};
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    /// The operations a benchmark needs from a lock.
+    pub trait Lock<T: Copy>: Sync {
+        const NAME: &'static str;
+        fn new(val: T) -> Self;
+        fn read(&self) -> T;
+        fn write(&self, val: T);
+    }
+
+    impl<T: Copy + Send> Lock<T> for SeqLock<T> {
+        const NAME: &'static str = "SeqLock";
+        fn new(val: T) -> Self {
+            SeqLock::new(val)
+        }
+        fn read(&self) -> T {
+            SeqLock::read(self)
+        }
+        fn write(&self, val: T) {
+            SeqLock::write(self, val)
+        }
+    }
+
+    impl<T: Copy + Send + Sync> Lock<T> for RwLock<T> {
+        const NAME: &'static str = "std::RwLock";
+        fn new(val: T) -> Self {
+            RwLock::new(val)
+        }
+        fn read(&self) -> T {
+            *RwLock::read(self).unwrap()
+        }
+        fn write(&self, val: T) {
+            *RwLock::write(self).unwrap() = val;
+        }
+    }
+
+    impl<T: Copy + Send + Sync> Lock<T> for parking_lot::RwLock<T> {
+        const NAME: &'static str = "parking_lot::RwLock";
+        fn new(val: T) -> Self {
+            parking_lot::RwLock::new(val)
+        }
+        fn read(&self) -> T {
+            *parking_lot::RwLock::read(self)
+        }
+        fn write(&self, val: T) {
+            *parking_lot::RwLock::write(self) = val;
+        }
+    }
+
+    impl<T: Copy + Send> Lock<T> for Mutex<T> {
+        const NAME: &'static str = "std::Mutex";
+        fn new(val: T) -> Self {
+            Mutex::new(val)
+        }
+        fn read(&self) -> T {
+            *self.lock().unwrap()
+        }
+        fn write(&self, val: T) {
+            *self.lock().unwrap() = val;
+        }
+    }
+
+    /// Threads hammering a lock while a benchmark runs.
+    #[derive(Clone, Copy)]
+    struct Background {
+        readers: usize,
+        // `Some(Duration::ZERO)` writes continuously.
+        writer_period: Option<Duration>,
+    }
+
+    const IDLE: Background = Background {
+        readers: 0,
+        writer_period: None,
+    };
+
+    impl Background {
+        /// Runs `f` while the background threads are running on `lock`.
+        fn run<const N: usize, L: Lock<[u64; N]>>(self, lock: &L, f: impl FnOnce()) {
+            let stop = AtomicBool::new(false);
+            let writers = usize::from(self.writer_period.is_some());
+            let started = Barrier::new(self.readers + writers + 1);
+            let (stop, started) = (&stop, &started);
+            thread::scope(|s| {
+                for _ in 0..self.readers {
+                    s.spawn(|| {
+                        started.wait();
+                        while !stop.load(Ordering::Relaxed) {
+                            black_box(lock.read());
+                        }
+                    });
+                }
+                if let Some(period) = self.writer_period {
+                    s.spawn(move || {
+                        started.wait();
+                        let mut i = 0;
+                        while !stop.load(Ordering::Relaxed) {
+                            let start = Instant::now();
+                            i += 1;
+                            lock.write([i; N]);
+                            while start.elapsed() < period {
+                                spin_loop();
+                            }
+                        }
+                    });
+                }
+                started.wait();
+                f();
+                stop.store(true, Ordering::Relaxed);
+            });
+        }
+    }
+
+    fn bench_read<M: Measurement, const N: usize, L: Lock<[u64; N]>>(
+        group: &mut BenchmarkGroup<'_, M>,
+        param: impl std::fmt::Display,
+        background: Background,
+    ) {
+        let lock = L::new([0; N]);
+        background.run(&lock, || {
+            group.bench_function(BenchmarkId::new(L::NAME, param), |b| {
+                b.iter(|| black_box(black_box(&lock).read()))
+            });
+        });
+    }
+
+    fn bench_write<M: Measurement, const N: usize, L: Lock<[u64; N]>>(
+        group: &mut BenchmarkGroup<'_, M>,
+        param: impl std::fmt::Display,
+        background: Background,
+    ) {
+        let lock = L::new([0; N]);
+        background.run(&lock, || {
+            group.bench_function(BenchmarkId::new(L::NAME, param), |b| {
+                let mut i = 0;
+                b.iter(|| {
+                    i += 1;
+                    black_box(&lock).write(black_box([i; N]))
+                })
+            });
+        });
+    }
+
+    // Runs `$bench` for each lock type, with the payload `[u64; $n]`.
+    macro_rules! for_each_lock {
+        ($bench:ident::<$n:literal>($($arg:expr),*)) => {
+            $bench::<_, $n, SeqLock<[u64; $n]>>($($arg),*);
+            $bench::<_, $n, RwLock<[u64; $n]>>($($arg),*);
+            $bench::<_, $n, parking_lot::RwLock<[u64; $n]>>($($arg),*);
+            $bench::<_, $n, Mutex<[u64; $n]>>($($arg),*);
+        };
+    }
+
+    /// Reads the time stamp counter, which counts reference cycles.
+    #[cfg(target_arch = "x86_64")]
+    pub struct Cycles;
+
+    #[cfg(target_arch = "x86_64")]
+    fn rdtsc() -> u64 {
+        use std::arch::x86_64::{_mm_lfence, _rdtsc};
+        // Keep the measured code from being reordered around the reads.
+        unsafe {
+            _mm_lfence();
+            let t = _rdtsc();
+            _mm_lfence();
+            t
+        }
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    impl Measurement for Cycles {
+        type Intermediate = u64;
+        type Value = u64;
+        fn start(&self) -> u64 {
+            rdtsc()
+        }
+        fn end(&self, start: u64) -> u64 {
+            rdtsc().wrapping_sub(start)
+        }
+        fn add(&self, a: &u64, b: &u64) -> u64 {
+            a + b
+        }
+        fn zero(&self) -> u64 {
+            0
+        }
+        fn to_f64(&self, val: &u64) -> f64 {
+            *val as f64
+        }
+        fn formatter(&self) -> &dyn ValueFormatter {
+            &CyclesFormatter
+        }
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    struct CyclesFormatter;
+
+    #[cfg(target_arch = "x86_64")]
+    impl ValueFormatter for CyclesFormatter {
+        fn scale_values(&self, _typical: f64, _values: &mut [f64]) -> &'static str {
+            "cycles"
+        }
+        fn scale_throughputs(
+            &self,
+            _typical: f64,
+            throughput: &Throughput,
+            values: &mut [f64],
+        ) -> &'static str {
+            let (n, unit) = match *throughput {
+                Throughput::Bytes(n) | Throughput::BytesDecimal(n) => (n, "cycles/byte"),
+                Throughput::Bits(n) => (n, "cycles/bit"),
+                Throughput::Elements(n) | Throughput::ElementsAndBytes { elements: n, .. } => {
+                    (n, "cycles/element")
+                }
+            };
+            for val in values {
+                *val /= n as f64;
+            }
+            unit
+        }
+        fn scale_for_machines(&self, _values: &mut [f64]) -> &'static str {
+            "cycles"
+        }
+    }
+
+    pub fn uncontended_read<M: Measurement>(c: &mut Criterion<M>) {
+        let mut group = c.benchmark_group("uncontended_read");
+        for_each_lock!(bench_read::<1>(&mut group, "8B", IDLE));
+        group.finish();
+    }
+
+    fn cores() -> usize {
+        thread::available_parallelism().map_or(1, |n| n.get())
+    }
+
+    pub fn scenarios(c: &mut Criterion) {
+        let mut group = c.benchmark_group("read");
+        for_each_lock!(bench_read::<1>(&mut group, "8B", IDLE));
+        for_each_lock!(bench_read::<8>(&mut group, "64B", IDLE));
+        for_each_lock!(bench_read::<64>(&mut group, "512B", IDLE));
+        for_each_lock!(bench_read::<512>(&mut group, "4KiB", IDLE));
+        for_each_lock!(bench_read::<8192>(&mut group, "64KiB", IDLE));
+        group.finish();
+
+        let mut group = c.benchmark_group("read_contended");
+        group.sampling_mode(SamplingMode::Flat);
+        for readers in [1, 2, 4, 8].into_iter().filter(|&n| n <= cores()) {
+            let background = Background {
+                readers: readers - 1,
+                writer_period: None,
+            };
+            for_each_lock!(bench_read::<8>(&mut group, readers, background));
+        }
+        group.finish();
+
+        let mut group = c.benchmark_group("read_with_writer");
+        group.sampling_mode(SamplingMode::Flat);
+        for period in [100_000, 10_000, 1_000, 0] {
+            let background = Background {
+                readers: 0,
+                writer_period: Some(Duration::from_nanos(period)),
+            };
+            let param = match period {
+                0 => "continuous".to_string(),
+                _ => format!("{:?}", Duration::from_nanos(period)),
+            };
+            for_each_lock!(bench_read::<8>(&mut group, &param, background));
+        }
+        group.finish();
+
+        let mut group = c.benchmark_group("write");
+        group.sampling_mode(SamplingMode::Flat);
+        group.throughput(Throughput::Elements(1));
+        for readers in [0, 1, 4].into_iter().filter(|&n| n < cores()) {
+            let background = Background {
+                readers,
+                writer_period: None,
+            };
+            for_each_lock!(bench_write::<8>(&mut group, readers, background));
+        }
+        group.finish();
+    }
+
+    pub fn main() {
+        let config = || {
+            Criterion::default()
+                .warm_up_time(Duration::from_secs(1))
+                .measurement_time(Duration::from_secs(2))
+        };
+
+        #[cfg(target_arch = "x86_64")]
+        uncontended_read(&mut config().with_measurement(Cycles).configure_from_args());
+        #[cfg(not(target_arch = "x86_64"))]
+        uncontended_read(&mut config().configure_from_args());
+
+        let mut c = config().configure_from_args();
+        scenarios(&mut c);
+        c.final_summary();
+    }
+}
+
+fn main() {
+    #[cfg(not(any(loom, shuttle, target_family = "wasm")))]
+    suite::main();
+}
@@ -0,0 +1,73 @@
+//! Measures the cost of uncontended reads and writes for a range of payload
+//! sizes.
+//!
+//! Compare the default volatile copy with the `atomic-memcpy` feature:
+//!
+//! ```sh
+//! cargo bench --bench copy
+//! cargo bench --bench copy --features atomic-memcpy
+//! ```
+
+#[cfg(feature = "critical-section")]
+#[path = "../tests/common/mod.rs"]
+mod common;
+
+use seqlock::SeqLock;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+const TARGET: Duration = Duration::from_millis(200);
+
+// Runs `f` repeatedly for about `TARGET` and returns the time per call.
+fn measure(mut f: impl FnMut()) -> Duration {
+    let mut iters = 1u32;
+    loop {
+        let start = Instant::now();
+        for _ in 0..iters {
+            f();
+        }
+        let elapsed = start.elapsed();
+        if elapsed >= TARGET {
+            return elapsed / iters;
+        }
+        iters = iters.saturating_mul(2);
+    }
+}
+
+fn bench<const N: usize>() {
+    let lock = SeqLock::new([0u8; N]);
+    let read = measure(|| {
+        black_box(black_box(&lock).read());
+    });
+    let write = measure(|| black_box(&lock).write(black_box([1; N])));
+    println!("{N:>6} bytes: read {read:>10.2?}  write {write:>10.2?}");
+}
+
+// Values which are as large as their alignment are copied with a single
+// access, unlike byte arrays of the same size.
+fn bench_int<T: Copy + Default + Send>(name: &str, val: T) {
+    let lock = SeqLock::new(T::default());
+    let read = measure(|| {
+        black_box(black_box(&lock).read());
+    });
+    let write = measure(|| black_box(&lock).write(black_box(val)));
+    println!("{name:>12}: read {read:>10.2?}  write {write:>10.2?}");
+}
+
+fn main() {
+    if cfg!(feature = "atomic-memcpy") {
+        println!("atomic copy");
+    } else {
+        println!("volatile copy");
+    }
+    // Odd lengths, so that the atomic copy has an unaligned tail.
+    bench::<1>();
+    bench::<7>();
+    bench::<63>();
+    bench::<255>();
+    bench::<1023>();
+    bench::<4095>();
+    bench_int("u16", 1u16);
+    bench_int("u32", 1u32);
+    bench_int("u64", 1u64);
+}
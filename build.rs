@@ -0,0 +1,11 @@
+// `cfg(sanitize)` is unstable, so detect ThreadSanitizer here instead. Under
+// it the data is copied with atomics, since TSan reports the volatile copy
+// racing with writers.
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-env-changed=CARGO_CFG_SANITIZE");
+    let sanitizers = std::env::var("CARGO_CFG_SANITIZE").unwrap_or_default();
+    if sanitizers.split(',').any(|s| s == "thread") {
+        println!("cargo:rustc-cfg=tsan");
+    }
+}
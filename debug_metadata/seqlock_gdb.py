@@ -0,0 +1,72 @@
+# GDB pretty-printers for seqlock types. Embedded into the `.debug_gdb_scripts`
+# section by the `debugger_visualizer` attribute in src/lib.rs, so they are
+# loaded automatically once the binary's directory is in gdb's
+# `auto-load safe-path`.
+
+import re
+
+import gdb
+
+
+def _scalar(val):
+    # Descend through the single-field wrappers of atomics and `UnsafeCell`
+    # (whose layout differs between core and portable-atomic) to the integer.
+    while True:
+        ty = val.type.strip_typedefs()
+        if ty.code != gdb.TYPE_CODE_STRUCT or not ty.fields():
+            return val
+        val = val[ty.fields()[0]]
+
+
+class SeqLockPrinter:
+    """Prints a `SeqLock<T>` as its value, sequence number and writer state."""
+
+    def __init__(self, val):
+        self.val = val
+
+    def to_string(self):
+        return "SeqLock"
+
+    def children(self):
+        seq = int(_scalar(self.val["seq"]))
+        yield "value", self.val["data"]["value"]
+        yield "seq", seq
+        yield "writer_active", bool(seq & 1)
+
+
+class SeqLockGuardPrinter:
+    """Prints a `SeqLockGuard<T>` as the value being written."""
+
+    def __init__(self, val):
+        self.val = val
+
+    def to_string(self):
+        return "SeqLockGuard"
+
+    def children(self):
+        write = self.val["write"]
+        yield "value", write["seqlock"].dereference()["data"]["value"]
+        yield "seq", int(write["seq"])
+
+
+_PRINTERS = [
+    (re.compile(r"^seqlock::SeqLock<.*>$"), SeqLockPrinter),
+    (re.compile(r"^seqlock::SeqLockGuard<.*>$"), SeqLockGuardPrinter),
+]
+
+
+def lookup(val):
+    tag = val.type.strip_typedefs().tag
+    if tag is None:
+        return None
+    for pattern, printer in _PRINTERS:
+        if pattern.match(tag):
+            return printer(val)
+    return None
+
+
+_objfile = gdb.current_objfile()
+if _objfile is not None:
+    _objfile.pretty_printers.append(lookup)
+else:
+    gdb.pretty_printers.append(lookup)
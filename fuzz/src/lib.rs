@@ -0,0 +1,228 @@
+//! Executes schedules of `SeqLock` operations decoded from fuzzer input, on
+//! real threads, and checks the lock's invariants:
+//!
+//! - every snapshot a reader observes is exactly one of the written values,
+//! - sequence numbers seen by readers are even and never go backwards,
+//! - the final sequence number accounts for exactly the writes which
+//!   happened,
+//! - and the schedule finishes, i.e. nothing deadlocks.
+//!
+//! A schedule is a list of phases, each giving every thread a few operations.
+//! All threads wait on a barrier between phases, so the fuzzer controls which
+//! operations may overlap.
+
+use arbitrary::{Arbitrary, Unstructured};
+use seqlock::SeqLock;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Barrier;
+use std::thread;
+
+/// The payload stored in the lock. Every value written is `pattern(n)` for
+/// some `n`, so a reader can tell whether a snapshot is torn.
+pub type Payload = [u64; 4];
+
+/// Returns the payload written with tag `n`.
+pub fn pattern(n: u64) -> Payload {
+    [n, n ^ 0x5555_5555_5555_5555, n.rotate_left(17), !n]
+}
+
+#[derive(Arbitrary, Clone, Copy, Debug)]
+pub enum Op {
+    Read,
+    ReadVersioned,
+    ReadGuard { refreshes: u8 },
+    ReadWatchdog { max_spins: u8 },
+    ReadMaybeUninit,
+    Write,
+    Update,
+    SetIfNe,
+    SwapIf { expect_tag: u8 },
+    LockWrite { yields: u8 },
+    TryLockWrite { yields: u8 },
+    Yield,
+}
+
+#[derive(Arbitrary, Debug)]
+pub struct Schedule {
+    threads: u8,
+    phases: Vec<Vec<Vec<Op>>>,
+}
+
+const MAX_PHASES: usize = 8;
+const MAX_OPS: usize = 8;
+
+impl Schedule {
+    fn threads(&self) -> usize {
+        2 + usize::from(self.threads % 3)
+    }
+
+    /// Returns the operations thread `t` runs in phase `p`.
+    fn ops(&self, p: usize, t: usize) -> &[Op] {
+        let ops = self.phases[p].get(t).map_or(&[][..], |ops| &ops[..]);
+        &ops[..ops.len().min(MAX_OPS)]
+    }
+
+    fn phases(&self) -> usize {
+        self.phases.len().min(MAX_PHASES)
+    }
+}
+
+/// Returns the tag written by operation `i` of thread `t` in phase `p`. Tags
+/// are unique, and non-zero so that they differ from the initial value.
+fn tag(p: usize, t: usize, i: usize) -> u64 {
+    ((p as u64) << 32 | (t as u64) << 16 | i as u64) + 1
+}
+
+impl Op {
+    fn writes(self) -> bool {
+        matches!(
+            self,
+            Op::Write
+                | Op::Update
+                | Op::SetIfNe
+                | Op::SwapIf { .. }
+                | Op::LockWrite { .. }
+                | Op::TryLockWrite { .. }
+        )
+    }
+}
+
+/// Checks that `val` is the initial value or one written by the schedule.
+fn check(schedule: &Schedule, val: &Payload) {
+    assert_eq!(*val, pattern(val[0]), "torn snapshot {val:x?}");
+    if val[0] != 0 {
+        let t = val[0] - 1;
+        let (p, t, i) = (
+            (t >> 32) as usize,
+            (t >> 16 & 0xffff) as usize,
+            (t & 0xffff) as usize,
+        );
+        let op = (p < schedule.phases() && t < schedule.threads())
+            .then(|| schedule.ops(p, t).get(i))
+            .flatten();
+        assert!(
+            op.is_some_and(|op| op.writes()),
+            "value {val:x?} was never written"
+        );
+    }
+}
+
+fn spin(yields: u8) {
+    for _ in 0..yields % 4 {
+        thread::yield_now();
+    }
+}
+
+/// Decodes `data` into a schedule and runs it, panicking if an invariant is
+/// violated.
+pub fn run(data: &[u8]) {
+    let Ok(schedule) = Schedule::arbitrary_take_rest(Unstructured::new(data)) else {
+        return;
+    };
+    run_schedule(&schedule);
+}
+
+pub fn run_schedule(schedule: &Schedule) {
+    let lock = SeqLock::new(pattern(0));
+    let writes = AtomicUsize::new(0);
+    let threads = schedule.threads();
+    let barrier = Barrier::new(threads);
+
+    thread::scope(|s| {
+        for t in 0..threads {
+            let (lock, writes, barrier) = (&lock, &writes, &barrier);
+            s.spawn(move || {
+                let mut last_seq = 0;
+                let mut observe = |val: Payload, seq: usize| {
+                    check(schedule, &val);
+                    assert_eq!(seq & 1, 0, "odd sequence number {seq}");
+                    assert!(seq >= last_seq, "sequence went from {last_seq} to {seq}");
+                    last_seq = seq;
+                };
+                for p in 0..schedule.phases() {
+                    for (i, &op) in schedule.ops(p, t).iter().enumerate() {
+                        let new = pattern(tag(p, t, i));
+                        match op {
+                            Op::Read => check(schedule, &lock.read()),
+                            Op::ReadVersioned => {
+                                let (val, seq) = lock.read_versioned();
+                                observe(val, seq);
+                            }
+                            Op::ReadGuard { refreshes } => {
+                                let mut snapshot = lock.read_guard();
+                                observe(*snapshot, snapshot.seq());
+                                for _ in 0..refreshes % 4 {
+                                    snapshot.refresh();
+                                    observe(*snapshot, snapshot.seq());
+                                }
+                            }
+                            Op::ReadWatchdog { max_spins } => {
+                                match lock.read_watchdog(u64::from(max_spins)) {
+                                    Ok(val) => check(schedule, &val),
+                                    Err(stuck) => assert_eq!(stuck.seq() & 1, 1),
+                                }
+                            }
+                            Op::ReadMaybeUninit => {
+                                check(schedule, &unsafe { lock.read_maybe_uninit().assume_init() })
+                            }
+                            Op::Write => {
+                                lock.write(new);
+                                writes.fetch_add(1, Ordering::Relaxed);
+                            }
+                            Op::Update => {
+                                let old = lock.update(|val| {
+                                    let old = *val;
+                                    *val = new;
+                                    old
+                                });
+                                check(schedule, &old);
+                                writes.fetch_add(1, Ordering::Relaxed);
+                            }
+                            Op::SetIfNe => {
+                                if lock.set_if_ne(new) {
+                                    writes.fetch_add(1, Ordering::Relaxed);
+                                }
+                            }
+                            Op::SwapIf { expect_tag } => {
+                                let result = lock.swap_if(|cur| cur[0] as u8 == expect_tag, new);
+                                match result {
+                                    Ok(old) => {
+                                        check(schedule, &old);
+                                        writes.fetch_add(1, Ordering::Relaxed);
+                                    }
+                                    Err(cur) => check(schedule, &cur),
+                                }
+                            }
+                            Op::LockWrite { yields } => {
+                                let mut guard = lock.lock_write();
+                                check(schedule, &guard);
+                                // Write the payload a word at a time, with
+                                // the guard held for a while in between.
+                                for (dst, src) in guard.iter_mut().zip(new) {
+                                    *dst = src;
+                                    spin(yields);
+                                }
+                                drop(guard);
+                                writes.fetch_add(1, Ordering::Relaxed);
+                            }
+                            Op::TryLockWrite { yields } => {
+                                if let Some(mut guard) = lock.try_lock_write() {
+                                    spin(yields);
+                                    *guard = new;
+                                    spin(yields);
+                                    drop(guard);
+                                    writes.fetch_add(1, Ordering::Relaxed);
+                                }
+                            }
+                            Op::Yield => thread::yield_now(),
+                        }
+                    }
+                    barrier.wait();
+                }
+            });
+        }
+    });
+
+    check(schedule, &lock.read());
+    assert_eq!(lock.current_seq(), 2 * writes.load(Ordering::Relaxed));
+}
@@ -0,0 +1,32 @@
+//! Replays the seed corpus and every crasher committed under `regressions/`.
+//!
+//! When the fuzzer finds a crash, copy its artifact to
+//! `regressions/schedule/` once it is fixed so that it keeps being tested.
+
+use std::fs;
+use std::path::Path;
+
+fn replay(dir: &str) -> usize {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join(dir);
+    let mut count = 0;
+    for entry in fs::read_dir(&dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.file_name().unwrap() == ".gitkeep" {
+            continue;
+        }
+        println!("replaying {}", path.display());
+        seqlock_fuzz::run(&fs::read(&path).unwrap());
+        count += 1;
+    }
+    count
+}
+
+#[test]
+fn seeds() {
+    assert!(replay("corpus/schedule") > 0);
+}
+
+#[test]
+fn regressions() {
+    replay("regressions/schedule");
+}
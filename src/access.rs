@@ -0,0 +1,289 @@
+//! Storage for the data protected by a `SeqLock`, and copies of it which may
+//! race with writers.
+//!
+//! Normally readers copy the data with a volatile read, which doesn't race
+//! with the writer's plain stores in practice but is a data race according
+//! to the Rust memory model. Under Miri and ThreadSanitizer, which detect
+//! such races, or with the `atomic-memcpy` feature, the data is instead
+//! copied with relaxed atomic accesses on both sides: a word at a time where
+//! the data is word aligned, and a byte at a time for the unaligned head and
+//! tail. Kani uses the same copy, so that its simulated writer can preempt a
+//! reader between chunks.
+//!
+//! Under loom and shuttle the data is stored in the model checker's atomics,
+//! one per word, so that it sees the racing accesses: loom can check that the
+//! fences order them correctly, and shuttle can preempt a copy half way
+//! through. loom's `UnsafeCell` can't be used for this, since it reports
+//! every read racing with a write as an error, and such races are exactly
+//! what a seqlock relies on.
+//!
+//! A value no larger than a word whose size equals its alignment, such as
+//! a primitive integer or a `#[repr(transparent)]` wrapper of one, is instead
+//! copied with a single relaxed atomic access of its size, both by readers
+//! and by writers and in every configuration: its storage is then aligned
+//! for the integer of that size, and a read of it can't be torn. A word is
+//! accessed as a pointer, so that a value which is a pointer keeps its
+//! provenance. Other small values, such as `[u8; 4]`, still go through the
+//! chunked copy.
+//!
+//! The atomic copies read every byte of `T` as an integer, so in those
+//! configurations `T` must not contain padding or other uninitialized bytes,
+//! and must not contain references or pointers since their provenance is
+//! lost.
+
+use crate::sync::order;
+#[cfg(any(loom, shuttle))]
+use crate::sync::{AtomicUsize, Ordering};
+use core::mem::MaybeUninit;
+#[cfg(all(
+    not(any(loom, shuttle)),
+    any(miri, tsan, kani, feature = "atomic-memcpy")
+))]
+use core::sync::atomic::AtomicUsize;
+#[cfg(not(any(loom, shuttle)))]
+use core::sync::atomic::{AtomicPtr, AtomicU16, AtomicU32, AtomicU8, Ordering};
+
+// Transparent so that borrowed storage can be viewed as a `DataCell`, see
+// `from_cell`.
+#[cfg(not(any(loom, shuttle)))]
+#[repr(transparent)]
+pub(crate) struct DataCell<T> {
+    value: core::cell::UnsafeCell<T>,
+}
+
+#[cfg(not(any(loom, shuttle)))]
+impl<T> DataCell<T> {
+    #[inline]
+    pub(crate) const fn new(val: T) -> DataCell<T> {
+        DataCell {
+            value: core::cell::UnsafeCell::new(val),
+        }
+    }
+
+    /// Views the contents of `cell` as a `DataCell`.
+    #[inline]
+    pub(crate) fn from_cell(cell: &core::cell::UnsafeCell<T>) -> &DataCell<T> {
+        unsafe { &*(cell as *const core::cell::UnsafeCell<T>).cast::<DataCell<T>>() }
+    }
+
+    #[inline]
+    pub(crate) const fn as_ptr(&self) -> *mut T {
+        self.value.get()
+    }
+
+    /// Copies the data out, possibly racing with a writer.
+    #[inline]
+    pub(crate) unsafe fn load(&self) -> MaybeUninit<T> {
+        #[cfg(not(any(miri, tsan, kani, feature = "atomic-memcpy")))]
+        if !is_single_access::<T>() {
+            return core::ptr::read_volatile(self.as_ptr() as *const MaybeUninit<T>);
+        }
+
+        let mut dst = MaybeUninit::<T>::uninit();
+        self.load_into(&mut dst);
+        dst
+    }
+
+    /// Like `load`, but copies the data directly into `dst` instead of
+    /// returning it, so that no temporary copy is made on the stack.
+    #[inline]
+    pub(crate) unsafe fn load_into(&self, dst: &mut MaybeUninit<T>) {
+        let src = self.as_ptr().cast::<u8>();
+        let dst_bytes = dst.as_mut_ptr() as *mut u8;
+
+        if is_single_access::<T>() {
+            crate::sync::preempt();
+            let ordering = order(Ordering::Relaxed);
+            match core::mem::size_of::<T>() {
+                WORD => dst_bytes
+                    .cast::<*mut u8>()
+                    .write(AtomicPtr::from_ptr(src.cast()).load(ordering)),
+                1 => *dst_bytes = AtomicU8::from_ptr(src).load(ordering),
+                2 => dst_bytes
+                    .cast::<u16>()
+                    .write(AtomicU16::from_ptr(src.cast()).load(ordering)),
+                _ => dst_bytes
+                    .cast::<u32>()
+                    .write(AtomicU32::from_ptr(src.cast()).load(ordering)),
+            }
+            return;
+        }
+
+        // A single volatile read of the whole value would be made into a
+        // temporary, so copy a chunk at a time instead.
+        #[cfg(not(any(miri, tsan, kani, feature = "atomic-memcpy")))]
+        for_each_chunk(
+            src,
+            core::mem::size_of::<T>(),
+            |i| *dst_bytes.add(i) = core::ptr::read_volatile(src.add(i)),
+            |i| {
+                let word = core::ptr::read_volatile(src.add(i).cast::<usize>());
+                dst_bytes.add(i).cast::<usize>().write_unaligned(word);
+            },
+        );
+
+        #[cfg(any(miri, tsan, kani, feature = "atomic-memcpy"))]
+        for_each_chunk(
+            src,
+            core::mem::size_of::<T>(),
+            |i| {
+                crate::sync::preempt();
+                *dst_bytes.add(i) = AtomicU8::from_ptr(src.add(i)).load(order(Ordering::Relaxed));
+            },
+            |i| {
+                crate::sync::preempt();
+                let word = AtomicUsize::from_ptr(src.add(i).cast()).load(order(Ordering::Relaxed));
+                dst_bytes.add(i).cast::<usize>().write_unaligned(word);
+            },
+        );
+    }
+
+    /// Copies `src` into the data, possibly racing with readers. The caller
+    /// must have write access.
+    #[inline]
+    pub(crate) unsafe fn store(&self, src: *const T) {
+        let dst = self.as_ptr();
+
+        if is_single_access::<T>() {
+            let ordering = order(Ordering::Relaxed);
+            let dst = dst.cast::<u8>();
+            match core::mem::size_of::<T>() {
+                WORD => AtomicPtr::from_ptr(dst.cast()).store(*src.cast::<*mut u8>(), ordering),
+                1 => AtomicU8::from_ptr(dst).store(*src.cast::<u8>(), ordering),
+                2 => AtomicU16::from_ptr(dst.cast()).store(*src.cast::<u16>(), ordering),
+                _ => AtomicU32::from_ptr(dst.cast()).store(*src.cast::<u32>(), ordering),
+            }
+            return;
+        }
+
+        #[cfg(not(any(miri, tsan, kani, feature = "atomic-memcpy")))]
+        {
+            core::ptr::copy_nonoverlapping(src, dst, 1);
+        }
+
+        #[cfg(any(miri, tsan, kani, feature = "atomic-memcpy"))]
+        {
+            let src_bytes = src as *const u8;
+            let dst = dst.cast::<u8>();
+            for_each_chunk(
+                dst,
+                core::mem::size_of::<T>(),
+                |i| {
+                    AtomicU8::from_ptr(dst.add(i))
+                        .store(*src_bytes.add(i), order(Ordering::Relaxed))
+                },
+                |i| {
+                    let word = src_bytes.add(i).cast::<usize>().read_unaligned();
+                    AtomicUsize::from_ptr(dst.add(i).cast()).store(word, order(Ordering::Relaxed));
+                },
+            );
+        }
+    }
+
+    #[inline]
+    pub(crate) fn into_inner(self) -> T {
+        self.value.into_inner()
+    }
+
+    #[inline]
+    pub(crate) fn get_mut(&mut self) -> &mut T {
+        self.value.get_mut()
+    }
+}
+
+// The size of a word, which is copied as a pointer.
+#[cfg(not(any(loom, shuttle)))]
+const WORD: usize = core::mem::size_of::<usize>();
+
+/// Returns whether `T` is copied with a single access of its size, which
+/// is the case if it is no larger than a word and as large as its
+/// alignment.
+#[cfg(not(any(loom, shuttle)))]
+#[inline]
+const fn is_single_access<T>() -> bool {
+    let size = core::mem::size_of::<T>();
+    size == core::mem::align_of::<T>() && size <= core::mem::size_of::<usize>()
+}
+
+/// Splits the `size` bytes at `ptr` into chunks for a copy, calling
+/// `byte` with the offset of each byte before the first word boundary and
+/// after the last one, and `word` with the offset of each word in between.
+///
+/// Readers and writers split the data the same way, so every pair of racing
+/// accesses has the same size.
+#[cfg(not(any(loom, shuttle)))]
+#[inline]
+fn for_each_chunk(
+    ptr: *const u8,
+    size: usize,
+    mut byte: impl FnMut(usize),
+    mut word: impl FnMut(usize),
+) {
+    const WORD: usize = core::mem::size_of::<usize>();
+    let head = ptr.align_offset(WORD).min(size);
+    let words = (size - head) / WORD;
+    for i in 0..head {
+        byte(i);
+    }
+    for i in 0..words {
+        word(head + i * WORD);
+    }
+    for i in head + words * WORD..size {
+        byte(i);
+    }
+}
+
+#[cfg(any(loom, shuttle))]
+pub(crate) struct DataCell<T> {
+    words: alloc::boxed::Box<[AtomicUsize]>,
+    _marker: core::marker::PhantomData<T>,
+}
+
+#[cfg(any(loom, shuttle))]
+impl<T> DataCell<T> {
+    const WORD: usize = core::mem::size_of::<usize>();
+
+    pub(crate) fn new(val: T) -> DataCell<T> {
+        let words = core::mem::size_of::<T>().div_ceil(Self::WORD);
+        let cell = DataCell {
+            words: (0..words).map(|_| AtomicUsize::new(0)).collect(),
+            _marker: core::marker::PhantomData,
+        };
+        unsafe { cell.store(&val) };
+        core::mem::forget(val);
+        cell
+    }
+
+    /// Copies the data out, possibly racing with a writer.
+    pub(crate) unsafe fn load(&self) -> MaybeUninit<T> {
+        let mut dst = MaybeUninit::<T>::uninit();
+        self.load_into(&mut dst);
+        dst
+    }
+
+    /// Like `load`, but copies the data directly into `dst`.
+    pub(crate) unsafe fn load_into(&self, dst: &mut MaybeUninit<T>) {
+        let dst_bytes = dst.as_mut_ptr() as *mut u8;
+        for (i, word) in self.words.iter().enumerate() {
+            let bytes = word.load(order(Ordering::Relaxed)).to_ne_bytes();
+            let len = Self::WORD.min(core::mem::size_of::<T>() - i * Self::WORD);
+            core::ptr::copy_nonoverlapping(bytes.as_ptr(), dst_bytes.add(i * Self::WORD), len);
+        }
+    }
+
+    /// Copies `src` into the data, possibly racing with readers. The caller
+    /// must have write access.
+    pub(crate) unsafe fn store(&self, src: *const T) {
+        let src_bytes = src as *const u8;
+        for (i, word) in self.words.iter().enumerate() {
+            let mut bytes = [0; core::mem::size_of::<usize>()];
+            let len = Self::WORD.min(core::mem::size_of::<T>() - i * Self::WORD);
+            core::ptr::copy_nonoverlapping(src_bytes.add(i * Self::WORD), bytes.as_mut_ptr(), len);
+            word.store(usize::from_ne_bytes(bytes), order(Ordering::Relaxed));
+        }
+    }
+
+    pub(crate) fn into_inner(self) -> T {
+        unsafe { self.load().assume_init() }
+    }
+}
@@ -0,0 +1,170 @@
+//! A `SeqLock` whose writers wait asynchronously, for use with
+//! [embassy](https://embassy.dev).
+//!
+//! Readers are unchanged: they never block, so they can be used from
+//! interrupt handlers and from any task. Writers are serialized by an
+//! [`embassy_sync::mutex::Mutex`], so a task waiting for write access yields
+//! to the executor instead of blocking it. The mutex is generic over
+//! embassy's [`RawMutex`], which lets you pick between e.g.
+//! `CriticalSectionRawMutex` (if writers may run in different executors or
+//! interrupt priorities) and `NoopRawMutex` (if all writers run in the same
+//! executor).
+//!
+//! # Examples
+//!
+//! ```
+//! use embassy_sync::blocking_mutex::raw::NoopRawMutex;
+//! use seqlock::embassy::SeqLock;
+//!
+//! let lock = SeqLock::<NoopRawMutex, _>::new(5);
+//!
+//! # futures::executor::block_on(async {
+//! {
+//!     let mut w = lock.lock_write_async().await;
+//!     *w += 1;
+//! }
+//! assert_eq!(lock.read(), 6);
+//! # });
+//! ```
+
+use crate::location::Caller;
+use core::fmt;
+use core::future::Future;
+use core::ops::{Deref, DerefMut};
+use embassy_sync::blocking_mutex::raw::RawMutex;
+use embassy_sync::mutex::{Mutex, MutexGuard};
+
+/// A sequential lock whose writers are serialized by an embassy mutex.
+pub struct SeqLock<M: RawMutex, T> {
+    // Only the sequence counter and data of the inner lock are used, its
+    // writer mutex is never locked.
+    inner: crate::SeqLock<T>,
+    mutex: Mutex<M, ()>,
+}
+
+/// RAII structure used to release the exclusive write access of an embassy
+/// `SeqLock` when dropped.
+pub struct SeqLockAsyncGuard<'a, M: RawMutex, T> {
+    // The write must end before the mutex is unlocked.
+    write: crate::WriteScope<'a, T>,
+    _guard: MutexGuard<'a, M, ()>,
+}
+
+impl<M: RawMutex, T: Copy> SeqLock<M, T> {
+    /// Creates a new SeqLock with the given initial value.
+    #[inline]
+    pub const fn new(val: T) -> SeqLock<M, T> {
+        SeqLock {
+            inner: crate::SeqLock::new(val),
+            mutex: Mutex::new(()),
+        }
+    }
+
+    /// Reads the value protected by the `SeqLock`.
+    ///
+    /// This behaves exactly like [`crate::SeqLock::read`] and never waits
+    /// for the writer mutex.
+    #[inline]
+    pub fn read(&self) -> T {
+        self.inner.read()
+    }
+
+    /// Reads the value protected by the `SeqLock` along with the sequence
+    /// number it was read at.
+    ///
+    /// See [`crate::SeqLock::read_versioned`].
+    #[inline]
+    pub fn read_versioned(&self) -> (T, usize) {
+        self.inner.read_versioned()
+    }
+
+    #[inline]
+    fn lock_guard<'a>(
+        &'a self,
+        guard: MutexGuard<'a, M, ()>,
+        caller: Caller,
+    ) -> SeqLockAsyncGuard<'a, M, T> {
+        SeqLockAsyncGuard {
+            write: unsafe { crate::WriteScope::begin(&self.inner, caller) },
+            _guard: guard,
+        }
+    }
+
+    /// Locks this `SeqLock` with exclusive write access, waiting
+    /// asynchronously until it can be acquired.
+    ///
+    /// Dropping the returned future before it completes leaves the lock
+    /// untouched. Readers are only affected once the lock is acquired.
+    ///
+    /// Returns an RAII guard which will drop the write access of this `SeqLock`
+    /// when dropped.
+    #[inline]
+    #[cfg_attr(feature = "write-location", track_caller)]
+    pub fn lock_write_async(&self) -> impl Future<Output = SeqLockAsyncGuard<'_, M, T>> {
+        let caller = Caller::get();
+        async move {
+            let guard = self.mutex.lock().await;
+            self.lock_guard(guard, caller)
+        }
+    }
+
+    /// Attempts to lock this `SeqLock` with exclusive write access.
+    ///
+    /// If the lock could not be acquired at this time, then `None` is returned.
+    /// Otherwise, an RAII guard is returned which will release the lock when
+    /// it is dropped.
+    ///
+    /// This function does not block.
+    #[inline]
+    #[cfg_attr(feature = "write-location", track_caller)]
+    pub fn try_lock_write(&self) -> Option<SeqLockAsyncGuard<'_, M, T>> {
+        let caller = Caller::get();
+        self.mutex
+            .try_lock()
+            .ok()
+            .map(|g| self.lock_guard(g, caller))
+    }
+
+    /// Consumes this `SeqLock`, returning the underlying data.
+    #[inline]
+    pub fn into_inner(self) -> T {
+        self.inner.into_inner()
+    }
+
+    /// Returns a mutable reference to the underlying data.
+    ///
+    /// Since this call borrows the `SeqLock` mutably, no actual locking needs
+    /// to take place---the mutable borrow statically guarantees no locks exist.
+    #[inline]
+    pub fn get_mut(&mut self) -> &mut T {
+        self.inner.get_mut()
+    }
+}
+
+impl<M: RawMutex, T: Copy + Default> Default for SeqLock<M, T> {
+    #[inline]
+    fn default() -> SeqLock<M, T> {
+        SeqLock::new(Default::default())
+    }
+}
+
+impl<M: RawMutex, T: Copy + fmt::Debug> fmt::Debug for SeqLock<M, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "SeqLock {{ data: {:?} }}", &self.read())
+    }
+}
+
+impl<'a, M: RawMutex, T: Copy + 'a> Deref for SeqLockAsyncGuard<'a, M, T> {
+    type Target = T;
+    #[inline]
+    fn deref(&self) -> &T {
+        self.write.get()
+    }
+}
+
+impl<'a, M: RawMutex, T: Copy + 'a> DerefMut for SeqLockAsyncGuard<'a, M, T> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut T {
+        self.write.get_mut()
+    }
+}
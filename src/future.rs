@@ -0,0 +1,217 @@
+//! Futures and streams for observing writes to a `SeqLock`, and a sink for
+//! publishing to one.
+
+#[cfg(not(feature = "critical-section"))]
+use crate::sync::{WriterGuard, WriterLock};
+use crate::SeqLock;
+#[cfg(not(feature = "critical-section"))]
+use alloc::boxed::Box;
+#[cfg(not(feature = "critical-section"))]
+use core::convert::Infallible;
+use core::fmt;
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+use futures_core::Stream;
+#[cfg(not(feature = "critical-section"))]
+use futures_sink::Sink;
+
+/// A future which resolves once a `SeqLock` has been written.
+///
+/// This future is created by [`SeqLock::changed`].
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct Changed<'a, T> {
+    seqlock: &'a SeqLock<T>,
+    seen_seq: usize,
+    key: usize,
+}
+
+impl<'a, T> Changed<'a, T> {
+    #[inline]
+    pub(crate) fn new(seqlock: &'a SeqLock<T>, seen_seq: usize, key: usize) -> Changed<'a, T> {
+        Changed {
+            seqlock,
+            seen_seq,
+            key,
+        }
+    }
+}
+
+impl<T: Copy> Future for Changed<'_, T> {
+    type Output = (T, usize);
+
+    #[inline]
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<(T, usize)> {
+        self.seqlock
+            .poll_changed_keyed(self.seen_seq, self.key, cx.waker())
+    }
+}
+
+// A future dropped before it completes, e.g. by a timeout, must not leave
+// its waker behind until the next write.
+impl<T> Drop for Changed<'_, T> {
+    #[inline]
+    fn drop(&mut self) {
+        self.seqlock.wakers.unregister(self.key);
+    }
+}
+
+impl<T> fmt::Debug for Changed<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Changed")
+            .field("seen_seq", &self.seen_seq)
+            .finish_non_exhaustive()
+    }
+}
+
+/// A stream of the values published to a `SeqLock`.
+///
+/// This stream is created by [`SeqLock::stream`]. It conflates writes, so a
+/// slow consumer only sees the latest value.
+#[must_use = "streams do nothing unless polled"]
+pub struct SnapshotStream<'a, T> {
+    seqlock: &'a SeqLock<T>,
+    last_seq: Option<usize>,
+    key: usize,
+}
+
+impl<'a, T> SnapshotStream<'a, T> {
+    #[inline]
+    pub(crate) fn new(seqlock: &'a SeqLock<T>, key: usize) -> SnapshotStream<'a, T> {
+        SnapshotStream {
+            seqlock,
+            last_seq: None,
+            key,
+        }
+    }
+}
+
+impl<T: Copy> Stream for SnapshotStream<'_, T> {
+    type Item = T;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        let (val, seq) = match self.last_seq {
+            None => self.seqlock.read_versioned(),
+            Some(last_seq) => {
+                match self
+                    .seqlock
+                    .poll_changed_keyed(last_seq, self.key, cx.waker())
+                {
+                    Poll::Ready(res) => res,
+                    Poll::Pending => return Poll::Pending,
+                }
+            }
+        };
+        self.last_seq = Some(seq);
+        Poll::Ready(Some(val))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (usize::MAX, None)
+    }
+}
+
+impl<T> Drop for SnapshotStream<'_, T> {
+    #[inline]
+    fn drop(&mut self) {
+        self.seqlock.wakers.unregister(self.key);
+    }
+}
+
+impl<T> fmt::Debug for SnapshotStream<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SnapshotStream")
+            .field("last_seq", &self.last_seq)
+            .finish_non_exhaustive()
+    }
+}
+
+/// A sink which publishes each item it receives to a `SeqLock`.
+///
+/// This sink is created by [`SeqLock::sink`]. Every item overwrites the
+/// previous one, so readers which don't read often enough only see the
+/// latest item.
+#[cfg(not(feature = "critical-section"))]
+#[must_use = "sinks do nothing unless polled"]
+pub struct SeqLockSink<'a, T> {
+    seqlock: &'a SeqLock<T>,
+    lock: Option<Pin<Box<WriterLock<'a, ()>>>>,
+    guard: Option<WriterGuard<'a, ()>>,
+}
+
+#[cfg(not(feature = "critical-section"))]
+impl<'a, T> SeqLockSink<'a, T> {
+    #[inline]
+    pub(crate) fn new(seqlock: &'a SeqLock<T>) -> SeqLockSink<'a, T> {
+        SeqLockSink {
+            seqlock,
+            lock: None,
+            guard: None,
+        }
+    }
+}
+
+#[cfg(not(feature = "critical-section"))]
+impl<'a, T: Copy> Sink<T> for SeqLockSink<'a, T> {
+    type Error = Infallible;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Infallible>> {
+        let this = self.get_mut();
+        if this.guard.is_some() {
+            return Poll::Ready(Ok(()));
+        }
+
+        // Only allocate a lock future if the mutex is contended.
+        if this.lock.is_none() {
+            if let Some(guard) = this.seqlock.mutex.try_lock() {
+                this.guard = Some(guard);
+                return Poll::Ready(Ok(()));
+            }
+            #[cfg(feature = "metrics")]
+            this.seqlock.counters.record_writer_wait();
+            this.lock = Some(Box::pin(this.seqlock.mutex.lock()));
+        }
+
+        let guard = match this.lock.as_mut().unwrap().as_mut().poll(cx) {
+            Poll::Ready(guard) => guard,
+            Poll::Pending => return Poll::Pending,
+        };
+        this.lock = None;
+        this.guard = Some(guard);
+        Poll::Ready(Ok(()))
+    }
+
+    fn start_send(self: Pin<&mut Self>, item: T) -> Result<(), Infallible> {
+        let this = self.get_mut();
+        let guard = this
+            .guard
+            .take()
+            .expect("poll_ready must be called before start_send");
+        *this
+            .seqlock
+            .lock_guard(guard, crate::location::Caller::get()) = item;
+        Ok(())
+    }
+
+    #[inline]
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<(), Infallible>> {
+        Poll::Ready(Ok(()))
+    }
+
+    #[inline]
+    fn poll_close(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<(), Infallible>> {
+        // Release the writer mutex if `poll_ready` reserved it.
+        self.get_mut().guard = None;
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[cfg(not(feature = "critical-section"))]
+impl<T> fmt::Debug for SeqLockSink<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SeqLockSink")
+            .field("ready", &self.guard.is_some())
+            .finish_non_exhaustive()
+    }
+}
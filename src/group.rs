@@ -0,0 +1,238 @@
+//! Several sequence-locked values sharing one writer mutex.
+
+use crate::access::DataCell;
+use crate::sync::AtomicUsize;
+#[cfg(not(feature = "critical-section"))]
+use crate::sync::{self, WriterGuard, WriterMutex};
+use core::fmt;
+use core::mem::MaybeUninit;
+#[cfg(not(feature = "critical-section"))]
+use core::ops::{Deref, DerefMut};
+
+/// A fixed number of values which are always written together, under a
+/// single writer mutex.
+///
+/// Each member has its own sequence number and is read on its own, like a
+/// [`SeqLock`], but writers only ever write all of them at once. This saves
+/// the memory of a mutex per member, and makes it explicit that the members
+/// are updated as one. During a write every member is marked as being
+/// written before any of them is modified, so a reader of a member sees
+/// either its value from before the write or from after it.
+///
+/// Readers of different members are not synchronized with each other, so
+/// reading two members may still return them from different writes.
+///
+/// [`SeqLock`]: crate::SeqLock
+///
+/// # Examples
+///
+/// ```
+/// use seqlock::SeqLockGroup;
+///
+/// let group = SeqLockGroup::new([0u64; 3]);
+/// group.update_all(|values| {
+///     for (i, val) in values.iter_mut().enumerate() {
+///         *val = i as u64 + 1;
+///     }
+/// });
+/// assert_eq!(group.members()[1].read(), 2);
+/// assert_eq!(group.members()[2].read_versioned(), (3, 2));
+/// ```
+pub struct SeqLockGroup<T, const N: usize> {
+    #[cfg(not(feature = "critical-section"))]
+    mutex: WriterMutex<()>,
+    members: [SeqLockMember<T>; N],
+}
+
+/// A value in a [`SeqLockGroup`], which is read like a `SeqLock`.
+pub struct SeqLockMember<T> {
+    seq: AtomicUsize,
+    data: DataCell<T>,
+}
+
+unsafe impl<T: Send, const N: usize> Send for SeqLockGroup<T, N> {}
+unsafe impl<T: Send, const N: usize> Sync for SeqLockGroup<T, N> {}
+unsafe impl<T: Send> Send for SeqLockMember<T> {}
+unsafe impl<T: Send> Sync for SeqLockMember<T> {}
+
+/// RAII structure giving write access to every member of a `SeqLockGroup`,
+/// which publishes their new values when dropped.
+///
+/// The guard dereferences to a copy of the values, so readers are only
+/// disturbed once it is dropped.
+#[cfg(not(feature = "critical-section"))]
+pub struct SeqLockGroupGuard<'a, T: Copy, const N: usize> {
+    group: &'a SeqLockGroup<T, N>,
+    values: [T; N],
+    _guard: WriterGuard<'a, ()>,
+}
+
+impl<T: Copy, const N: usize> SeqLockGroup<T, N> {
+    const_fn! {
+        /// Creates a new group holding `values`, each at sequence number 0.
+        #[inline]
+        pub fn new(values: [T; N]) -> SeqLockGroup<T, N> {
+            // `array::map` can't be called in a const fn.
+            let mut members = [const { MaybeUninit::<SeqLockMember<T>>::uninit() }; N];
+            let mut i = 0;
+            while i < N {
+                members[i] = MaybeUninit::new(SeqLockMember {
+                    seq: AtomicUsize::new(0),
+                    data: DataCell::new(values[i]),
+                });
+                i += 1;
+            }
+            SeqLockGroup {
+                #[cfg(not(feature = "critical-section"))]
+                mutex: WriterMutex::new(()),
+                // Every member was initialized above.
+                members: unsafe {
+                    (&members as *const [MaybeUninit<SeqLockMember<T>>; N])
+                        .cast::<[SeqLockMember<T>; N]>()
+                        .read()
+                },
+            }
+        }
+    }
+
+    /// Returns the members of the group, for reading.
+    #[inline]
+    pub fn members(&self) -> &[SeqLockMember<T>; N] {
+        &self.members
+    }
+
+    /// Modifies the values of every member, returning the result of `f`.
+    ///
+    /// `f` runs on a copy of the values with writers excluded, and readers
+    /// are only disturbed once it returns. If `f` panics nothing is written.
+    #[inline]
+    pub fn update_all<R>(&self, f: impl FnOnce(&mut [T; N]) -> R) -> R {
+        let exclusive = || {
+            let mut values = self.copy_all();
+            let res = f(&mut values);
+            self.publish(&values);
+            res
+        };
+
+        #[cfg(not(feature = "critical-section"))]
+        {
+            let _guard = sync::lock_writer(&self.mutex);
+            exclusive()
+        }
+
+        #[cfg(feature = "critical-section")]
+        critical_section::with(|_| exclusive())
+    }
+
+    /// Locks the group with exclusive write access to every member, blocking
+    /// the current thread until it can be acquired.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use seqlock::SeqLockGroup;
+    ///
+    /// let group = SeqLockGroup::new([1, 2]);
+    /// let mut guard = group.lock_write_all();
+    /// guard.swap(0, 1);
+    /// // Nothing is published until the guard is dropped.
+    /// assert_eq!(group.members()[0].read(), 1);
+    /// drop(guard);
+    /// assert_eq!(group.members().each_ref().map(|m| m.read()), [2, 1]);
+    /// ```
+    #[cfg(not(feature = "critical-section"))]
+    #[inline]
+    pub fn lock_write_all(&self) -> SeqLockGroupGuard<'_, T, N> {
+        let guard = sync::lock_writer(&self.mutex);
+        SeqLockGroupGuard {
+            group: self,
+            values: self.copy_all(),
+            _guard: guard,
+        }
+    }
+
+    // Copies the values out. Other writers must be excluded.
+    #[inline]
+    fn copy_all(&self) -> [T; N] {
+        self.members
+            .each_ref()
+            .map(|member| unsafe { member.data.load().assume_init() })
+    }
+
+    // Writes `values` into the members. Other writers must be excluded.
+    #[inline]
+    fn publish(&self, values: &[T; N]) {
+        // Every member is marked as being written before any is modified.
+        let seqs = self
+            .members
+            .each_ref()
+            .map(|member| crate::begin_write(&member.seq));
+        for (member, val) in self.members.iter().zip(values) {
+            unsafe { member.data.store(val) };
+        }
+        for (member, seq) in self.members.iter().zip(seqs) {
+            crate::end_write(&member.seq, seq);
+        }
+    }
+}
+
+impl<T: Copy> SeqLockMember<T> {
+    /// Reads the value of the member.
+    ///
+    /// If the group is currently being written then the calling thread will
+    /// wait until the write is complete.
+    #[inline]
+    pub fn read(&self) -> T {
+        self.read_versioned().0
+    }
+
+    /// Reads the value along with the sequence number it was read at.
+    ///
+    /// See `SeqLock::read_versioned`. Every write to the group increments
+    /// the sequence number of every member.
+    #[inline]
+    pub fn read_versioned(&self) -> (T, usize) {
+        crate::read_versioned(&self.seq, &self.data)
+    }
+}
+
+#[cfg(not(feature = "critical-section"))]
+impl<T: Copy, const N: usize> Deref for SeqLockGroupGuard<'_, T, N> {
+    type Target = [T; N];
+
+    #[inline]
+    fn deref(&self) -> &[T; N] {
+        &self.values
+    }
+}
+
+#[cfg(not(feature = "critical-section"))]
+impl<T: Copy, const N: usize> DerefMut for SeqLockGroupGuard<'_, T, N> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut [T; N] {
+        &mut self.values
+    }
+}
+
+#[cfg(not(feature = "critical-section"))]
+impl<T: Copy, const N: usize> Drop for SeqLockGroupGuard<'_, T, N> {
+    #[inline]
+    fn drop(&mut self) {
+        // The mutex is only released once this returns.
+        self.group.publish(&self.values);
+    }
+}
+
+impl<T: Copy + fmt::Debug, const N: usize> fmt::Debug for SeqLockGroup<T, N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SeqLockGroup")
+            .field("members", &self.members)
+            .finish()
+    }
+}
+
+impl<T: Copy + fmt::Debug> fmt::Debug for SeqLockMember<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "SeqLockMember {{ data: {:?} }}", &self.read())
+    }
+}
@@ -0,0 +1,101 @@
+//! Callbacks invoked on every write, enabled by the `write-hooks` feature.
+
+use crate::access::DataCell;
+use crate::sync::{order, AtomicBool, Mutex, Ordering};
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+
+/// A callback registered with `SeqLock::add_write_hook`.
+pub(crate) type WriteHook<T> = Box<dyn Fn(&T) + Send + Sync>;
+
+/// A token identifying a hook registered with `SeqLock::add_write_hook`.
+///
+/// Pass it to `SeqLock::remove_write_hook` to unregister the hook.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct WriteHookId(usize);
+
+struct HookList<T> {
+    next_id: usize,
+    hooks: Vec<(usize, WriteHook<T>)>,
+}
+
+pub(crate) struct WriteHooks<T> {
+    // Fast-path flag checked by writers so that publishing a value with no
+    // registered hooks only costs a single relaxed load.
+    has_hooks: AtomicBool,
+    list: Mutex<HookList<T>>,
+}
+
+impl<T> WriteHooks<T> {
+    const_fn! {
+        #[inline]
+        pub(crate) fn new() -> WriteHooks<T> {
+            WriteHooks {
+                has_hooks: AtomicBool::new(false),
+                list: Mutex::new(HookList {
+                    next_id: 0,
+                    hooks: Vec::new(),
+                }),
+            }
+        }
+    }
+
+    pub(crate) fn add(&self, hook: WriteHook<T>) -> WriteHookId {
+        with_list(&self.list, |list| {
+            let id = list.next_id;
+            list.next_id += 1;
+            list.hooks.push((id, hook));
+            self.has_hooks.store(true, order(Ordering::Relaxed));
+            WriteHookId(id)
+        })
+    }
+
+    pub(crate) fn remove(&self, id: WriteHookId) -> bool {
+        // Drop the hook outside the lock in case its destructor touches the
+        // `SeqLock`.
+        let hook = with_list(&self.list, |list| {
+            let index = list.hooks.iter().position(|(i, _)| *i == id.0)?;
+            let hook = list.hooks.remove(index);
+            if list.hooks.is_empty() {
+                self.has_hooks.store(false, order(Ordering::Relaxed));
+            }
+            Some(hook)
+        });
+        hook.is_some()
+    }
+
+    /// Calls every registered hook with the value which was just published.
+    ///
+    /// Must be called while holding write access to the `SeqLock`.
+    #[inline]
+    pub(crate) fn call(&self, data: &DataCell<T>) {
+        if self.has_hooks.load(order(Ordering::Relaxed)) {
+            self.call_slow(data);
+        }
+    }
+
+    #[cold]
+    fn call_slow(&self, data: &DataCell<T>) {
+        // Other writers are excluded, so this copy is consistent.
+        let val = unsafe { data.load() };
+        let val = unsafe { val.assume_init_ref() };
+
+        // With the `critical-section` feature, writers are already inside a
+        // critical section here.
+        for (_, hook) in &self.list.lock().hooks {
+            hook(val);
+        }
+    }
+}
+
+// With the `critical-section` feature hooks may be called from an interrupt
+// handler, so the list must only be locked inside a critical section to avoid
+// deadlocking against a registration on the interrupted thread.
+#[inline]
+fn with_list<T, R>(list: &Mutex<HookList<T>>, f: impl FnOnce(&mut HookList<T>) -> R) -> R {
+    #[cfg(feature = "critical-section")]
+    return critical_section::with(|_| f(&mut list.lock()));
+
+    #[cfg(not(feature = "critical-section"))]
+    f(&mut list.lock())
+}
@@ -8,7 +8,8 @@
 //!
 //! The only downside of `SeqLock` is that it only works on types that are
 //! `Copy`. This means that it is unsuitable for types that contains pointers
-//! to owned data.
+//! to owned data. Types without such pointers which can't be `Copy` for
+//! other reasons can opt in by implementing the unsafe `SeqlockSafe` trait.
 //!
 //! You should instead use a `RwLock` if you need
 //! a reader-writer lock for types that are not `Copy`.
@@ -31,6 +32,318 @@
 //! counter has not changed while it was reading the data, it can safely return
 //! that data to the caller since it is known to be in a consistent state.
 //!
+//! The data read in step 2 is kept in a `MaybeUninit<T>` and is only turned
+//! into a `T` once step 4 has confirmed that it wasn't torn. A torn copy is
+//! simply discarded, so types with invalid bit patterns, such as enums, `bool`
+//! or `NonZeroU32`, can be stored in a `SeqLock` without any extra validation:
+//!
+//! ```
+//! use seqlock::SeqLock;
+//! use std::num::NonZeroU32;
+//! use std::thread;
+//!
+//! #[derive(Clone, Copy)]
+//! enum State {
+//!     Idle,
+//!     Busy(NonZeroU32),
+//! }
+//!
+//! let lock = SeqLock::new([State::Idle; 4]);
+//! thread::scope(|s| {
+//!     s.spawn(|| {
+//!         for i in 1..=100 {
+//!             let state = match NonZeroU32::new(i % 3) {
+//!                 Some(n) => State::Busy(n),
+//!                 None => State::Idle,
+//!             };
+//!             lock.write([state; 4]);
+//!         }
+//!     });
+//!     for _ in 0..100 {
+//!         // Every value returned is a valid `State`.
+//!         if let State::Busy(n) = lock.read()[0] {
+//!             assert!(n.get() < 3);
+//!         }
+//!     }
+//! });
+//! ```
+//!
+//! Padding bytes in `T` are copied as uninitialized memory, as they would be
+//! by a plain copy. The exception is Miri, ThreadSanitizer, loom and shuttle,
+//! and the `atomic-memcpy` feature, which copy the data with integer atomics
+//! and so require `T` to have no padding and no references or pointers.
+//! Values no larger than a word whose size equals their alignment, such as
+//! the integer types, are copied with a single relaxed atomic access of
+//! their size in every configuration, so a read can't be torn to begin with. Types with a smaller alignment, such as
+//! `[u8; 4]`, and larger types are copied in pieces.
+//!
+//! `#[repr(packed)]` types need no special treatment: the data is always
+//! copied as a whole `T`, from storage aligned for `T`, and never through
+//! references to its possibly misaligned fields.
+//!
+//! # Memory ordering
+//!
+//! The orderings pair up as follows:
+//!
+//! - The writer's final store of the even sequence number is a `Release`
+//!   store, and the reader's first load of it is an `Acquire` load. A reader
+//!   which sees that sequence number therefore also sees everything that
+//!   write, and every earlier one, stored.
+//! - After making the sequence number odd, the writer issues a `Release`
+//!   fence before modifying the data. Before its second load of the sequence
+//!   number, the reader issues an `Acquire` fence. If the copy saw any of the
+//!   writer's stores, the second load sees the odd sequence number, or a later
+//!   one, and the read is retried.
+//!
+//! Neither load can be relaxed on its own: see the comments in `try_read` and
+//! the `read_protocol` loom tests for the interleavings this rules out. The
+//! data itself is copied with relaxed or volatile accesses, since the
+//! sequence number alone decides whether a copy is used. Besides the loom
+//! models, the `ordering` test checks two-word reads against a writer on real
+//! threads, which catches regressions on weakly-ordered CPUs without a
+//! special build.
+//!
+//! The copy can't be a plain `ptr::read`, even for plain-old-data types
+//! whose callers would accept a torn value. A non-atomic read which races
+//! with a write is undefined behavior whatever the fences around it, and the
+//! compiler may assume it doesn't happen, e.g. by reading the data again
+//! after the sequence number was checked. The volatile copy is not slower
+//! for it in practice: it reads a word at a time, and values like integers
+//! which fit a single access are read with one relaxed atomic load, which
+//! compiles to the same instruction as a plain one. What it prevents is
+//! mostly merging the copy with the caller's use of the value.
+//!
+//! # Fields updated independently
+//!
+//! A `SeqLock` has a single sequence number, so a write to any part of the
+//! value makes every reader retry, including readers which only need an
+//! unrelated field. When groups of fields are written independently, give
+//! each group its own `SeqLock` instead. Each group is then consistent on its
+//! own, and writes to one group don't disturb readers of another:
+//!
+//! ```
+//! use seqlock::{ReadKind, SeqLock};
+//!
+//! // Aligned so that the groups don't share a cache line either.
+//! #[repr(align(128))]
+//! struct Group<T>(SeqLock<T>);
+//!
+//! struct Vehicle {
+//!     position: Group<[f64; 3]>,
+//!     status: Group<(u32, bool)>,
+//! }
+//!
+//! let vehicle = Vehicle {
+//!     position: Group(SeqLock::new([0.0; 3])),
+//!     status: Group(SeqLock::new((0, false))),
+//! };
+//! std::thread::scope(|s| {
+//!     s.spawn(|| {
+//!         for i in 0..1000 {
+//!             vehicle.position.0.write([i as f64; 3]);
+//!         }
+//!     });
+//!     for _ in 0..1000 {
+//!         assert_eq!(vehicle.status.0.read_instrumented(), ((0, false), ReadKind::FirstTry));
+//!     }
+//! });
+//! ```
+//!
+//! A reader which needs several groups at once reads them one after the
+//! other, and gets no guarantee that they are from the same moment. Fields
+//! which must be consistent with each other belong in the same group.
+//!
+//! # `no_std` support
+//!
+//! This crate is `no_std` compatible when the default `std` feature is
+//! disabled. In that configuration the writer mutex is a spinlock instead of
+//! a `parking_lot::Mutex`, and readers spin with `core::hint::spin_loop`
+//! instead of yielding to the OS scheduler while a write is in progress. The
+//! `async` feature additionally requires the `alloc` crate and native atomic
+//! compare-exchange operations.
+//!
+//! Targets without native atomic read-modify-write operations (such as
+//! `thumbv6m-none-eabi` or `riscv32imc-unknown-none-elf`) can enable the
+//! `portable-atomic` feature, which takes all atomic types from the
+//! [`portable-atomic`](https://docs.rs/portable-atomic) crate. Refer to its
+//! documentation for how to provide the operations it cannot implement
+//! natively, e.g. through its `critical-section` feature. Together with the
+//! `metrics` feature, it also provides the 64-bit counters on targets
+//! without native 64-bit atomics.
+//!
+//! The `critical-section` feature, see below, works on such targets without
+//! `portable-atomic`: the crate then only needs atomic loads and stores, and
+//! does what would take a compare-exchange inside a critical section
+//! instead. Building for a target without a native compare-exchange with
+//! neither feature fails with an error saying so.
+//!
+//! # WebAssembly
+//!
+//! The crate supports both the single-threaded `wasm32-unknown-unknown`
+//! target and builds with the `atomics` and `bulk-memory` target features,
+//! where threads are implemented with web workers sharing a
+//! `SharedArrayBuffer`.
+//!
+//! The main thread of a browser is not allowed to block, so on WebAssembly
+//! readers waiting for a writer always spin, and the writer mutex is a
+//! spinlock instead of a `parking_lot::Mutex`. Reads never block, which makes
+//! them safe to use from the main thread. Writers should preferably run in
+//! workers: a write from the main thread will spin for as long as a worker
+//! holds the write lock.
+//!
+//! # Interrupt-driven writers
+//!
+//! On single-core microcontrollers the writer is often an interrupt handler
+//! while readers run in thread mode. Taking a mutex in an interrupt handler
+//! is not an option, so with the `critical-section` feature the writer mutex
+//! is replaced by the [`critical-section`](https://docs.rs/critical-section)
+//! crate: `write` and `update` run inside `critical_section::with`, which
+//! serializes writers by masking interrupts (or by whatever implementation
+//! the application provides). Readers don't enter a critical section and
+//! therefore never mask interrupts; they retry if the interrupt handler
+//! preempted them in the middle of a read, exactly like a reader racing with
+//! a writer thread. This also works for readers on another core sharing the
+//! memory.
+//!
+//! A guard cannot keep a critical section open, so `lock_write` and
+//! `try_lock_write` are not available with this feature.
+//!
+//! ```ignore
+//! static SENSOR: SeqLock<Reading> = SeqLock::new(Reading::ZERO);
+//!
+//! #[interrupt]
+//! fn ADC() {
+//!     SENSOR.write(read_adc());
+//! }
+//!
+//! fn main() -> ! {
+//!     loop {
+//!         // Never masks interrupts, retries if ADC preempted the read.
+//!         let reading = SENSOR.read();
+//!         display(reading);
+//!     }
+//! }
+//! ```
+//!
+//! # Single-threaded writers
+//!
+//! When every write happens on the same thread, e.g. in firmware whose main
+//! loop is the only writer, building with
+//! `--cfg seqlock_unsafe_assume_single_writer` replaces the writer mutex with
+//! a plain flag, which removes the atomic read-modify-write operations from
+//! each write. The flag only catches a thread taking the
+//! write lock while it already holds it: `try_lock_write` returns `None`,
+//! and `lock_write` panics instead of deadlocking. Readers can still run on
+//! any thread, except that `read_helping`, which falls back to taking the
+//! writer lock, isn't available.
+//!
+//! **This is unsound if two threads ever write to the same `SeqLock`**, since
+//! nothing stops them from writing the data at the same time. Reads of the
+//! `MutexRef` strategy take the writer lock and count as writes here. This is
+//! why it is a `--cfg` flag rather than a Cargo feature: only whoever builds
+//! the final binary can promise that it has a single writer, e.g. with
+//! `RUSTFLAGS="--cfg seqlock_unsafe_assume_single_writer"`. It can't be
+//! combined with the `async` and `critical-section` features.
+//!
+//! # Persistent memory
+//!
+//! When the `pmem` feature is enabled (x86 and x86_64 only), every write
+//! first flushes the odd sequence counter to the persistence domain, before
+//! the data is modified. It then flushes the cache lines holding the data
+//! with `clflushopt` (or `clflush` on CPUs which lack it) followed by an
+//! `sfence` before the sequence counter is made even again. The cache line holding the
+//! sequence counter is then flushed as well. If the `SeqLock` is placed in
+//! persistent memory, this guarantees that after a crash the data is never
+//! older than the last even sequence counter that reached persistent memory:
+//! a persisted even sequence counter always describes fully persisted data.
+//! A persisted odd sequence counter means the crash happened in the middle of
+//! a write.
+//!
+//! ```
+//! use seqlock::SeqLock;
+//!
+//! // Values spanning several cache lines have all of their lines flushed.
+//! let lock = SeqLock::new([0u8; 256]);
+//! lock.lock_write()[255] = 1;
+//! assert_eq!(lock.read()[255], 1);
+//! ```
+//!
+//! # Failpoints
+//!
+//! The `failpoints` feature places [`fail`](https://docs.rs/fail) crate
+//! failpoints at the points of the read and write paths where a concurrent
+//! operation matters, so that tests can force an interleaving instead of
+//! hoping to hit it under stress. They can be configured to sleep, yield,
+//! pause or run a callback like any other failpoint. Without the feature
+//! they compile to nothing. The names below are kept stable across minor
+//! versions:
+//!
+//! - `seqlock::read::after_seq1`: a reader has loaded an even sequence
+//!   number and is about to copy the data.
+//! - `seqlock::read::after_copy`: a reader has copied the data and is about
+//!   to check the sequence number again.
+//! - `seqlock::read::wait`: a reader saw an odd sequence number and is about
+//!   to wait for the writer.
+//! - `seqlock::write::after_begin`: a writer has made the sequence number
+//!   odd and is about to write the data.
+//! - `seqlock::write::before_publish`: a writer has written the data and is
+//!   about to make the sequence number even again.
+//!
+//! The write failpoints run with the writer mutex held, or inside the
+//! critical section with the `critical-section` feature.
+//!
+//! ```
+//! # #[cfg(feature = "failpoints")] {
+//! use seqlock::SeqLock;
+//! use std::sync::atomic::{AtomicBool, Ordering};
+//! use std::sync::Arc;
+//!
+//! let scenario = fail::FailScenario::setup();
+//! let lock = Arc::new(SeqLock::new(1));
+//! let writer = lock.clone();
+//! let written = AtomicBool::new(false);
+//! // Write once, right after the reader loaded the sequence number.
+//! fail::cfg_callback("seqlock::read::after_seq1", move || {
+//!     if !written.swap(true, Ordering::Relaxed) {
+//!         writer.write(2);
+//!     }
+//! })
+//! .unwrap();
+//! // The first attempt is invalidated by the write, so the read retries.
+//! assert_eq!(lock.read_versioned(), (2, 2));
+//! scenario.teardown();
+//! # }
+//! ```
+//!
+//! # Bisecting bugs
+//!
+//! `SeqLock` takes a second type parameter selecting its [`Strategy`],
+//! which defaults to the sequence lock algorithm, [`Seq`]. When a bug is
+//! suspected to come from a `SeqLock`, e.g. a torn or stale value, switching
+//! the lock to [`MutexRef`], in which readers take the writer lock, tells
+//! whether the optimistic reads are to blame: if the bug persists with
+//! `MutexRef`, it lies in the code using the lock. Naming the lock type in a
+//! single alias makes this a one-line change:
+//!
+//! ```
+//! use seqlock::{SeqLock, Strategy};
+//!
+//! // Change `seqlock::Seq` to `seqlock::MutexRef` to bisect.
+//! type Lock<T> = SeqLock<T, seqlock::Seq>;
+//!
+//! fn make<S: Strategy>() -> SeqLock<(u32, u32), S> {
+//!     SeqLock::with_strategy((0, 0))
+//! }
+//!
+//! let lock: Lock<_> = make();
+//! lock.write((1, 1));
+//! assert_eq!(lock.read(), (1, 1));
+//! ```
+//!
+//! The methods which depend on the timing of the sequence number, such as
+//! [`SeqLock::read_watchdog`] and the async change notifications, are only
+//! available with `Seq`.
+//!
 //! # Examples
 //!
 //! ```
@@ -52,200 +365,3282 @@
 //! }
 //! ```
 
+#![no_std]
 #![warn(missing_docs, rust_2018_idioms)]
+#![debugger_visualizer(natvis_file = "../debug_metadata/seqlock.natvis")]
+#![debugger_visualizer(gdb_script_file = "../debug_metadata/seqlock_gdb.py")]
+
+#[cfg(any(feature = "async", feature = "write-hooks", loom, shuttle))]
+extern crate alloc;
+#[cfg(any(feature = "std", feature = "test-utils"))]
+extern crate std;
+
+#[cfg(all(
+    any(loom, shuttle),
+    any(
+        feature = "async",
+        feature = "critical-section",
+        feature = "embassy",
+        feature = "failpoints",
+        feature = "pmem"
+    )
+))]
+compile_error!(
+    "`--cfg loom` and `--cfg shuttle` do not support the async, critical-section, embassy, failpoints and pmem features"
+);
+#[cfg(all(
+    seqlock_unsafe_assume_single_writer,
+    any(feature = "async", feature = "critical-section", loom, shuttle)
+))]
+compile_error!(
+    "`--cfg seqlock_unsafe_assume_single_writer` can't be combined with the async and critical-section features, `--cfg loom` or `--cfg shuttle`"
+);
+#[cfg(all(loom, shuttle))]
+compile_error!("`--cfg loom` and `--cfg shuttle` can't be used together");
+
+// Evaluates the named failpoint when the `failpoints` feature is enabled, and
+// does nothing otherwise.
+macro_rules! fail_point {
+    ($name:literal) => {
+        #[cfg(feature = "failpoints")]
+        fail::fail_point!($name);
+    };
+}
+
+// The model checkers' data cell can't be created in a const context, so
+// constructors are only `const` when not model checking.
+macro_rules! const_fn {
+    ($($(#[$attr:meta])* $vis:vis fn $name:ident($($arg:tt)*) -> $ret:ty $body:block)*) => {
+        $(
+            #[cfg(not(any(loom, shuttle)))]
+            $(#[$attr])*
+            $vis const fn $name($($arg)*) -> $ret $body
+
+            #[cfg(any(loom, shuttle))]
+            $(#[$attr])*
+            $vis fn $name($($arg)*) -> $ret $body
+        )*
+    };
+}
+
+mod access;
+#[cfg(feature = "embassy")]
+pub mod embassy;
+#[cfg(feature = "async")]
+mod future;
+mod group;
+#[cfg(feature = "write-hooks")]
+mod hooks;
+mod location;
+#[cfg(feature = "metrics")]
+mod metrics;
+mod non_copy;
+#[cfg(feature = "async")]
+mod notify;
+#[cfg(feature = "pmem")]
+mod pmem;
+mod snapshot;
+mod static_lock;
+mod strategy;
+mod sync;
+#[cfg(feature = "test-utils")]
+pub mod test_utils;
+#[cfg(kani)]
+#[path = "../verification/mod.rs"]
+mod verification;
+#[cfg(not(any(loom, shuttle)))]
+mod view;
+
+use core::cmp;
+use core::fmt;
+#[cfg(all(feature = "async", not(feature = "critical-section")))]
+use core::future::Future;
+use core::marker::PhantomData;
+use core::mem::MaybeUninit;
+use core::ops::{BitAnd, BitOr, BitXor};
+#[cfg(not(feature = "critical-section"))]
+use core::ops::{Deref, DerefMut};
+#[cfg(feature = "write-location")]
+use core::panic::Location;
+#[cfg(not(any(loom, shuttle)))]
+use core::pin::Pin;
+#[cfg(feature = "async")]
+use core::task::{Poll, Waker};
+use sync::{fence, order, AtomicBool, AtomicUsize, Ordering};
+#[cfg(not(feature = "critical-section"))]
+use sync::{WriterGuard, WriterMutex};
+
+#[cfg(all(feature = "async", not(feature = "critical-section")))]
+pub use future::SeqLockSink;
+#[cfg(feature = "async")]
+pub use future::{Changed, SnapshotStream};
+#[cfg(not(feature = "critical-section"))]
+pub use group::SeqLockGroupGuard;
+pub use group::{SeqLockGroup, SeqLockMember};
+#[cfg(feature = "write-hooks")]
+pub use hooks::WriteHookId;
+#[cfg(feature = "metrics")]
+pub use metrics::SeqLockStats;
+pub use non_copy::SeqlockSafe;
+pub use snapshot::{ConsistentReader, ReadSnapshot, Snapshot};
+pub use static_lock::SeqLockStatic;
+pub use strategy::{MutexRef, Seq, Strategy};
+#[cfg(not(any(loom, shuttle)))]
+pub use view::SeqLockView;
 
-use parking_lot::{Mutex, MutexGuard};
-use std::cell::UnsafeCell;
-use std::fmt;
-use std::mem::MaybeUninit;
-use std::ops::{Deref, DerefMut};
-use std::ptr;
-use std::sync::atomic::{fence, AtomicUsize, Ordering};
-use std::thread;
+/// Issues the fence which readers of a `SeqLock` execute between reading the
+/// data and re-reading the sequence number.
+///
+/// This is an acquire fence. It can be used to extend the read side of a
+/// `SeqLock` to additional data kept outside of it, typically in relaxed
+/// atomics which writers only modify while holding the write lock:
+///
+/// 1. Get the value and sequence number with `read_versioned`.
+/// 2. Load the additional data with `Relaxed` ordering.
+/// 3. Call `read_fence`.
+/// 4. Check that `current_seq` still returns the same sequence number,
+///    otherwise a writer modified the additional data and you should retry.
+///
+/// # Examples
+///
+/// ```
+/// use seqlock::{read_fence, SeqLock};
+/// use std::sync::atomic::{AtomicBool, Ordering};
+///
+/// let position = SeqLock::new((0i32, 0i32));
+/// let moving = AtomicBool::new(false);
+///
+/// {
+///     let mut w = position.lock_write();
+///     *w = (1, 2);
+///     moving.store(true, Ordering::Relaxed);
+/// }
+///
+/// let (pos, moving) = loop {
+///     let (pos, seq) = position.read_versioned();
+///     let moving = moving.load(Ordering::Relaxed);
+///     read_fence();
+///     if position.current_seq() == seq {
+///         break (pos, moving);
+///     }
+/// };
+/// assert_eq!(pos, (1, 2));
+/// assert!(moving);
+/// ```
+#[inline]
+pub fn read_fence() {
+    fence(order(Ordering::Acquire));
+}
+
+/// Issues the fence which writers of a `SeqLock` execute between making the
+/// sequence number odd and modifying the data.
+///
+/// This is a release fence. `lock_write` and the other write methods
+/// already issue it before handing out write access, so additional data
+/// modified while holding the write lock needs no further fences: it is
+/// published together with the data when the write lock is released. This
+/// function is only needed when implementing the write side of the protocol
+/// by hand over your own sequence counter.
+#[inline]
+pub fn write_fence() {
+    fence(order(Ordering::Release));
+}
 
 /// A sequential lock
-pub struct SeqLock<T> {
+///
+/// The `S` parameter selects how readers are synchronized with writers, see
+/// [`Strategy`]. It defaults to the sequence lock algorithm, [`Seq`].
+pub struct SeqLock<T, S: Strategy = Seq> {
     seq: AtomicUsize,
-    data: UnsafeCell<T>,
-    mutex: Mutex<()>,
+    data: access::DataCell<T>,
+    #[cfg(not(feature = "critical-section"))]
+    mutex: WriterMutex<()>,
+    // Set by `freeze`, with writers excluded.
+    frozen: AtomicBool,
+    // Set by the first write to finish.
+    written: AtomicBool,
+    #[cfg(feature = "async")]
+    wakers: notify::WakerSet,
+    #[cfg(feature = "write-hooks")]
+    hooks: hooks::WriteHooks<T>,
+    #[cfg(feature = "metrics")]
+    counters: metrics::Counters,
+    #[cfg(feature = "write-location")]
+    last_writer: location::LastWriter,
+    strategy: PhantomData<S>,
 }
 
-unsafe impl<T: Send> Send for SeqLock<T> {}
-unsafe impl<T: Send> Sync for SeqLock<T> {}
+unsafe impl<T: Send, S: Strategy> Send for SeqLock<T, S> {}
+unsafe impl<T: Send, S: Strategy> Sync for SeqLock<T, S> {}
 
 /// RAII structure used to release the exclusive write access of a `SeqLock`
 /// when dropped.
-pub struct SeqLockGuard<'a, T> {
-    _guard: MutexGuard<'a, ()>,
-    seqlock: &'a SeqLock<T>,
+#[cfg(not(feature = "critical-section"))]
+pub struct SeqLockGuard<'a, T, S: Strategy = Seq> {
+    // The write must end before the mutex is unlocked.
+    write: WriteScope<'a, T, S>,
+    _guard: WriterGuard<'a, ()>,
+}
+
+/// RAII structure returned by `SeqLock::lock_write_with_previous`, which
+/// also holds the value the lock had before the write.
+#[cfg(not(feature = "critical-section"))]
+pub struct SeqLockPreviousGuard<'a, T, S: Strategy = Seq> {
+    guard: SeqLockGuard<'a, T, S>,
+    previous: T,
+}
+
+/// Makes a single attempt at reading the value protected by `seq`,
+/// returning the sequence number it started at if the attempt raced with a
+/// writer.
+#[inline]
+fn try_read<T: Copy>(seq: &AtomicUsize, data: &access::DataCell<T>) -> Result<(T, usize), usize> {
+    // The data is only ever initialized and written with valid values of `T`,
+    // so an untorn copy is always valid, whatever niches `T` has.
+    try_read_uninit(seq, data).map(|(val, seq)| (unsafe { val.assume_init() }, seq))
+}
+
+/// Like `try_read`, but returns the copy without assuming it is initialized.
+#[inline]
+fn try_read_uninit<T: Copy>(
+    seq: &AtomicUsize,
+    data: &access::DataCell<T>,
+) -> Result<(MaybeUninit<T>, usize), usize> {
+    // We need to use a volatile read here because the data may be
+    // concurrently modified by a writer. We also use MaybeUninit in
+    // case we read the data in the middle of a modification.
+    try_read_with(seq, || unsafe { data.load() })
+}
+
+/// A single read attempt, with `copy` copying the data out.
+#[inline]
+fn try_read_with<R>(seq: &AtomicUsize, copy: impl FnOnce() -> R) -> Result<(R, usize), usize> {
+    // Load the first sequence number. The acquire ordering ensures that
+    // this is done before reading the data, and that the data read is at
+    // least as new as the write which published `seq1`. It can't be relaxed
+    // even though seq2 is checked: the copy could then return data older
+    // than `seq1`, mixed with data from that write, without overlapping any
+    // later write that seq2 would detect (see the `read_protocol` loom
+    // tests).
+    let seq1 = seq.load(order(Ordering::Acquire));
+
+    // If the sequence number is odd then it means a writer is currently
+    // modifying the value.
+    if seq1 & 1 != 0 {
+        return Err(seq1);
+    }
+    fail_point!("seqlock::read::after_seq1");
+
+    let result = copy();
+    sync::preempt();
+    fail_point!("seqlock::read::after_copy");
+
+    // Make sure the seq2 read occurs after reading the data. What we
+    // ideally want is a load(Release), but the Release ordering is not
+    // available on loads.
+    fence(order(Ordering::Acquire));
+
+    // If the sequence number is the same then the data wasn't modified
+    // while we were reading it, and can be returned.
+    let seq2 = seq.load(order(Ordering::Relaxed));
+    if seq1 == seq2 {
+        Ok((result, seq1))
+    } else {
+        Err(seq1)
+    }
+}
+
+/// Reads the data protected by `seq` with `copy` until an attempt doesn't
+/// race with a writer, calling `retried` after every failed attempt.
+///
+/// This is the read side of the protocol shared by `SeqLock` and the other
+/// sequence-locked types of the crate.
+#[inline]
+pub(crate) fn read_versioned_with<R>(
+    seq: &AtomicUsize,
+    mut copy: impl FnMut() -> R,
+    mut retried: impl FnMut(),
+) -> (R, usize) {
+    loop {
+        match try_read_with(seq, &mut copy) {
+            Ok(res) => return res,
+            Err(seq) => {
+                retried();
+                if seq & 1 != 0 {
+                    fail_point!("seqlock::read::wait");
+                    sync::relax();
+                }
+            }
+        }
+    }
+}
+
+/// Reads the value in `data`, protected by `seq`, along with the sequence
+/// number it was read at.
+#[inline]
+pub(crate) fn read_versioned<T: Copy>(seq: &AtomicUsize, data: &access::DataCell<T>) -> (T, usize) {
+    let (val, seq) = read_versioned_with(seq, || unsafe { data.load() }, || {});
+    // See `try_read`.
+    (unsafe { val.assume_init() }, seq)
+}
+
+/// Marks the data protected by `seq` as being written, returning the odd
+/// sequence number readers now see.
+///
+/// This is the write side of the protocol shared by `SeqLock` and the other
+/// sequence-locked types of the crate. The caller must exclude other writers
+/// until the matching `end_write`.
+#[inline]
+pub(crate) fn begin_write(seq: &AtomicUsize) -> usize {
+    // Increment the sequence number. At this point, the number will be odd,
+    // which will force readers to spin until we finish writing.
+    let odd = seq.load(order(Ordering::Relaxed)).wrapping_add(1);
+    seq.store(odd, order(Ordering::Relaxed));
+
+    // Make sure any writes to the data happen after incrementing the
+    // sequence number. What we ideally want is a store(Acquire), but the
+    // Acquire ordering is not available on stores.
+    fence(order(Ordering::Release));
+    fail_point!("seqlock::write::after_begin");
+    odd
+}
+
+/// Publishes the data written since `begin_write` returned `odd`.
+#[inline]
+pub(crate) fn end_write(seq: &AtomicUsize, odd: usize) {
+    fail_point!("seqlock::write::before_publish");
+
+    // Increment the sequence number again, which will make it even and
+    // allow readers to access the data. The release ordering ensures that
+    // all writes to the data are done before writing the sequence number.
+    seq.store(odd.wrapping_add(1), order(Ordering::Release));
+}
+
+#[cold]
+#[inline(never)]
+#[track_caller]
+fn frozen_write() -> ! {
+    panic!("SeqLock written after being frozen");
+}
+
+// The number of failed attempts after which `SeqLock::read_helping` takes
+// the writer mutex.
+#[cfg(not(any(feature = "critical-section", seqlock_unsafe_assume_single_writer)))]
+const READ_HELPING_ATTEMPTS: u32 = 100;
+
+/// The error returned by `SeqLock::read_watchdog` when a writer appears to
+/// be stuck in the middle of a write.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StuckWriter {
     seq: usize,
 }
 
-impl<T> SeqLock<T> {
+impl StuckWriter {
+    /// Returns the odd sequence number the `SeqLock` was stuck at.
     #[inline]
-    fn end_write(&self, seq: usize) {
-        // Increment the sequence number again, which will make it even and
-        // allow readers to access the data. The release ordering ensures that
-        // all writes to the data are done before writing the sequence number.
-        self.seq.store(seq.wrapping_add(1), Ordering::Release);
+    pub fn seq(&self) -> usize {
+        self.seq
     }
 }
 
-impl<T: Copy> SeqLock<T> {
-    /// Creates a new SeqLock with the given initial value.
+impl fmt::Display for StuckWriter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "SeqLock writer stuck at sequence number {}", self.seq)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for StuckWriter {}
+
+/// The reason a single read attempt by `SeqLock::try_read_classified`
+/// failed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReadMiss {
+    /// A writer was in the middle of a write when the read started, so the
+    /// data wasn't copied.
+    WriterActive,
+    /// A write started while the data was being copied, so the copy may be
+    /// torn.
+    Torn,
+}
+
+impl ReadMiss {
+    /// Classifies a failed attempt which started at sequence number `seq`.
     #[inline]
-    pub const fn new(val: T) -> SeqLock<T> {
-        SeqLock {
-            seq: AtomicUsize::new(0),
-            data: UnsafeCell::new(val),
-            mutex: Mutex::new(()),
+    fn at(seq: usize) -> ReadMiss {
+        if seq & 1 != 0 {
+            ReadMiss::WriterActive
+        } else {
+            ReadMiss::Torn
         }
     }
+}
 
-    /// Reads the value protected by the `SeqLock`.
-    ///
-    /// This operation is extremely fast since it only reads the `SeqLock`,
-    /// which allows multiple readers to read the value without interfering with
-    /// each other.
-    ///
-    /// If a writer is currently modifying the contained value then the calling
-    /// thread will block until the writer thread releases the lock.
-    ///
-    /// Attempting to read from a `SeqLock` while already holding a write lock
-    /// in the current thread will result in a deadlock.
+impl fmt::Display for ReadMiss {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReadMiss::WriterActive => f.write_str("SeqLock read found a write in progress"),
+            ReadMiss::Torn => f.write_str("SeqLock read overlapped a write"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ReadMiss {}
+
+/// How a read by `SeqLock::read_instrumented` went.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReadKind {
+    /// The first attempt succeeded.
+    FirstTry,
+    /// The read succeeded after the given number of failed attempts, the
+    /// last of which found a writer in the middle of a write.
+    RetriedAfterWrite(u32),
+    /// The read succeeded after the given number of failed attempts, the
+    /// last of which overlapped a write which started during the copy.
+    RetriedAfterTear(u32),
+}
+
+/// How long `SeqLock::read_with_policy` keeps trying before it gives up.
+///
+/// Some policies only exist in some configurations, so this enum is
+/// non-exhaustive.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ReadPolicy {
+    /// Retry until a read succeeds, like `read`.
+    Block,
+    /// Make a single attempt, like `try_read_classified`.
+    TryOnce,
+    /// Retry up to the given number of times, busy-waiting in between.
+    Spin(u32),
+    /// Retry up to the given number of times, yielding to the OS scheduler
+    /// in between where `read` does, and busy-waiting otherwise.
+    Yield(u32),
+    /// Retry until the given time has passed since the call, yielding in
+    /// between like `Yield`.
+    #[cfg(all(
+        feature = "std",
+        not(all(target_family = "wasm", target_os = "unknown"))
+    ))]
+    Timeout(core::time::Duration),
+}
+
+/// The result of `SeqLock::read_with_policy`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReadOutcome<T> {
+    /// A read succeeded, with this value.
+    Read(T),
+    /// Every attempt allowed by the policy failed, the last one for this
+    /// reason.
+    GaveUp(ReadMiss),
+    /// The timeout of `ReadPolicy::Timeout` passed before a read succeeded.
+    TimedOut,
+}
+
+impl<T> ReadOutcome<T> {
+    /// Returns the value read, or `None` if the read gave up.
     #[inline]
-    pub fn read(&self) -> T {
-        loop {
-            // Load the first sequence number. The acquire ordering ensures that
-            // this is done before reading the data.
-            let seq1 = self.seq.load(Ordering::Acquire);
-
-            // If the sequence number is odd then it means a writer is currently
-            // modifying the value.
-            if seq1 & 1 != 0 {
-                // Yield to give the writer a chance to finish. Writing is
-                // expected to be relatively rare anyways so this isn't too
-                // performance critical.
-                thread::yield_now();
-                continue;
+    pub fn ok(self) -> Option<T> {
+        match self {
+            ReadOutcome::Read(val) => Some(val),
+            ReadOutcome::GaveUp(_) | ReadOutcome::TimedOut => None,
+        }
+    }
+}
+
+impl<T, S: Strategy> SeqLock<T, S> {
+    const_fn! {
+        #[inline]
+        fn with_parts(val: T, seq: usize) -> SeqLock<T, S> {
+            assert!(seq & 1 == 0, "SeqLock sequence number must be even");
+            SeqLock {
+                seq: AtomicUsize::new(seq),
+                data: access::DataCell::new(val),
+                #[cfg(not(feature = "critical-section"))]
+                mutex: WriterMutex::new(()),
+                frozen: AtomicBool::new(false),
+                written: AtomicBool::new(false),
+                #[cfg(feature = "async")]
+                wakers: notify::WakerSet::new(),
+                #[cfg(feature = "write-hooks")]
+                hooks: hooks::WriteHooks::new(),
+                #[cfg(feature = "metrics")]
+                counters: metrics::Counters::new(),
+                #[cfg(feature = "write-location")]
+                last_writer: location::LastWriter::new(),
+                strategy: PhantomData,
             }
+        }
+    }
+
+    // The read loop, with `copy` copying the data out.
+    #[inline]
+    fn read_versioned_with<R>(&self, copy: impl FnMut() -> R) -> (R, usize) {
+        if S::LOCKED_READS {
+            return self.read_locked(copy);
+        }
+        read_versioned_with(&self.seq, copy, || {
+            #[cfg(feature = "metrics")]
+            self.counters.record_read_retry();
+        })
+    }
 
-            // We need to use a volatile read here because the data may be
-            // concurrently modified by a writer. We also use MaybeUninit in
-            // case we read the data in the middle of a modification.
-            let result = unsafe { ptr::read_volatile(self.data.get() as *mut MaybeUninit<T>) };
+    // The read of the `MutexRef` strategy, done with writers excluded.
+    #[inline]
+    fn read_locked<R>(&self, copy: impl FnOnce() -> R) -> (R, usize) {
+        let read = || (copy(), self.seq.load(order(Ordering::Relaxed)));
 
-            // Make sure the seq2 read occurs after reading the data. What we
-            // ideally want is a load(Release), but the Release ordering is not
-            // available on loads.
-            fence(Ordering::Acquire);
+        #[cfg(not(feature = "critical-section"))]
+        {
+            let _guard = sync::lock_writer(&self.mutex);
+            read()
+        }
 
-            // If the sequence number is the same then the data wasn't modified
-            // while we were reading it, and can be returned.
-            let seq2 = self.seq.load(Ordering::Relaxed);
-            if seq1 == seq2 {
-                return unsafe { result.assume_init() };
-            }
+        #[cfg(feature = "critical-section")]
+        critical_section::with(|_| read())
+    }
+
+    // Locks the writer mutex for a writer, counting the writers which find it
+    // held with the `metrics` feature.
+    #[cfg(not(feature = "critical-section"))]
+    #[inline]
+    fn lock_mutex(&self) -> WriterGuard<'_, ()> {
+        #[cfg(feature = "metrics")]
+        match self.mutex.try_lock() {
+            Some(guard) => return guard,
+            None => self.counters.record_writer_wait(),
         }
+        sync::lock_writer(&self.mutex)
     }
 
     #[inline]
-    fn begin_write(&self) -> usize {
-        // Increment the sequence number. At this point, the number will be odd,
-        // which will force readers to spin until we finish writing.
-        let seq = self.seq.load(Ordering::Relaxed).wrapping_add(1);
-        self.seq.store(seq, Ordering::Relaxed);
+    fn begin_write(&self, caller: location::Caller) -> usize {
+        // Writers are excluded, so this is synchronized with `freeze`.
+        if self.frozen.load(order(Ordering::Relaxed)) {
+            frozen_write();
+        }
 
-        // Make sure any writes to the data happen after incrementing the
-        // sequence number. What we ideally want is a store(Acquire), but the
-        // Acquire ordering is not available on stores.
-        fence(Ordering::Release);
+        let seq = begin_write(&self.seq);
+
+        // The odd sequence number must reach persistent memory before any of
+        // the data does, or a crash half way through the write could leave
+        // modified data behind the previous even sequence number.
+        #[cfg(feature = "pmem")]
+        pmem::persist(
+            &self.seq as *const AtomicUsize as *const u8,
+            core::mem::size_of::<AtomicUsize>(),
+        );
+
+        // The callers of `begin_write` exclude other writers.
+        #[cfg(feature = "metrics")]
+        unsafe {
+            self.counters.record_write_start()
+        };
+        #[cfg(feature = "write-location")]
+        self.last_writer.record(caller);
+        #[cfg(not(feature = "write-location"))]
+        let _ = caller;
 
         seq
     }
 
     #[inline]
-    fn lock_guard<'a>(&'a self, guard: MutexGuard<'a, ()>) -> SeqLockGuard<'a, T> {
-        let seq = self.begin_write();
-        SeqLockGuard {
-            _guard: guard,
-            seqlock: self,
-            seq: seq,
+    fn end_write(&self, seq: usize) {
+        // Make sure the data reaches persistent memory before readers (and
+        // recovery code) can observe the even sequence number.
+        #[cfg(feature = "pmem")]
+        pmem::persist(self.data.as_ptr() as *const u8, core::mem::size_of::<T>());
+
+        end_write(&self.seq, seq);
+
+        // Only stored once, so that later writes don't touch the flag's cache
+        // line. Pairs with the acquire load in `has_been_written`.
+        if !self.written.load(order(Ordering::Relaxed)) {
+            self.written.store(true, order(Ordering::Release));
+        }
+
+        #[cfg(feature = "pmem")]
+        pmem::persist(
+            &self.seq as *const AtomicUsize as *const u8,
+            core::mem::size_of::<AtomicUsize>(),
+        );
+
+        #[cfg(feature = "metrics")]
+        unsafe {
+            self.counters.record_write()
+        };
+
+        #[cfg(feature = "async")]
+        self.wakers.wake_all();
+
+        // The caller still excludes other writers, so hooks observe each
+        // publication in order and the data can't change under them.
+        #[cfg(feature = "write-hooks")]
+        self.hooks.call(&self.data);
+    }
+
+    // Runs `f` with other writers excluded and, if it returns `Ok`, stores
+    // the new value it returns along with its result.
+    #[inline]
+    fn write_exclusive<R, E>(
+        &self,
+        caller: location::Caller,
+        f: impl FnOnce() -> Result<(T, R), E>,
+    ) -> Result<R, E> {
+        let exclusive = || {
+            let (new, res) = f()?;
+            let seq = self.begin_write(caller);
+            unsafe { self.data.store(&new) };
+            self.end_write(seq);
+            Ok(res)
+        };
+
+        #[cfg(not(feature = "critical-section"))]
+        {
+            let _guard = self.lock_mutex();
+            exclusive()
         }
+
+        #[cfg(feature = "critical-section")]
+        critical_section::with(|_| exclusive())
     }
 
-    /// Locks this `SeqLock` with exclusive write access, blocking the current
-    /// thread until it can be acquired.
+    /// Registers a hook which is called with the new value after every write
+    /// to this `SeqLock`.
     ///
-    /// This function does not block while waiting for concurrent readers.
-    /// Instead, readers will detect the concurrent write and retry the read.
+    /// Hooks run on the writing thread once the value has been published, but
+    /// before other writers are allowed in, so they observe every write
+    /// exactly once and in order. They slow down writers accordingly: when no
+    /// hook is registered, writes only pay for a single relaxed load.
     ///
-    /// Returns an RAII guard which will drop the write access of this `SeqLock`
-    /// when dropped.
-    #[inline]
-    pub fn lock_write(&self) -> SeqLockGuard<'_, T> {
-        self.lock_guard(self.mutex.lock())
+    /// Hooks may read the `SeqLock`, and will see the value they were called
+    /// with. They must not write to it, nor add or remove hooks on it, since
+    /// that would deadlock.
+    ///
+    /// Returns a token which can be passed to `remove_write_hook`. This is
+    /// only available with the `write-hooks` feature.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use seqlock::SeqLock;
+    /// use std::sync::atomic::{AtomicU32, Ordering};
+    /// use std::sync::Arc;
+    ///
+    /// let lock = SeqLock::new(0);
+    /// let sum = Arc::new(AtomicU32::new(0));
+    /// let id = lock.add_write_hook({
+    ///     let sum = sum.clone();
+    ///     Box::new(move |&val| {
+    ///         sum.fetch_add(val, Ordering::Relaxed);
+    ///     })
+    /// });
+    ///
+    /// lock.write(1);
+    /// lock.write(2);
+    /// assert!(lock.remove_write_hook(id));
+    /// lock.write(4);
+    /// assert_eq!(sum.load(Ordering::Relaxed), 3);
+    /// ```
+    #[cfg(feature = "write-hooks")]
+    pub fn add_write_hook(&self, hook: alloc::boxed::Box<dyn Fn(&T) + Send + Sync>) -> WriteHookId {
+        self.hooks.add(hook)
     }
 
-    /// Attempts to lock this `SeqLock` with exclusive write access.
+    /// Unregisters a hook added with `add_write_hook`.
     ///
-    /// If the lock could not be acquired at this time, then `None` is returned.
-    /// Otherwise, an RAII guard is returned which will release the lock when
-    /// it is dropped.
+    /// Returns `false` if the hook had already been removed. Once this
+    /// returns, the hook is not running and will not be called again.
+    #[cfg(feature = "write-hooks")]
+    pub fn remove_write_hook(&self, id: WriteHookId) -> bool {
+        self.hooks.remove(id)
+    }
+
+    /// Returns the call site of the most recent write, or `None` if the
+    /// `SeqLock` has never been written.
     ///
-    /// This function does not block.
+    /// This is only available with the `write-location` feature, which makes
+    /// every write method record `Location::caller()` when the write begins,
+    /// so it is where an unexpected overwrite came from. Writes through a
+    /// guard are recorded where the guard was taken. A `SeqLockSink` records
+    /// a location inside this crate, since sinks are driven from elsewhere.
+    ///
+    /// The location is recorded separately from the value, so a write which
+    /// is still in progress may already be reported.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use seqlock::SeqLock;
+    ///
+    /// let lock = SeqLock::new(1);
+    /// assert!(lock.last_writer_location().is_none());
+    /// lock.write(2);
+    /// assert_eq!(lock.last_writer_location().unwrap().line(), line!() - 1);
+    /// ```
+    #[cfg(feature = "write-location")]
     #[inline]
-    pub fn try_lock_write(&self) -> Option<SeqLockGuard<'_, T>> {
-        self.mutex.try_lock().map(|g| self.lock_guard(g))
+    pub fn last_writer_location(&self) -> Option<&'static Location<'static>> {
+        self.last_writer.get()
     }
 
-    /// Consumes this `SeqLock`, returning the underlying data.
+    /// Returns the current value of the sequence number.
+    ///
+    /// This is a relaxed load. It is odd while a write is in progress. See
+    /// [`read_fence`] for how to use it to validate data read alongside the
+    /// `SeqLock`.
     #[inline]
-    pub fn into_inner(self) -> T {
-        self.data.into_inner()
+    pub fn current_seq(&self) -> usize {
+        self.seq.load(order(Ordering::Relaxed))
     }
 
-    /// Returns a mutable reference to the underlying data.
+    /// Returns the address of this `SeqLock`, for telling locks apart in
+    /// logs.
     ///
-    /// Since this call borrows the `SeqLock` mutably, no actual locking needs
-    /// to take place---the mutable borrow statically guarantees no locks exist.
+    /// Two locks which are alive at the same time never have the same
+    /// address, but a lock which is moved gets a new one. `SeqLock` also
+    /// implements `fmt::Pointer`, which formats the same address.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use seqlock::SeqLock;
+    /// use std::sync::Arc;
+    ///
+    /// let a = Arc::new(SeqLock::new(0));
+    /// let b = Arc::new(SeqLock::new(0));
+    /// assert_ne!(a.addr(), b.addr());
+    /// assert_eq!(a.addr(), a.clone().addr());
+    /// assert_eq!(format!("{:p}", *a), format!("{:#x}", a.addr()));
+    /// ```
     #[inline]
-    pub fn get_mut(&mut self) -> &mut T {
-        unsafe { &mut *self.data.get() }
+    pub fn addr(&self) -> usize {
+        self as *const Self as usize
     }
-}
 
-impl<T: Copy + Default> Default for SeqLock<T> {
-    #[inline]
-    fn default() -> SeqLock<T> {
-        SeqLock::new(Default::default())
+    /// Returns a snapshot of the activity counters of this `SeqLock`.
+    ///
+    /// The counters are read one at a time, so the result is only a
+    /// best-effort view when the lock is in concurrent use.
+    ///
+    /// The crate doesn't depend on any metrics backend. To export the
+    /// counters, poll this periodically and record the differences between
+    /// snapshots, along with `max_write_duration`, under a name of your
+    /// choosing. Latency histograms of individual reads or writes are best
+    /// recorded by timing the calls at the call site, which keeps the cost
+    /// out of code which doesn't need it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use seqlock::SeqLock;
+    ///
+    /// let lock = SeqLock::new(0);
+    /// {
+    ///     let mut w = lock.lock_write();
+    ///     *w += 1;
+    ///     let stats = lock.stats();
+    ///     assert_eq!(stats.writes, 0);
+    ///     assert!(stats.write_in_progress);
+    /// }
+    /// lock.read();
+    ///
+    /// let stats = lock.stats();
+    /// assert_eq!(stats.writes, 1);
+    /// assert_eq!(stats.read_retries, 0);
+    /// assert_eq!(stats.current_seq, 2);
+    /// assert!(!stats.write_in_progress);
+    /// ```
+    ///
+    /// Readers which have to wait for a writer are counted as retries:
+    ///
+    /// ```
+    /// use seqlock::SeqLock;
+    /// use std::thread;
+    ///
+    /// let lock = SeqLock::new(0);
+    /// thread::scope(|s| {
+    ///     let guard = lock.lock_write();
+    ///     let reader = s.spawn(|| lock.read());
+    ///     while lock.stats().read_retries == 0 {
+    ///         thread::yield_now();
+    ///     }
+    ///     drop(guard);
+    ///     reader.join().unwrap();
+    /// });
+    /// assert!(lock.stats().read_retries > 0);
+    /// ```
+    #[cfg(feature = "metrics")]
+    pub fn stats(&self) -> SeqLockStats {
+        self.counters.stats(self.seq.load(order(Ordering::Relaxed)))
     }
-}
 
-impl<T: Copy + fmt::Debug> fmt::Debug for SeqLock<T> {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "SeqLock {{ data: {:?} }}", &self.read())
+    /// Returns the longest time a write has taken so far, from making the
+    /// sequence number odd to publishing the new value.
+    ///
+    /// Readers retry or wait for as long as a write is in progress, so long
+    /// writes are a common cause of read latency. For a guard this includes
+    /// the whole time it was held. Writes are only timed with the `std`
+    /// feature, and not on `wasm32-unknown-unknown`; elsewhere this always
+    /// returns zero.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use seqlock::SeqLock;
+    /// use std::thread;
+    /// use std::time::Duration;
+    ///
+    /// let lock = SeqLock::new(0);
+    /// lock.write(1);
+    /// let mut w = lock.lock_write();
+    /// thread::sleep(Duration::from_millis(10));
+    /// *w += 1;
+    /// drop(w);
+    /// assert!(lock.max_write_duration() >= Duration::from_millis(10));
+    /// ```
+    #[cfg(feature = "metrics")]
+    pub fn max_write_duration(&self) -> core::time::Duration {
+        self.counters.max_write_duration()
     }
-}
 
-impl<'a, T: Copy + 'a> Deref for SeqLockGuard<'a, T> {
-    type Target = T;
-    #[inline]
-    fn deref(&self) -> &T {
-        unsafe { &*self.seqlock.data.get() }
+    /// Returns the number of times a writer found the writer mutex held by
+    /// another writer and had to wait for it.
+    ///
+    /// A writer first tries to take the mutex, and is only counted if that
+    /// fails, so comparing this with `stats().writes` shows how many writes
+    /// were contended. This counts `lock_write`, `write` and the other
+    /// blocking and async writers, but not `try_lock_write`, which doesn't
+    /// wait. With the `critical-section` feature there is no writer mutex and
+    /// this always returns zero.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use seqlock::SeqLock;
+    /// use std::thread;
+    ///
+    /// let lock = SeqLock::new(0);
+    /// lock.write(1);
+    /// assert_eq!(lock.writer_contention_count(), 0);
+    ///
+    /// thread::scope(|s| {
+    ///     let guard = lock.lock_write();
+    ///     let writer = s.spawn(|| lock.write(2));
+    ///     while lock.writer_contention_count() == 0 {
+    ///         thread::yield_now();
+    ///     }
+    ///     drop(guard);
+    ///     writer.join().unwrap();
+    /// });
+    /// assert_eq!(lock.writer_contention_count(), 1);
+    /// ```
+    #[cfg(feature = "metrics")]
+    pub fn writer_contention_count(&self) -> u64 {
+        self.counters.writer_waits()
     }
 }
 
-impl<'a, T: Copy + 'a> DerefMut for SeqLockGuard<'a, T> {
-    #[inline]
-    fn deref_mut(&mut self) -> &mut T {
-        unsafe { &mut *self.seqlock.data.get() }
+impl<T: Copy> SeqLock<T> {
+    const_fn! {
+        /// Creates a new SeqLock with the given initial value.
+        #[inline]
+        pub fn new(val: T) -> SeqLock<T> {
+            SeqLock::from_parts(val, 0)
+        }
+
+        /// Creates a new SeqLock with the given initial value and sequence
+        /// number.
+        ///
+        /// This is used to restore state saved with `into_parts`, or read
+        /// with `read_versioned`, so that sequence numbers observed before
+        /// the state was saved still compare as expected against later ones.
+        /// The lock has no writer in progress, so `seq` must be even.
+        ///
+        /// A `SeqLock` always owns its data and writer mutex, so this copies
+        /// the state rather than attaching to a lock which may be in use
+        /// elsewhere.
+        ///
+        /// # Panics
+        ///
+        /// Panics if `seq` is odd.
+        ///
+        /// # Examples
+        ///
+        /// ```
+        /// use seqlock::SeqLock;
+        ///
+        /// let lock = SeqLock::new(1);
+        /// lock.write(2);
+        /// let (val, seq) = lock.into_parts();
+        ///
+        /// let resumed = SeqLock::from_parts(val, seq);
+        /// assert_eq!(resumed.read_versioned(), (2, seq));
+        /// resumed.write(3);
+        /// assert_eq!(resumed.read_versioned(), (3, seq + 2));
+        /// ```
+        #[inline]
+        pub fn from_parts(val: T, seq: usize) -> SeqLock<T> {
+            SeqLock::with_parts(val, seq)
+        }
     }
 }
 
-impl<T> Drop for SeqLockGuard<'_, T> {
-    #[inline]
-    fn drop(&mut self) {
+impl<T: Copy, S: Strategy> SeqLock<T, S> {
+    const_fn! {
+        /// Creates a new SeqLock with the given initial value, using the
+        /// strategy `S`.
+        ///
+        /// `SeqLock::new` always uses the default `Seq` strategy, so that
+        /// its type can be inferred. Code which should keep working when the
+        /// strategy of a type alias is changed can construct it with this
+        /// instead.
+        ///
+        /// # Examples
+        ///
+        /// ```
+        /// use seqlock::{MutexRef, SeqLock};
+        ///
+        /// type Counter = SeqLock<u64, MutexRef>;
+        ///
+        /// let counter = Counter::with_strategy(0);
+        /// counter.update(|n| *n += 1);
+        /// assert_eq!(counter.read_versioned(), (1, 2));
+        /// ```
+        #[inline]
+        pub fn with_strategy(val: T) -> SeqLock<T, S> {
+            SeqLock::with_parts(val, 0)
+        }
+    }
+
+    /// Reads the value protected by the `SeqLock`.
+    ///
+    /// This operation is extremely fast since it only reads the `SeqLock`,
+    /// which allows multiple readers to read the value without interfering with
+    /// each other.
+    ///
+    /// If a writer is currently modifying the contained value then the calling
+    /// thread will block until the writer thread releases the lock.
+    ///
+    /// Attempting to read from a `SeqLock` while already holding a write lock
+    /// in the current thread will result in a deadlock.
+    ///
+    /// A read is never optimized away, even if its result is unused, but
+    /// only with the default volatile copy, which the compiler has to perform
+    /// regardless: `lock.read();` in a benchmark loop then measures a
+    /// complete read. With the `atomic-memcpy` feature, and under Miri, the
+    /// copy is made of relaxed atomic loads instead, which carry no such
+    /// guarantee, so benchmarks which may run in those configurations should
+    /// pass the result through `std::hint::black_box`.
+    #[inline]
+    pub fn read(&self) -> T {
+        self.read_versioned().0
+    }
+
+    /// Reads the value protected by the `SeqLock`, busy-waiting up to
+    /// `SPINS` times for a writer before falling back to the waiting of
+    /// `read`.
+    ///
+    /// `read` yields to the OS scheduler as soon as it finds a write in
+    /// progress (where it can, see the crate documentation). Spinning first
+    /// is cheaper when writes are very short. Since the count is a constant,
+    /// each call site can pick its own without any cost at runtime, and with
+    /// `SPINS = 0` this is the same as `read`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use seqlock::SeqLock;
+    ///
+    /// let lock = SeqLock::new(5);
+    /// assert_eq!(lock.read_const_spin::<100>(), 5);
+    /// ```
+    #[inline]
+    pub fn read_const_spin<const SPINS: usize>(&self) -> T {
+        if S::LOCKED_READS {
+            return self.read();
+        }
+        let mut spins = 0;
+        loop {
+            match try_read(&self.seq, &self.data) {
+                Ok((val, _)) => return val,
+                Err(seq) => {
+                    #[cfg(feature = "metrics")]
+                    self.counters.record_read_retry();
+                    if seq & 1 != 0 {
+                        fail_point!("seqlock::read::wait");
+                        if spins < SPINS {
+                            spins += 1;
+                            sync::spin();
+                        } else {
+                            sync::relax();
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Brings the sequence number and the value into the cache of the
+    /// calling thread, without changing either of them.
+    ///
+    /// This is a plain read whose result is discarded. It's meant to be
+    /// called right before a latency-sensitive section which reads the lock,
+    /// so that its first read doesn't miss the cache. The lines may of course
+    /// be evicted again, and a write on another thread invalidates them.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use seqlock::SeqLock;
+    ///
+    /// let lock = SeqLock::new([7u64; 32]);
+    /// lock.prewarm();
+    /// assert_eq!(lock.read_versioned(), ([7; 32], 0));
+    /// ```
+    #[inline]
+    pub fn prewarm(&self) {
+        core::hint::black_box(self.read());
+    }
+
+    /// Reads the value protected by the `SeqLock` and returns the result of
+    /// `f` on it, e.g. a single field or a value computed from it.
+    ///
+    /// `f` runs once, on a consistent copy, after the read has been
+    /// validated: it never sees a torn value, so it doesn't need to be
+    /// prepared for one, and it doesn't hold up writers. A result which may
+    /// be absent is simply an `Option`, as with `Iterator::filter_map`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use seqlock::SeqLock;
+    ///
+    /// #[derive(Clone, Copy)]
+    /// struct Position {
+    ///     x: f64,
+    ///     y: f64,
+    /// }
+    ///
+    /// let lock = SeqLock::new(Position { x: 3.0, y: 4.0 });
+    /// assert_eq!(lock.read_map(|p| p.x), 3.0);
+    /// assert_eq!(lock.read_map(|p| p.x.hypot(p.y)), 5.0);
+    /// assert_eq!(lock.read_map(|p| (p.y > 0.0).then_some(p.y)), Some(4.0));
+    /// ```
+    #[inline]
+    pub fn read_map<U>(&self, f: impl FnOnce(&T) -> U) -> U {
+        f(&self.read())
+    }
+
+    /// Runs `f` with a [`ConsistentReader`], through which every read sees
+    /// the same version of the value.
+    ///
+    /// Each [`ConsistentReader::map`] copies the value and checks it against
+    /// the sequence number loaded before `f` started, so that several fields
+    /// extracted by separate calls are known to come from one write. If a
+    /// writer got in between, the whole of `f` is run again, and its result
+    /// thrown away, until no write overlaps it. `f` must therefore be safe to
+    /// run any number of times: it shouldn't have side effects other than
+    /// through its result. A closure which waits for the lock to change will
+    /// never return.
+    ///
+    /// Every `map` copies the whole value, so `k` calls copy it `k` times.
+    /// When `f` needs several parts of the value, a single `read_map` which
+    /// extracts all of them is cheaper: this is for reads which decide what
+    /// to look at next from what they have already seen. With the `MutexRef`
+    /// strategy the value is copied once, with writers excluded, and `f` runs
+    /// exactly once.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use seqlock::SeqLock;
+    ///
+    /// #[derive(Clone, Copy)]
+    /// struct Account {
+    ///     balance: i64,
+    ///     history: [i64; 64],
+    /// }
+    ///
+    /// let lock = SeqLock::new(Account {
+    ///     balance: 10,
+    ///     history: [0; 64],
+    /// });
+    /// lock.update(|account| {
+    ///     account.balance -= 3;
+    ///     account.history[0] = -3;
+    /// });
+    /// // The account is only copied a second time when it is overdrawn.
+    /// let overdraft = lock.read_consistent(|reader| {
+    ///     let balance = reader.map(|a| a.balance);
+    ///     (balance < 0).then(|| reader.map(|a| a.history[0]))
+    /// });
+    /// assert_eq!(overdraft, None);
+    /// ```
+    #[inline]
+    pub fn read_consistent<R>(&self, mut f: impl FnMut(&ConsistentReader<'_, T, S>) -> R) -> R {
+        if S::LOCKED_READS {
+            let (value, seq) = self.read_locked(|| unsafe { self.data.load().assume_init() });
+            return f(&ConsistentReader::new(self, seq, Some(value)));
+        }
+        loop {
+            let seq = self.seq.load(order(Ordering::Acquire));
+            if seq & 1 != 0 {
+                fail_point!("seqlock::read::wait");
+                sync::relax();
+                continue;
+            }
+            let reader = ConsistentReader::new(self, seq, None);
+            let result = f(&reader);
+            if !reader.is_torn() {
+                return result;
+            }
+            #[cfg(feature = "metrics")]
+            self.counters.record_read_retry();
+        }
+    }
+
+    // Copies the data out and checks that nothing has been written since
+    // `seq` was loaded, with acquire ordering, earlier in the same read.
+    #[inline]
+    pub(crate) fn copy_at(&self, seq: usize) -> Option<T> {
+        let val = unsafe { self.data.load() };
+        sync::preempt();
+        fence(order(Ordering::Acquire));
+        (self.seq.load(order(Ordering::Relaxed)) == seq).then(|| unsafe { val.assume_init() })
+    }
+
+    /// Reads the value protected by the `SeqLock` along with the sequence
+    /// number it was read at.
+    ///
+    /// The sequence number is always even and increases (with wrapping) each
+    /// time the value is written, so comparing two sequence numbers tells you
+    /// whether the value has been modified in between.
+    ///
+    /// Nothing resets the sequence number of a `SeqLock` in place, so a
+    /// sequence number read earlier from the same lock is only ever
+    /// invalidated by writes. Sequence numbers are not comparable between
+    /// different locks: a lock which replaces another one should be created
+    /// with `from_parts` to continue its sequence.
+    #[inline]
+    pub fn read_versioned(&self) -> (T, usize) {
+        let (val, seq) = self.read_uninit_versioned();
+        // See `try_read`.
+        (unsafe { val.assume_init() }, seq)
+    }
+
+    /// Reads the value protected by the `SeqLock` without assuming that the
+    /// copy is initialized, leaving that to the caller.
+    ///
+    /// This waits for a consistent snapshot exactly like `read`, so the
+    /// result always holds a complete value which was written to the lock,
+    /// and calling `assume_init` on it is sound. The only bytes which may be
+    /// uninitialized are padding bytes of `T`, which must not be read as
+    /// integers. This is useful to feed the snapshot into further unsafe
+    /// processing, such as validating or reinterpreting its bytes, without
+    /// first producing a `T`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use seqlock::SeqLock;
+    ///
+    /// let lock = SeqLock::new([1u32, 2, 3, 4]);
+    /// let snapshot = lock.read_maybe_uninit();
+    ///
+    /// // `[u32; 4]` has no padding, so all of its bytes are initialized.
+    /// let bytes = unsafe { snapshot.as_ptr().cast::<[u8; 16]>().read() };
+    /// let expected: Vec<u8> = lock.read().iter().flat_map(|x| x.to_ne_bytes()).collect();
+    /// assert_eq!(bytes[..], expected[..]);
+    /// assert_eq!(unsafe { snapshot.assume_init() }, [1, 2, 3, 4]);
+    /// ```
+    #[inline]
+    pub fn read_maybe_uninit(&self) -> MaybeUninit<T> {
+        self.read_uninit_versioned().0
+    }
+
+    /// Reads the value protected by the `SeqLock` into `scratch`, returning a
+    /// reference to it.
+    ///
+    /// This is the same as `read`, except that the data is copied directly
+    /// into `scratch` instead of into a temporary on the stack. For very
+    /// large values this keeps the read from using a large stack frame: the
+    /// scratch space can live wherever the caller likes, e.g. on the heap,
+    /// and can be reused across reads. Whatever `scratch` contained before is
+    /// overwritten.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use seqlock::SeqLock;
+    ///
+    /// let lock = Box::new(SeqLock::new([7u64; 4096]));
+    /// let mut scratch = Box::new_uninit();
+    /// assert_eq!(lock.read_with_scratch(&mut scratch)[1234], 7);
+    ///
+    /// lock.update(|data| data[1234] = 8);
+    /// assert_eq!(lock.read_with_scratch(&mut scratch)[1234], 8);
+    /// ```
+    #[inline]
+    pub fn read_with_scratch<'s>(&self, scratch: &'s mut MaybeUninit<T>) -> &'s T {
+        self.read_versioned_with(|| unsafe { self.data.load_into(scratch) });
+        // See `try_read`.
+        unsafe { scratch.assume_init_ref() }
+    }
+
+    /// Reads the value protected by the `SeqLock` into a new `Box`.
+    ///
+    /// The data is copied directly into the heap allocation, as with
+    /// `read_with_scratch`, so even very large values never go through the
+    /// stack.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use seqlock::SeqLock;
+    ///
+    /// let lock = Box::new(SeqLock::new([3u8; 8192]));
+    /// let snapshot: Box<[u8; 8192]> = lock.read_boxed();
+    /// assert_eq!(snapshot[..], [3; 8192]);
+    /// ```
+    #[cfg(feature = "std")]
+    #[inline]
+    pub fn read_boxed(&self) -> std::boxed::Box<T> {
+        let mut boxed = std::boxed::Box::new_uninit();
+        self.read_with_scratch(&mut boxed);
+        unsafe { boxed.assume_init() }
+    }
+
+    /// Reads each of `locks` along with the sequence number it was read at,
+    /// as with `read_versioned`.
+    ///
+    /// Each value is consistent with its own sequence number, but the locks
+    /// are read one after the other, so the values may not have existed at
+    /// the same time. The sequence numbers can be passed to
+    /// `read_all_changed` to poll for changes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use seqlock::SeqLock;
+    ///
+    /// let locks = [SeqLock::new(1), SeqLock::new(2)];
+    /// locks[1].write(3);
+    /// assert_eq!(SeqLock::read_all_versioned(&locks), [(1, 0), (3, 2)]);
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn read_all_versioned(locks: &[SeqLock<T, S>]) -> std::vec::Vec<(T, usize)> {
+        locks.iter().map(SeqLock::read_versioned).collect()
+    }
+
+    /// Reads the locks which were written since `seqs` was read, returning
+    /// the index of each along with its value and new sequence number.
+    ///
+    /// `seqs` holds a sequence number for each lock, such as those returned
+    /// by `read_all_versioned`, and can be updated with the sequence numbers
+    /// returned to continue polling. Locks which haven't been written are
+    /// only checked with a load of their sequence number.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `locks` and `seqs` have different lengths.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use seqlock::SeqLock;
+    ///
+    /// let locks = [SeqLock::new(1), SeqLock::new(2), SeqLock::new(3)];
+    /// let mut seqs: Vec<usize> = SeqLock::read_all_versioned(&locks)
+    ///     .into_iter()
+    ///     .map(|(_, seq)| seq)
+    ///     .collect();
+    ///
+    /// locks[2].write(4);
+    /// let changed = SeqLock::read_all_changed(&locks, &seqs);
+    /// assert_eq!(changed, [(2, 4, 2)]);
+    /// for &(i, _, seq) in &changed {
+    ///     seqs[i] = seq;
+    /// }
+    /// assert!(SeqLock::read_all_changed(&locks, &seqs).is_empty());
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn read_all_changed(
+        locks: &[SeqLock<T, S>],
+        seqs: &[usize],
+    ) -> std::vec::Vec<(usize, T, usize)> {
+        assert_eq!(
+            locks.len(),
+            seqs.len(),
+            "read_all_changed needs one sequence number per lock"
+        );
+        locks
+            .iter()
+            .zip(seqs)
+            .enumerate()
+            .filter(|(_, (lock, &seq))| lock.current_seq() != seq)
+            .map(|(i, (lock, _))| {
+                let (val, seq) = lock.read_versioned();
+                (i, val, seq)
+            })
+            .collect()
+    }
+
+    /// Reads the value protected by the `SeqLock` and appends it to
+    /// `history`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use seqlock::SeqLock;
+    ///
+    /// let lock = SeqLock::new(1);
+    /// let mut history = Vec::new();
+    /// lock.read_append(&mut history);
+    /// lock.write(2);
+    /// lock.read_append(&mut history);
+    /// assert_eq!(history, [1, 2]);
+    /// ```
+    #[cfg(feature = "std")]
+    #[inline]
+    pub fn read_append(&self, history: &mut std::vec::Vec<T>) {
+        history.push(self.read());
+    }
+
+    /// Appends the value protected by the `SeqLock` to `history` if it was
+    /// written since sequence number `last_seq`, and returns the sequence
+    /// number it was read at.
+    ///
+    /// If nothing was written only the sequence number is loaded, and
+    /// `last_seq` is returned. Passing the result back in as `last_seq`
+    /// records each value once. Sequence numbers are always even, so an odd
+    /// `last_seq` such as `usize::MAX` always records the current value.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use seqlock::SeqLock;
+    ///
+    /// let lock = SeqLock::new(1);
+    /// let mut history = Vec::new();
+    /// let mut seq = lock.read_append_if_changed(&mut history, usize::MAX);
+    /// seq = lock.read_append_if_changed(&mut history, seq);
+    /// lock.write(2);
+    /// seq = lock.read_append_if_changed(&mut history, seq);
+    /// assert_eq!((history, seq), (vec![1, 2], 2));
+    /// ```
+    #[cfg(feature = "std")]
+    #[inline]
+    pub fn read_append_if_changed(&self, history: &mut std::vec::Vec<T>, last_seq: usize) -> usize {
+        if self.current_seq() == last_seq {
+            return last_seq;
+        }
+        let (val, seq) = self.read_versioned();
+        if seq != last_seq {
+            history.push(val);
+        }
+        seq
+    }
+
+    /// Reads the value protected by the `SeqLock` in a `const` context.
+    ///
+    /// Atomics can't be used in `const` evaluation, so this reads the data
+    /// directly without checking the sequence number. Since `const`
+    /// evaluation is single-threaded, nothing can write concurrently and the
+    /// read is always consistent there. This is meant for deriving other
+    /// constants from a `SeqLock` defined as a `const`, e.g. a table of
+    /// configuration values which is later used to initialize a `static`.
+    ///
+    /// Using the lock in `const` evaluation requires it to have no
+    /// destructor, which is not the case with the `async` or `write-hooks`
+    /// features. This method is not available under loom or shuttle, which
+    /// store the data in their own atomics.
+    ///
+    /// # Safety
+    ///
+    /// When called at runtime, no write may run concurrently with this read.
+    /// This always holds in `const` evaluation.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[cfg(not(any(feature = "async", feature = "write-hooks")))] {
+    /// use seqlock::SeqLock;
+    ///
+    /// const LIMITS: SeqLock<[u32; 3]> = SeqLock::new([10, 20, 30]);
+    /// const MAX: u32 = unsafe { LIMITS.read_const() }[2];
+    /// static CURRENT: SeqLock<[u32; 3]> = LIMITS;
+    ///
+    /// assert_eq!(MAX, 30);
+    /// CURRENT.write([1, 2, 3]);
+    /// assert_eq!(const { unsafe { LIMITS.read_const() } }, [10, 20, 30]);
+    /// # }
+    /// ```
+    #[cfg(not(any(loom, shuttle)))]
+    #[inline]
+    pub const unsafe fn read_const(&self) -> T {
+        *self.data.as_ptr()
+    }
+
+    #[inline]
+    fn read_uninit_versioned(&self) -> (MaybeUninit<T>, usize) {
+        self.read_versioned_with(|| unsafe { self.data.load() })
+    }
+
+    /// Reads the newest stable version of the value, along with its sequence
+    /// number.
+    ///
+    /// This is the same as `read_versioned`, and exists to make explicit a
+    /// guarantee that `read` and `read_versioned` already provide: a
+    /// `SeqLock` only stores the latest value, so there is no older
+    /// consistent version for a read to fall back to. If a write is in
+    /// progress when this is called, it waits for that write (and any which
+    /// start before the data is copied out) to finish, and returns the value
+    /// it published. The result is therefore never older than the newest
+    /// write which completed before the call, and successive calls from one
+    /// thread never go backwards.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use seqlock::SeqLock;
+    /// use std::thread;
+    ///
+    /// let lock = SeqLock::new(0u64);
+    /// thread::scope(|s| {
+    ///     s.spawn(|| {
+    ///         for i in 1..=1000 {
+    ///             lock.write(i);
+    ///         }
+    ///     });
+    ///
+    ///     let (mut last, mut last_seq) = lock.read_latest();
+    ///     while last != 1000 {
+    ///         let (val, seq) = lock.read_latest();
+    ///         assert!(val >= last && seq >= last_seq);
+    ///         (last, last_seq) = (val, seq);
+    ///     }
+    /// });
+    /// ```
+    ///
+    /// Readers which would rather get an older value than wait for a write
+    /// can keep the previous value in a second `SeqLock`, which the writer
+    /// only updates once the first one is published. At most one of the two
+    /// is being written at any time, so a read of one of them always
+    /// succeeds with a single writer. This doubles the memory and the cost of
+    /// each write, which is why a `SeqLock` doesn't do it on its own:
+    ///
+    /// ```
+    /// use seqlock::SeqLock;
+    ///
+    /// struct Config {
+    ///     current: SeqLock<[u64; 8]>,
+    ///     previous: SeqLock<[u64; 8]>,
+    /// }
+    ///
+    /// impl Config {
+    ///     fn write(&self, val: [u64; 8]) {
+    ///         self.current.write(val);
+    ///         self.previous.write(val);
+    ///     }
+    ///
+    ///     // Never waits for a writer, but may return the previous value.
+    ///     fn read_eventually_consistent(&self) -> [u64; 8] {
+    ///         loop {
+    ///             if let Ok(val) = self.current.try_read_classified() {
+    ///                 return val;
+    ///             }
+    ///             if let Ok(val) = self.previous.try_read_classified() {
+    ///                 return val;
+    ///             }
+    ///         }
+    ///     }
+    /// }
+    ///
+    /// let config = Config {
+    ///     current: SeqLock::new([0; 8]),
+    ///     previous: SeqLock::new([0; 8]),
+    /// };
+    /// let mut guard = config.current.lock_write();
+    /// guard[0] = 1;
+    /// // The write in progress doesn't hold up readers.
+    /// assert_eq!(config.read_eventually_consistent(), [0; 8]);
+    /// drop(guard);
+    /// assert_eq!(config.read_eventually_consistent()[0], 1);
+    /// ```
+    #[inline]
+    pub fn read_latest(&self) -> (T, usize) {
+        self.read_versioned()
+    }
+
+    /// Performs one consistent read and returns a snapshot which caches it.
+    ///
+    /// The snapshot can be dereferenced any number of times without touching
+    /// the `SeqLock` again. It does not track later writes: call
+    /// [`ReadSnapshot::refresh`] to re-read the value, or
+    /// [`ReadSnapshot::is_stale`] to check whether that is needed.
+    ///
+    /// The snapshot always owns its copy. A `SeqLock` has a single buffer
+    /// which writers overwrite in place, so there is no older buffer a guard
+    /// could keep alive and lend out as a `&T` without copying, as a
+    /// double-buffered or epoch-based lock would. To avoid copying a large
+    /// value onto the stack, use `read_with_scratch` or `read_boxed`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use seqlock::SeqLock;
+    ///
+    /// let lock = SeqLock::new([1u32, 2, 3]);
+    /// let mut snapshot = lock.read_guard();
+    /// assert_eq!(snapshot.iter().sum::<u32>(), 6);
+    ///
+    /// lock.write([4, 5, 6]);
+    /// assert_eq!(*snapshot, [1, 2, 3]);
+    /// assert!(snapshot.is_stale());
+    ///
+    /// assert!(snapshot.refresh());
+    /// assert_eq!(*snapshot, [4, 5, 6]);
+    /// assert!(!snapshot.is_stale());
+    /// assert!(!snapshot.refresh());
+    /// ```
+    #[doc(alias = "read_snapshot")]
+    #[inline]
+    pub fn read_guard(&self) -> ReadSnapshot<'_, T, S> {
+        ReadSnapshot::new(self)
+    }
+
+    #[cfg(not(feature = "critical-section"))]
+    #[inline]
+    fn lock_guard<'a>(
+        &'a self,
+        guard: WriterGuard<'a, ()>,
+        caller: location::Caller,
+    ) -> SeqLockGuard<'a, T, S> {
+        SeqLockGuard {
+            write: unsafe { WriteScope::begin(self, caller) },
+            _guard: guard,
+        }
+    }
+
+    /// Locks this `SeqLock` with exclusive write access, blocking the current
+    /// thread until it can be acquired.
+    ///
+    /// This function does not block while waiting for concurrent readers.
+    /// Instead, readers will detect the concurrent write and retry the read.
+    ///
+    /// Returns an RAII guard which will drop the write access of this `SeqLock`
+    /// when dropped.
+    #[cfg(not(feature = "critical-section"))]
+    #[inline]
+    #[cfg_attr(feature = "write-location", track_caller)]
+    pub fn lock_write(&self) -> SeqLockGuard<'_, T, S> {
+        self.lock_guard(self.lock_mutex(), location::Caller::get())
+    }
+
+    /// Like `lock_write`, but the returned guard also keeps a copy of the
+    /// value from before the write, see `SeqLockPreviousGuard::previous`.
+    ///
+    /// The copy is made once, when the lock is acquired, so this costs an
+    /// extra copy of `T` which `lock_write` doesn't make.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use seqlock::SeqLock;
+    ///
+    /// let lock = SeqLock::new(1);
+    /// let mut w = lock.lock_write_with_previous();
+    /// *w *= 10;
+    /// *w += 2;
+    /// assert_eq!((*w.previous(), *w), (1, 12));
+    /// drop(w);
+    ///
+    /// assert_eq!(*lock.lock_write_with_previous().previous(), 12);
+    /// ```
+    #[cfg(not(feature = "critical-section"))]
+    #[inline]
+    #[cfg_attr(feature = "write-location", track_caller)]
+    pub fn lock_write_with_previous(&self) -> SeqLockPreviousGuard<'_, T, S> {
+        let guard = self.lock_mutex();
+        // Other writers are excluded so the data can't change under us.
+        let previous = unsafe { self.data.load().assume_init() };
+        SeqLockPreviousGuard {
+            guard: self.lock_guard(guard, location::Caller::get()),
+            previous,
+        }
+    }
+
+    /// Locks this `SeqLock` with exclusive write access, waiting
+    /// asynchronously until it can be acquired.
+    ///
+    /// This shares its mutual exclusion with `lock_write` and
+    /// `try_lock_write`, so blocking and async writers can be mixed freely.
+    /// Dropping the returned future before it completes leaves the lock
+    /// untouched. Readers are only affected once the lock is acquired.
+    ///
+    /// With the `async` feature the returned guard is `Send`, so it can be
+    /// held across an `.await` in a multi-threaded executor. The mutex is
+    /// runtime-agnostic, so this also works on single-threaded executors.
+    /// Readers still spin while a write is in progress, though: a task which
+    /// reads on the same thread as a writer suspended with the guard held
+    /// never returns, so on a single-threaded executor avoid holding the
+    /// guard across an `.await` if tasks on it also read.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use seqlock::SeqLock;
+    ///
+    /// let lock = SeqLock::new(5);
+    /// futures::executor::block_on(async {
+    ///     let mut w = lock.lock_write_async().await;
+    ///     *w += 1;
+    ///     assert!(lock.try_lock_write().is_none());
+    /// });
+    /// assert_eq!(lock.read(), 6);
+    /// ```
+    #[cfg(all(feature = "async", not(feature = "critical-section")))]
+    #[inline]
+    #[cfg_attr(feature = "write-location", track_caller)]
+    pub fn lock_write_async(&self) -> impl Future<Output = SeqLockGuard<'_, T, S>> {
+        // Taken outside the future, whose body can't track its caller.
+        let caller = location::Caller::get();
+        async move {
+            #[cfg(feature = "metrics")]
+            if let Some(guard) = self.mutex.try_lock() {
+                return self.lock_guard(guard, caller);
+            }
+            #[cfg(feature = "metrics")]
+            self.counters.record_writer_wait();
+            let guard = self.mutex.lock().await;
+            self.lock_guard(guard, caller)
+        }
+    }
+
+    /// Locks this `SeqLock` and `other` with exclusive write access, returning
+    /// their guards in the same order.
+    ///
+    /// The two locks are always acquired in the order of their addresses, so
+    /// threads locking the same pair with the arguments swapped can't
+    /// deadlock. Readers see each lock written separately, when its guard is
+    /// dropped. This isn't available with the `critical-section` feature,
+    /// where calling `update` on both locks inside one `critical_section::with`
+    /// does the same.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `self` and `other` are the same lock.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use seqlock::SeqLock;
+    ///
+    /// let (from, to) = (SeqLock::new(100), SeqLock::new(0));
+    /// let (mut a, mut b) = from.lock_write_pair(&to);
+    /// *a -= 30;
+    /// *b += 30;
+    /// drop((a, b));
+    /// assert_eq!((from.read(), to.read()), (70, 30));
+    /// ```
+    #[cfg(not(feature = "critical-section"))]
+    #[cfg_attr(feature = "write-location", track_caller)]
+    pub fn lock_write_pair<'a>(
+        &'a self,
+        other: &'a SeqLock<T, S>,
+    ) -> (SeqLockGuard<'a, T, S>, SeqLockGuard<'a, T, S>) {
+        assert!(
+            !core::ptr::eq(self, other),
+            "lock_write_pair called with the same SeqLock twice"
+        );
+        let caller = location::Caller::get();
+        if self.addr() < other.addr() {
+            let first = self.lock_guard(self.lock_mutex(), caller);
+            (first, other.lock_guard(other.lock_mutex(), caller))
+        } else {
+            let second = other.lock_guard(other.lock_mutex(), caller);
+            (self.lock_guard(self.lock_mutex(), caller), second)
+        }
+    }
+
+    /// Attempts to lock this `SeqLock` with exclusive write access.
+    ///
+    /// If the lock could not be acquired at this time, then `None` is returned.
+    /// Otherwise, an RAII guard is returned which will release the lock when
+    /// it is dropped.
+    ///
+    /// This function does not block.
+    #[cfg(not(feature = "critical-section"))]
+    #[inline]
+    #[cfg_attr(feature = "write-location", track_caller)]
+    pub fn try_lock_write(&self) -> Option<SeqLockGuard<'_, T, S>> {
+        let caller = location::Caller::get();
+        self.mutex.try_lock().map(|g| self.lock_guard(g, caller))
+    }
+
+    /// Attempts to lock this `SeqLock` with exclusive write access, retrying
+    /// up to `spins` times if it is held by another writer.
+    ///
+    /// This busy-waits between attempts instead of parking the thread, which
+    /// suits locks that are only ever held for very short writes. With
+    /// `spins` set to 0 this is the same as `try_lock_write`. Readers are
+    /// only affected once the lock is acquired.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use seqlock::SeqLock;
+    ///
+    /// let lock = SeqLock::new(5);
+    /// let guard = lock.lock_write();
+    /// assert!(lock.try_lock_write_spin(100).is_none());
+    /// drop(guard);
+    /// *lock.try_lock_write_spin(100).unwrap() = 6;
+    /// assert_eq!(lock.read(), 6);
+    /// ```
+    #[cfg(not(feature = "critical-section"))]
+    #[inline]
+    #[cfg_attr(feature = "write-location", track_caller)]
+    pub fn try_lock_write_spin(&self, spins: u32) -> Option<SeqLockGuard<'_, T, S>> {
+        let caller = location::Caller::get();
+        for _ in 0..spins {
+            if let Some(guard) = self.mutex.try_lock() {
+                return Some(self.lock_guard(guard, caller));
+            }
+            sync::spin();
+        }
+        self.mutex.try_lock().map(|g| self.lock_guard(g, caller))
+    }
+
+    /// Runs `f` with exclusive write access to the data.
+    #[inline]
+    #[cfg_attr(feature = "write-location", track_caller)]
+    fn write_with<R>(&self, f: impl FnOnce(&mut T) -> R) -> R {
+        #[cfg(not(feature = "critical-section"))]
+        {
+            f(&mut self.lock_write())
+        }
+
+        #[cfg(feature = "critical-section")]
+        {
+            let caller = location::Caller::get();
+            critical_section::with(|_| f(unsafe { WriteScope::begin(self, caller) }.get_mut()))
+        }
+    }
+
+    /// Runs `f` on the current value with exclusive write access, and writes
+    /// the value it returns in `Ok`, returning the previous value.
+    ///
+    /// If `f` returns `Err` then nothing is written and the sequence number
+    /// is left unchanged, so readers are not disturbed.
+    #[inline]
+    #[cfg_attr(feature = "write-location", track_caller)]
+    fn write_if<E>(&self, f: impl FnOnce(&T) -> Result<T, E>) -> Result<T, E> {
+        self.write_exclusive(location::Caller::get(), || {
+            // Other writers are excluded so the data can't change under us.
+            let old = unsafe { self.data.load().assume_init() };
+            Ok((f(&old)?, old))
+        })
+    }
+
+    /// Replaces the value protected by the `SeqLock`.
+    ///
+    /// This acquires exclusive write access for the duration of the store, or
+    /// enters a critical section if the `critical-section` feature is enabled.
+    ///
+    /// The new value is published with a release store of the sequence
+    /// number, so a reader which gets the new value also sees every memory
+    /// access the writer made before calling `write`, like an acquire load
+    /// seeing a release store. There is no variant with a relaxed publish:
+    /// the data is stored inside this call, so no fence the caller could
+    /// issue before or after it would order those stores before the
+    /// sequence number. To publish several values at once, write them with
+    /// `apply_batch`, or keep them in a `SeqLockGroup`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use seqlock::SeqLock;
+    ///
+    /// let lock = SeqLock::new(1);
+    /// lock.write(2);
+    /// assert_eq!(lock.read(), 2);
+    /// ```
+    #[doc(alias = "store")]
+    #[inline]
+    #[cfg_attr(feature = "write-location", track_caller)]
+    pub fn write(&self, val: T) {
+        self.write_with(|data| *data = val);
+    }
+
+    /// Makes the `SeqLock` immutable, so that its value can be borrowed with
+    /// `read_ref_frozen` instead of copied.
+    ///
+    /// This waits for a write in progress to finish. Any write after this
+    /// returns panics instead of modifying the value, and a frozen lock can't
+    /// be unfrozen; only `get_mut`, which can't run while the value is
+    /// borrowed, still modifies it. Freezing a frozen lock does nothing. This
+    /// is meant for configuration-style data which is written while it is
+    /// set up and only read afterwards.
+    ///
+    /// For the purposes of `write_unchecked`, this is a write: it must not
+    /// run concurrently with one.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use seqlock::SeqLock;
+    /// use std::panic::{self, AssertUnwindSafe};
+    ///
+    /// let lock = SeqLock::new([0u8; 64]);
+    /// lock.write([1; 64]);
+    /// lock.freeze();
+    /// assert_eq!(lock.read_ref_frozen(), Some(&[1; 64]));
+    ///
+    /// let res = panic::catch_unwind(AssertUnwindSafe(|| lock.write([2; 64])));
+    /// assert!(res.is_err());
+    /// assert_eq!(lock.read(), [1; 64]);
+    /// ```
+    #[inline]
+    pub fn freeze(&self) {
+        // Pairs with the acquire load in `read_ref_frozen`, so that the last
+        // write is visible to anyone who sees the flag.
+        let freeze = || self.frozen.store(true, order(Ordering::Release));
+
+        #[cfg(not(feature = "critical-section"))]
+        {
+            let _guard = self.lock_mutex();
+            freeze();
+        }
+
+        #[cfg(feature = "critical-section")]
+        critical_section::with(|_| freeze())
+    }
+
+    /// Returns whether `freeze` has been called.
+    #[inline]
+    pub fn is_frozen(&self) -> bool {
+        self.frozen.load(order(Ordering::Relaxed))
+    }
+
+    /// Returns whether a write to this `SeqLock` has finished since it was
+    /// created.
+    ///
+    /// This is tracked separately from the sequence number, so it stays set
+    /// even if the sequence number wraps back to 0, and a lock created with
+    /// `from_parts` starts out unwritten whatever its sequence number. Once
+    /// this returns `true`, reads return the value of that write or a later
+    /// one.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use seqlock::SeqLock;
+    ///
+    /// let lock = SeqLock::new(0);
+    /// assert!(!lock.has_been_written());
+    /// lock.write(0);
+    /// assert!(lock.has_been_written());
+    /// ```
+    #[inline]
+    pub fn has_been_written(&self) -> bool {
+        self.written.load(order(Ordering::Acquire))
+    }
+
+    /// Borrows the value of a frozen `SeqLock` without copying it, or returns
+    /// `None` if the lock hasn't been frozen.
+    ///
+    /// Once `freeze` has returned no write can modify the value, so it can be
+    /// borrowed for as long as the lock is. This is not available under loom
+    /// or shuttle, where the data is stored in atomics.
+    #[cfg(not(any(loom, shuttle)))]
+    #[inline]
+    pub fn read_ref_frozen(&self) -> Option<&T> {
+        if self.frozen.load(order(Ordering::Acquire)) {
+            Some(unsafe { &*self.data.as_ptr() })
+        } else {
+            None
+        }
+    }
+
+    /// Copies a consistent snapshot of this `SeqLock` into `dest`.
+    ///
+    /// The snapshot is read first, exactly like `read`, and then written
+    /// with `dest.write`, so each side is consistent on its own but `dest`
+    /// may already be out of date by the time the write is visible. The
+    /// source isn't held while `dest` is written, which also makes it fine
+    /// for `dest` to be the same lock.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use seqlock::SeqLock;
+    ///
+    /// let primary = SeqLock::new([1u32; 4]);
+    /// let mirror = SeqLock::new([0; 4]);
+    /// primary.copy_into(&mirror);
+    /// assert_eq!(mirror.read_versioned(), ([1; 4], 2));
+    /// ```
+    #[inline]
+    #[cfg_attr(feature = "write-location", track_caller)]
+    pub fn copy_into<S2: Strategy>(&self, dest: &SeqLock<T, S2>) {
+        dest.write(self.read());
+    }
+
+    /// Writes `values[i]` into `locks[i]` for each `i`, in order.
+    ///
+    /// Each lock is written with a separate `write`, so readers may see some
+    /// of the locks updated and others not yet; the locks are not updated
+    /// atomically as a group.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `locks` and `values` have different lengths. Nothing is
+    /// written in that case.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use seqlock::SeqLock;
+    ///
+    /// let locks = [SeqLock::new(0), SeqLock::new(0), SeqLock::new(0)];
+    /// SeqLock::write_all(&locks, &[1, 2, 3]);
+    /// assert_eq!(locks.each_ref().map(SeqLock::read), [1, 2, 3]);
+    /// ```
+    #[inline]
+    #[cfg_attr(feature = "write-location", track_caller)]
+    pub fn write_all(locks: &[SeqLock<T, S>], values: &[T]) {
+        assert_eq!(
+            locks.len(),
+            values.len(),
+            "`locks` and `values` must have the same length"
+        );
+        for (lock, &val) in locks.iter().zip(values) {
+            lock.write(val);
+        }
+    }
+
+    /// Modifies the value protected by the `SeqLock` in place, returning the
+    /// result of `f`.
+    ///
+    /// This acquires exclusive write access while `f` runs, or enters a
+    /// critical section if the `critical-section` feature is enabled. In the
+    /// latter case `f` should be kept as short as possible since interrupts
+    /// may be masked while it runs.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use seqlock::SeqLock;
+    ///
+    /// let lock = SeqLock::new(1);
+    /// let old = lock.update(|x| {
+    ///     let old = *x;
+    ///     *x += 1;
+    ///     old
+    /// });
+    /// assert_eq!(old, 1);
+    /// assert_eq!(lock.read(), 2);
+    /// ```
+    #[inline]
+    #[cfg_attr(feature = "write-location", track_caller)]
+    pub fn update<R>(&self, f: impl FnOnce(&mut T) -> R) -> R {
+        self.write_with(f)
+    }
+
+    /// Applies each of `deltas` to the value with `apply`, as a single write.
+    ///
+    /// Write access is acquired once for the whole batch, and the sequence
+    /// number is only incremented once, so readers never see the value with
+    /// only some of the deltas applied. They do wait for the whole batch
+    /// though, just as other writers do, so the deltas should be cheap to
+    /// produce and apply. If they aren't and there is only one writer, it can
+    /// apply them to a copy from `read` and `write` the result instead.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use seqlock::SeqLock;
+    ///
+    /// let lock = SeqLock::new([0u32; 4]);
+    /// lock.apply_batch([(0, 1), (2, 5), (0, 2)], |val, (i, delta)| val[i] += delta);
+    /// assert_eq!(lock.read_versioned(), ([3, 0, 5, 0], 2));
+    /// ```
+    #[inline]
+    #[cfg_attr(feature = "write-location", track_caller)]
+    pub fn apply_batch<D>(
+        &self,
+        deltas: impl IntoIterator<Item = D>,
+        mut apply: impl FnMut(&mut T, D),
+    ) {
+        self.write_with(|val| {
+            for delta in deltas {
+                apply(val, delta);
+            }
+        })
+    }
+
+    /// Writes `val` unless the `SeqLock` already contains an equal value,
+    /// returning whether a write happened.
+    ///
+    /// The current value is first checked with a normal read, so no write
+    /// access is needed if it is already equal to `val`. Otherwise the value
+    /// is compared again after acquiring write access, since another writer
+    /// may have stored `val` in the meantime. When no write happens the
+    /// sequence number is unchanged, so readers never retry because of a
+    /// redundant store.
+    ///
+    /// The comparison is `T`'s own `PartialEq`, not a comparison of the
+    /// bytes, which would treat e.g. `0.0` and `-0.0` as different and look
+    /// at padding. Comparisons of arrays and slices of integers already
+    /// compile to `memcmp`, which is vectorized, so large values of those
+    /// don't need anything special; a struct of them can forward its
+    /// `PartialEq` to such an array to get the same.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use seqlock::SeqLock;
+    ///
+    /// let lock = SeqLock::new(1);
+    /// assert!(!lock.set_if_ne(1));
+    /// assert_eq!(lock.read_versioned(), (1, 0));
+    ///
+    /// assert!(lock.set_if_ne(2));
+    /// assert_eq!(lock.read_versioned(), (2, 2));
+    /// ```
+    ///
+    /// Concurrent callers storing the same value write it only once:
+    ///
+    /// ```
+    /// use seqlock::SeqLock;
+    /// use std::thread;
+    ///
+    /// let lock = SeqLock::new(0);
+    /// let writes = thread::scope(|s| {
+    ///     let threads: Vec<_> = (0..8).map(|_| s.spawn(|| lock.set_if_ne(1))).collect();
+    ///     threads
+    ///         .into_iter()
+    ///         .map(|t| t.join().unwrap() as u32)
+    ///         .sum::<u32>()
+    /// });
+    /// assert_eq!(writes, 1);
+    /// assert_eq!(lock.read_versioned(), (1, 2));
+    /// ```
+    ///
+    /// A producer which keeps publishing the same value doesn't disturb
+    /// readers:
+    ///
+    /// ```
+    /// use seqlock::SeqLock;
+    ///
+    /// let lock = SeqLock::new(0);
+    /// let mut snapshot = lock.read_guard();
+    /// for _ in 0..100 {
+    ///     lock.set_if_ne(0);
+    /// }
+    /// assert!(!snapshot.refresh());
+    /// assert_eq!(lock.current_seq(), 0);
+    /// ```
+    #[doc(alias = "store_dedup")]
+    #[inline]
+    #[cfg_attr(feature = "write-location", track_caller)]
+    pub fn set_if_ne(&self, val: T) -> bool
+    where
+        T: PartialEq,
+    {
+        if self.read() == val {
+            return false;
+        }
+        self.write_if(|cur| if *cur != val { Ok(val) } else { Err(()) })
+            .is_ok()
+    }
+
+    /// Like `set_if_ne`, but returns the value which was replaced, or `None`
+    /// if the `SeqLock` already contained an equal value.
+    ///
+    /// As with `set_if_ne`, the sequence number is only changed if `val` is
+    /// written.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use seqlock::SeqLock;
+    ///
+    /// let lock = SeqLock::new(1);
+    /// assert_eq!(lock.replace_if_ne(1), None);
+    /// assert_eq!(lock.read_versioned(), (1, 0));
+    ///
+    /// assert_eq!(lock.replace_if_ne(2), Some(1));
+    /// assert_eq!(lock.read_versioned(), (2, 2));
+    /// ```
+    #[inline]
+    #[cfg_attr(feature = "write-location", track_caller)]
+    pub fn replace_if_ne(&self, val: T) -> Option<T>
+    where
+        T: PartialEq,
+    {
+        if self.read() == val {
+            return None;
+        }
+        self.swap_if(|cur| *cur != val, val).ok()
+    }
+
+    /// Replaces the value with `new` if `predicate` accepts the current
+    /// value, all under write access.
+    ///
+    /// Returns `Ok` with the old value if it was replaced, or `Err` with the
+    /// current value if `predicate` rejected it. In the latter case nothing
+    /// is written and the sequence number is unchanged.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use seqlock::SeqLock;
+    ///
+    /// let lock = SeqLock::new(1);
+    /// assert_eq!(lock.swap_if(|&cur| cur == 1, 2), Ok(1));
+    /// assert_eq!(lock.read_versioned(), (2, 2));
+    ///
+    /// // A rejected swap leaves the value and the sequence number alone.
+    /// assert_eq!(lock.swap_if(|&cur| cur == 1, 3), Err(2));
+    /// assert_eq!(lock.read_versioned(), (2, 2));
+    /// ```
+    #[inline]
+    #[cfg_attr(feature = "write-location", track_caller)]
+    pub fn swap_if<P: FnOnce(&T) -> bool>(&self, predicate: P, new: T) -> Result<T, T> {
+        self.write_if(|cur| if predicate(cur) { Ok(new) } else { Err(*cur) })
+    }
+
+    /// Consumes this `SeqLock`, returning the underlying data.
+    #[inline]
+    pub fn into_inner(self) -> T {
+        self.data.into_inner()
+    }
+
+    /// Consumes this `SeqLock`, returning the underlying data and its sequence
+    /// number.
+    ///
+    /// The result can be passed to `from_parts` to recreate the lock.
+    #[inline]
+    pub fn into_parts(self) -> (T, usize) {
+        (self.data.into_inner(), self.seq.into_inner())
+    }
+
+    /// Returns a mutable reference to the underlying data.
+    ///
+    /// Since this call borrows the `SeqLock` mutably, no actual locking needs
+    /// to take place---the mutable borrow statically guarantees no locks exist.
+    ///
+    /// This is not available under loom or shuttle, where the data is stored
+    /// in atomics.
+    #[cfg(not(any(loom, shuttle)))]
+    #[inline]
+    pub fn get_mut(&mut self) -> &mut T {
+        self.data.get_mut()
+    }
+
+    /// Clears the state the `SeqLock` keeps besides its value, and returns a
+    /// mutable reference to the value like `get_mut`.
+    ///
+    /// Afterwards the lock behaves like a newly created one with the same
+    /// value: it is no longer frozen or `has_been_written`, and the `metrics`
+    /// counters and the location recorded by the `write-location` feature
+    /// are cleared. The sequence number is kept, so that sequence numbers
+    /// read earlier are still invalidated by later writes, and so are the
+    /// write hooks, which are configuration rather than state.
+    ///
+    /// This is not available under loom or shuttle, where the data is stored
+    /// in atomics.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use seqlock::SeqLock;
+    ///
+    /// let mut lock = SeqLock::new(1);
+    /// lock.freeze();
+    /// *lock.reset() = 2;
+    /// assert!(!lock.is_frozen());
+    ///
+    /// lock.write(3);
+    /// assert_eq!(lock.read_versioned(), (3, 2));
+    /// ```
+    #[cfg(not(any(loom, shuttle)))]
+    #[inline]
+    pub fn reset(&mut self) -> &mut T {
+        *self.frozen.get_mut() = false;
+        *self.written.get_mut() = false;
+        #[cfg(feature = "metrics")]
+        {
+            self.counters = metrics::Counters::new();
+        }
+        #[cfg(feature = "write-location")]
+        {
+            self.last_writer = location::LastWriter::new();
+        }
+        self.data.get_mut()
+    }
+
+    /// Returns a pinned mutable reference to the underlying data.
+    ///
+    /// The data is structurally pinned: it is never moved out of a pinned
+    /// `SeqLock`, only overwritten in place by writers, which is allowed since
+    /// `T: Copy` has no destructor. Reads need no special support, since
+    /// `Pin<&SeqLock<T>>` dereferences to `&SeqLock<T>`.
+    ///
+    /// This is not available under loom or shuttle, where the data is stored
+    /// in atomics.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use seqlock::SeqLock;
+    /// use std::pin::pin;
+    ///
+    /// let mut lock = pin!(SeqLock::new(1));
+    /// *lock.as_mut().get_pin_mut().get_mut() += 1;
+    /// assert_eq!(lock.as_ref().read(), 2);
+    ///
+    /// lock.write(3);
+    /// assert_eq!(*lock.as_mut().get_pin_mut(), 3);
+    /// ```
+    ///
+    /// This also works for data which is not `Unpin`:
+    ///
+    /// ```
+    /// use seqlock::SeqLock;
+    /// use std::marker::PhantomPinned;
+    /// use std::pin::{pin, Pin};
+    ///
+    /// #[derive(Clone, Copy)]
+    /// struct Pinned {
+    ///     val: u32,
+    ///     _pin: PhantomPinned,
+    /// }
+    ///
+    /// let mut lock = pin!(SeqLock::new(Pinned { val: 1, _pin: PhantomPinned }));
+    /// let data: Pin<&mut Pinned> = lock.as_mut().get_pin_mut();
+    /// assert_eq!(data.val, 1);
+    /// assert_eq!(lock.read().val, 1);
+    /// ```
+    #[cfg(not(any(loom, shuttle)))]
+    #[inline]
+    pub fn get_pin_mut(self: Pin<&mut Self>) -> Pin<&mut T> {
+        // SAFETY: the data is structurally pinned, see above.
+        unsafe { self.map_unchecked_mut(|lock| lock.data.get_mut()) }
+    }
+}
+
+/// Helpers for a value which may be absent.
+///
+/// Like any other `T`, an `Option<T>` is only ever read from a copy which
+/// has been validated against the sequence number, so a torn copy whose
+/// discriminant or niche is invalid is never returned.
+///
+/// `None` usually leaves the bytes of the payload uninitialized, so where
+/// the data is copied with atomics (see the crate documentation) `Option<T>`
+/// only meets the no-padding requirement if `T` is a single value with a
+/// niche, such as `Option<NonZeroU64>`.
+impl<T: Copy, S: Strategy> SeqLock<Option<T>, S> {
+    /// Takes the value out of the `SeqLock`, leaving `None` in its place.
+    ///
+    /// Nothing is written if the `SeqLock` already contains `None`, so
+    /// readers are not disturbed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use seqlock::SeqLock;
+    ///
+    /// let lock = SeqLock::new(Some(1));
+    /// assert_eq!(lock.take_value(), Some(1));
+    /// assert_eq!(lock.take_value(), None);
+    /// assert_eq!(lock.read_versioned(), (None, 2));
+    /// ```
+    #[inline]
+    #[cfg_attr(feature = "write-location", track_caller)]
+    pub fn take_value(&self) -> Option<T> {
+        match self.swap_if(Option::is_some, None) {
+            Ok(val) | Err(val) => val,
+        }
+    }
+
+    /// Stores `Some(val)`, returning the previous value.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use seqlock::SeqLock;
+    ///
+    /// let lock = SeqLock::new(None);
+    /// assert_eq!(lock.set_value(1), None);
+    /// assert_eq!(lock.set_value(2), Some(1));
+    /// assert!(lock.is_some());
+    /// ```
+    #[inline]
+    #[cfg_attr(feature = "write-location", track_caller)]
+    pub fn set_value(&self, val: T) -> Option<T> {
+        self.update(|data| data.replace(val))
+    }
+
+    /// Returns whether the `SeqLock` currently contains a value.
+    #[inline]
+    pub fn is_some(&self) -> bool {
+        self.read().is_some()
+    }
+}
+
+/// Bitwise operations, e.g. for flag registers.
+///
+/// These mirror the methods of the atomic integer types, for widths and
+/// types that `core::sync::atomic` doesn't cover, such as `u128` or a
+/// bitflags type. Each one is a single write, so readers see either the old
+/// or the new value.
+impl<T, S> SeqLock<T, S>
+where
+    T: Copy + BitAnd<Output = T> + BitOr<Output = T> + BitXor<Output = T>,
+    S: Strategy,
+{
+    /// Stores the bitwise or of the value and `val`, returning the previous
+    /// value.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use seqlock::SeqLock;
+    ///
+    /// let lock = SeqLock::new(0b0011u128);
+    /// assert_eq!(lock.fetch_or(0b0110), 0b0011);
+    /// assert_eq!(lock.read(), 0b0111);
+    /// ```
+    #[inline]
+    #[cfg_attr(feature = "write-location", track_caller)]
+    pub fn fetch_or(&self, val: T) -> T {
+        self.update(|data| core::mem::replace(data, *data | val))
+    }
+
+    /// Stores the bitwise and of the value and `val`, returning the previous
+    /// value.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use seqlock::SeqLock;
+    ///
+    /// let lock = SeqLock::new(0b0011u128);
+    /// assert_eq!(lock.fetch_and(0b0110), 0b0011);
+    /// assert_eq!(lock.read(), 0b0010);
+    /// ```
+    #[inline]
+    #[cfg_attr(feature = "write-location", track_caller)]
+    pub fn fetch_and(&self, val: T) -> T {
+        self.update(|data| core::mem::replace(data, *data & val))
+    }
+
+    /// Stores the bitwise xor of the value and `val`, returning the previous
+    /// value.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use seqlock::SeqLock;
+    ///
+    /// let lock = SeqLock::new(0b0011u128);
+    /// assert_eq!(lock.fetch_xor(0b0110), 0b0011);
+    /// assert_eq!(lock.read(), 0b0101);
+    /// ```
+    #[inline]
+    #[cfg_attr(feature = "write-location", track_caller)]
+    pub fn fetch_xor(&self, val: T) -> T {
+        self.update(|data| core::mem::replace(data, *data ^ val))
+    }
+}
+
+/// Methods which rely on the timing of the sequence number, and so are only
+/// available with the default `Seq` strategy.
+impl<T: Copy> SeqLock<T> {
+    #[inline]
+    fn try_read(&self) -> Result<(T, usize), usize> {
+        try_read(&self.seq, &self.data)
+    }
+
+    /// Modifies the value protected by the `SeqLock` in place without taking
+    /// the writer mutex.
+    ///
+    /// This brackets `f` with the sequence number exactly like `update`, so
+    /// readers are unaffected, but skips the cost of the mutex for callers
+    /// which can guarantee on their own that nothing else writes. If `f`
+    /// panics the write still ends, with whatever `f` left in the value.
+    ///
+    /// This is only available with the `Seq` strategy, whose readers don't
+    /// take the writer mutex.
+    ///
+    /// # Safety
+    ///
+    /// No other write to this `SeqLock` may run while `f` does, on any
+    /// thread: neither another `write_unchecked`, nor a write through the
+    /// mutex such as `write` or a `SeqLockGuard`. In particular, this must not
+    /// be called while the current thread holds a guard, or from within
+    /// `f`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use seqlock::SeqLock;
+    /// use std::thread;
+    ///
+    /// let lock = SeqLock::new([0u64; 4]);
+    /// thread::scope(|s| {
+    ///     s.spawn(|| loop {
+    ///         let val = lock.read();
+    ///         assert!(val.iter().all(|&x| x == val[0]));
+    ///         if val[0] == 100 {
+    ///             break;
+    ///         }
+    ///     });
+    ///     // This thread is the only writer.
+    ///     for _ in 0..100 {
+    ///         unsafe { lock.write_unchecked(|val| val.iter_mut().for_each(|x| *x += 1)) };
+    ///     }
+    /// });
+    /// ```
+    #[inline]
+    #[cfg_attr(feature = "write-location", track_caller)]
+    pub unsafe fn write_unchecked(&self, f: impl FnOnce(&mut T)) {
+        f(WriteScope::begin(self, location::Caller::get()).get_mut());
+    }
+
+    /// Makes a single attempt at reading the value protected by the
+    /// `SeqLock` along with its sequence number, without spinning or waiting
+    /// for writers.
+    ///
+    /// This is the non-blocking counterpart of `read_versioned`: the
+    /// sequence number returned is always even. `None` is returned if a
+    /// writer was active or a write started during the copy; use
+    /// `try_read_classified` to tell the two apart.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use seqlock::SeqLock;
+    ///
+    /// let lock = SeqLock::new(5);
+    /// lock.write(6);
+    /// assert_eq!(lock.try_read_versioned(), Some((6, 2)));
+    ///
+    /// let guard = lock.lock_write();
+    /// assert_eq!(lock.try_read_versioned(), None);
+    /// drop(guard);
+    /// assert_eq!(lock.try_read_versioned(), Some((6, 4)));
+    /// ```
+    #[inline]
+    pub fn try_read_versioned(&self) -> Option<(T, usize)> {
+        self.read_retrying(|_| Some(())).ok()
+    }
+
+    /// Makes a single attempt at reading the value protected by the
+    /// `SeqLock`, without spinning or waiting for writers.
+    ///
+    /// If the attempt fails, the returned `ReadMiss` tells why: a writer was
+    /// already active, in which case it may be worth waiting for it, or a
+    /// write started during the copy, in which case retrying immediately is
+    /// likely to succeed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use seqlock::{ReadMiss, SeqLock};
+    ///
+    /// let lock = SeqLock::new(5);
+    /// assert_eq!(lock.try_read_classified(), Ok(5));
+    ///
+    /// let guard = lock.lock_write();
+    /// assert_eq!(lock.try_read_classified(), Err(ReadMiss::WriterActive));
+    /// drop(guard);
+    /// assert_eq!(lock.try_read_classified(), Ok(5));
+    /// ```
+    #[doc(alias = "try_read_exact")]
+    #[inline]
+    pub fn try_read_classified(&self) -> Result<T, ReadMiss> {
+        self.read_retrying(|seq| Some(ReadMiss::at(seq)))
+            .map(|(val, _)| val)
+    }
+
+    /// Reads the value protected by the `SeqLock`, giving up if a writer
+    /// appears to have crashed in the middle of a write.
+    ///
+    /// This behaves like `read`, except that if the sequence number stays at
+    /// the same odd value for more than `max_spins` consecutive attempts, an
+    /// error is returned instead of waiting forever. A sequence number which
+    /// keeps changing is never reported, since the writers are making
+    /// progress.
+    ///
+    /// This is mostly useful when the writer lives in another process which
+    /// may be killed while holding the lock. `max_spins` should be chosen to be
+    /// far longer than any legitimate write.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use seqlock::SeqLock;
+    ///
+    /// let lock = SeqLock::new(5);
+    /// assert_eq!(lock.read_watchdog(1000), Ok(5));
+    ///
+    /// // Simulate a writer which died without finishing its write.
+    /// std::mem::forget(lock.lock_write());
+    /// let err = lock.read_watchdog(1000).unwrap_err();
+    /// assert_eq!(err.seq(), 1);
+    /// ```
+    pub fn read_watchdog(&self, max_spins: u64) -> Result<T, StuckWriter> {
+        let mut stuck_seq = 0;
+        let mut spins = 0;
+        self.read_retrying(|seq| {
+            if seq & 1 == 0 {
+                return None;
+            }
+            if seq != stuck_seq {
+                stuck_seq = seq;
+                spins = 0;
+            } else if spins >= max_spins {
+                return Some(StuckWriter { seq });
+            }
+            spins += 1;
+            fail_point!("seqlock::read::wait");
+            sync::relax();
+            None
+        })
+        .map(|(val, _)| val)
+    }
+
+    /// Reads the value protected by the `SeqLock`, falling back to taking
+    /// the writer mutex if the read keeps failing.
+    ///
+    /// This behaves like `read` for the first attempts. After 100 failed
+    /// attempts the reader instead takes the writer mutex and copies the
+    /// data with writers excluded. A reader waiting for the mutex blocks
+    /// instead of spinning, which gives the CPU to a writer which was
+    /// preempted in the middle of a write, e.g. on an oversubscribed system
+    /// or when the writer has a lower priority, and it can't be starved by a
+    /// stream of writes. Every read therefore completes after a bounded
+    /// number of attempts and one acquisition of the mutex, even while a
+    /// writer writes back to back, which `read` doesn't guarantee.
+    ///
+    /// The downside is that such a reader delays the next writer until it
+    /// has copied the data, so a slow reader now slows down writes. This only
+    /// helps writers which hold the mutex for the whole write: it isn't
+    /// available with the `critical-section` feature, and doesn't wait for a
+    /// `write_unchecked`. Nor is it available with
+    /// `--cfg seqlock_unsafe_assume_single_writer`, whose writer lock can't be
+    /// taken by readers on other threads.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use seqlock::SeqLock;
+    /// use std::thread;
+    ///
+    /// let lock = SeqLock::new(5);
+    /// thread::scope(|s| {
+    ///     let mut w = lock.lock_write();
+    ///     let reader = s.spawn(|| lock.read_helping());
+    ///     *w = 6;
+    ///     drop(w);
+    ///     assert_eq!(reader.join().unwrap(), 6);
+    /// });
+    /// ```
+    #[doc(alias = "read_guaranteed")]
+    #[cfg(not(any(feature = "critical-section", seqlock_unsafe_assume_single_writer)))]
+    pub fn read_helping(&self) -> T {
+        let mut attempts = 0;
+        loop {
+            match self.try_read() {
+                Ok((val, _)) => return val,
+                Err(seq) => {
+                    #[cfg(feature = "metrics")]
+                    self.counters.record_read_retry();
+                    attempts += 1;
+                    if attempts >= READ_HELPING_ATTEMPTS {
+                        let (val, _) = self.read_locked(|| unsafe { self.data.load() });
+                        // Writers were excluded, so the copy can't be torn.
+                        return unsafe { val.assume_init() };
+                    }
+                    if seq & 1 != 0 {
+                        fail_point!("seqlock::read::wait");
+                        sync::relax();
+                    }
+                }
+            }
+        }
+    }
+
+    /// Reads the value protected by the `SeqLock`, and reports whether and
+    /// why the read had to be retried.
+    ///
+    /// This behaves exactly like `read`. The `ReadKind` tells how many
+    /// attempts failed, and whether the last failure was a writer already
+    /// holding the data, which readers can only wait out, or a write which
+    /// started during the copy, which a smaller value or less frequent writes
+    /// would avoid. The counts are per read, unlike the `metrics` counters.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use seqlock::{ReadKind, SeqLock};
+    ///
+    /// let lock = SeqLock::new(5);
+    /// assert_eq!(lock.read_instrumented(), (5, ReadKind::FirstTry));
+    /// ```
+    pub fn read_instrumented(&self) -> (T, ReadKind) {
+        let mut retries = 0u32;
+        let mut last_miss = ReadMiss::Torn;
+        loop {
+            match self.try_read() {
+                Ok((val, _)) => {
+                    let kind = match (retries, last_miss) {
+                        (0, _) => ReadKind::FirstTry,
+                        (n, ReadMiss::WriterActive) => ReadKind::RetriedAfterWrite(n),
+                        (n, ReadMiss::Torn) => ReadKind::RetriedAfterTear(n),
+                    };
+                    return (val, kind);
+                }
+                Err(seq) => {
+                    #[cfg(feature = "metrics")]
+                    self.counters.record_read_retry();
+                    retries = retries.saturating_add(1);
+                    if seq & 1 != 0 {
+                        last_miss = ReadMiss::WriterActive;
+                        fail_point!("seqlock::read::wait");
+                        sync::relax();
+                    } else {
+                        last_miss = ReadMiss::Torn;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Reads the value protected by the `SeqLock`, retrying failed attempts
+    /// only as far as `policy` allows.
+    ///
+    /// This is a single entry point for the different ways of waiting for a
+    /// writer: `ReadPolicy::Block` is the same as `read`, and
+    /// `ReadPolicy::TryOnce` the same as `try_read_classified`. The bounded
+    /// policies return the reason the last attempt failed once they give up.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use seqlock::{ReadMiss, ReadOutcome, ReadPolicy, SeqLock};
+    ///
+    /// let lock = SeqLock::new(5);
+    /// assert_eq!(lock.read_with_policy(ReadPolicy::Spin(10)), ReadOutcome::Read(5));
+    ///
+    /// let guard = lock.lock_write();
+    /// assert_eq!(
+    ///     lock.read_with_policy(ReadPolicy::Yield(10)),
+    ///     ReadOutcome::GaveUp(ReadMiss::WriterActive),
+    /// );
+    /// drop(guard);
+    /// assert_eq!(lock.read_with_policy(ReadPolicy::TryOnce).ok(), Some(5));
+    /// ```
+    pub fn read_with_policy(&self, policy: ReadPolicy) -> ReadOutcome<T> {
+        let retries = match policy {
+            ReadPolicy::Block => return ReadOutcome::Read(self.read()),
+            ReadPolicy::TryOnce => 0,
+            ReadPolicy::Spin(retries) | ReadPolicy::Yield(retries) => retries,
+            #[cfg(all(
+                feature = "std",
+                not(all(target_family = "wasm", target_os = "unknown"))
+            ))]
+            ReadPolicy::Timeout(timeout) => {
+                let start = std::time::Instant::now();
+                let res = self.read_retrying(|_| {
+                    if start.elapsed() >= timeout {
+                        return Some(ReadOutcome::TimedOut);
+                    }
+                    sync::relax();
+                    None
+                });
+                return res.map_or_else(|outcome| outcome, |(val, _)| ReadOutcome::Read(val));
+            }
+        };
+        let mut attempts = 0;
+        let res = self.read_retrying(|seq| {
+            if attempts == retries {
+                return Some(ReadOutcome::GaveUp(ReadMiss::at(seq)));
+            }
+            attempts += 1;
+            if let ReadPolicy::Spin(_) = policy {
+                sync::spin();
+            } else {
+                sync::relax();
+            }
+            None
+        });
+        res.map_or_else(|outcome| outcome, |(val, _)| ReadOutcome::Read(val))
+    }
+
+    // The retry loop behind `read_with_policy` and the other reads which may
+    // give up. After every failed attempt `miss` is called with the sequence
+    // number the attempt started at, and either returns the error to give up
+    // with, or waits as the policy requires before the next attempt.
+    #[inline]
+    fn read_retrying<E>(&self, mut miss: impl FnMut(usize) -> Option<E>) -> Result<(T, usize), E> {
+        loop {
+            match self.try_read() {
+                Ok(res) => return Ok(res),
+                Err(seq) => {
+                    if let Some(err) = miss(seq) {
+                        return Err(err);
+                    }
+                    #[cfg(feature = "metrics")]
+                    self.counters.record_read_retry();
+                }
+            }
+        }
+    }
+
+    /// Reads the value protected by the `SeqLock`, unless `cancel` is set
+    /// while waiting for a writer.
+    ///
+    /// `cancel` is only checked after a failed attempt, so a read which
+    /// succeeds on the first try costs the same as `read` and returns the
+    /// value even if `cancel` is already set. This lets another thread abort
+    /// a reader stuck behind a writer which never finishes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use seqlock::SeqLock;
+    /// use std::sync::atomic::AtomicBool;
+    ///
+    /// let lock = SeqLock::new(5);
+    /// let cancel = AtomicBool::new(true);
+    /// assert_eq!(lock.read_cancellable(&cancel), Some(5));
+    ///
+    /// let _guard = lock.lock_write();
+    /// assert_eq!(lock.read_cancellable(&cancel), None);
+    /// ```
+    pub fn read_cancellable(&self, cancel: &core::sync::atomic::AtomicBool) -> Option<T> {
+        self.read_retrying(|seq| {
+            if cancel.load(order(Ordering::Relaxed)) {
+                return Some(());
+            }
+            if seq & 1 != 0 {
+                fail_point!("seqlock::read::wait");
+                sync::relax();
+            }
+            None
+        })
+        .ok()
+        .map(|(val, _)| val)
+    }
+
+    /// Reads the data twice without waiting for a concurrent writer, for
+    /// debugging purposes.
+    ///
+    /// Returns the results of both reads, along with whether the sequence
+    /// number was even and unchanged across both reads, i.e. whether a normal
+    /// `read` would have accepted them. If it wasn't, the values may be torn
+    /// intermediate states of a write and may differ from each other.
+    ///
+    /// This is only available with the `debug-raw-read` feature and is
+    /// intended for inspecting the intermediate states produced by a writer
+    /// in tests.
+    ///
+    /// # Safety
+    ///
+    /// Every bit pattern must be a valid value of `T`, since the returned
+    /// values may be a mix of the bytes of several writes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use seqlock::SeqLock;
+    ///
+    /// let lock = SeqLock::new([0u32; 2]);
+    /// let mut w = lock.lock_write();
+    /// w[0] = 1;
+    ///
+    /// // The half-finished write is reported as inconsistent. (Under Miri or
+    /// // with the `atomic-memcpy` feature the guard buffers its writes, so
+    /// // they only become visible on drop.)
+    /// let (first, second, consistent) = unsafe { lock.read_raw_twice() };
+    /// assert!(!consistent);
+    /// assert_eq!(first, second);
+    /// assert!(first == [1, 0] || first == [0, 0]);
+    ///
+    /// w[1] = 1;
+    /// drop(w);
+    /// assert_eq!(unsafe { lock.read_raw_twice() }, ([1, 1], [1, 1], true));
+    /// ```
+    ///
+    /// Racing with a writer thread eventually catches it between the reads:
+    ///
+    /// ```
+    /// use seqlock::SeqLock;
+    /// use std::sync::atomic::{AtomicBool, Ordering};
+    /// use std::thread;
+    ///
+    /// let lock = SeqLock::new([0u64; 64]);
+    /// let done = AtomicBool::new(false);
+    /// thread::scope(|s| {
+    ///     s.spawn(|| {
+    ///         let mut i = 0;
+    ///         while !done.load(Ordering::Relaxed) {
+    ///             i += 1;
+    ///             lock.write([i; 64]);
+    ///         }
+    ///     });
+    ///     loop {
+    ///         let (first, second, consistent) = unsafe { lock.read_raw_twice() };
+    ///         if first != second {
+    ///             assert!(!consistent);
+    ///             break;
+    ///         }
+    ///     }
+    ///     done.store(true, Ordering::Relaxed);
+    /// });
+    /// ```
+    #[cfg(feature = "debug-raw-read")]
+    pub unsafe fn read_raw_twice(&self) -> (T, T, bool) {
+        let seq1 = self.seq.load(order(Ordering::Acquire));
+        let first = self.data.load();
+        let second = self.data.load();
+        fence(order(Ordering::Acquire));
+        let seq2 = self.seq.load(order(Ordering::Relaxed));
+        (
+            first.assume_init(),
+            second.assume_init(),
+            seq1 & 1 == 0 && seq1 == seq2,
+        )
+    }
+
+    /// Polls for a write to this `SeqLock` which happened after the
+    /// sequence number `last_seq` was observed.
+    ///
+    /// If the current sequence number differs from `last_seq` then the
+    /// current value and its sequence number are returned. Otherwise `waker`
+    /// is registered to be woken by the next write and `Poll::Pending` is
+    /// returned. Passing the sequence number returned by a previous call, or
+    /// by `read_versioned`, waits for the next change.
+    ///
+    /// This is intended for integrating with hand-written event loops. The
+    /// waker may be woken spuriously, in which case polling again simply
+    /// returns `Poll::Pending` after re-registering it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use seqlock::SeqLock;
+    /// use std::task::{Poll, Waker};
+    ///
+    /// let lock = SeqLock::new(1);
+    /// let (_, seq) = lock.read_versioned();
+    /// assert!(lock.poll_changed(seq, Waker::noop()).is_pending());
+    ///
+    /// *lock.lock_write() = 2;
+    /// assert_eq!(lock.poll_changed(seq, Waker::noop()), Poll::Ready((2, seq + 2)));
+    /// ```
+    ///
+    /// The waker is woken by a write from another thread:
+    ///
+    /// ```
+    /// use seqlock::SeqLock;
+    /// use std::sync::Arc;
+    /// use std::task::{Poll, Wake, Waker};
+    /// use std::thread::{self, Thread};
+    ///
+    /// struct ThreadWaker(Thread);
+    ///
+    /// impl Wake for ThreadWaker {
+    ///     fn wake(self: Arc<Self>) {
+    ///         self.0.unpark();
+    ///     }
+    /// }
+    ///
+    /// let lock = Arc::new(SeqLock::new(0));
+    /// let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+    ///
+    /// let writer = {
+    ///     let lock = lock.clone();
+    ///     thread::spawn(move || *lock.lock_write() = 42)
+    /// };
+    ///
+    /// let value = loop {
+    ///     match lock.poll_changed(0, &waker) {
+    ///         Poll::Ready((value, _)) => break value,
+    ///         Poll::Pending => thread::park(),
+    ///     }
+    /// };
+    /// assert_eq!(value, 42);
+    /// writer.join().unwrap();
+    /// ```
+    #[cfg(feature = "async")]
+    pub fn poll_changed(&self, last_seq: usize, waker: &Waker) -> Poll<(T, usize)> {
+        self.poll_changed_keyed(last_seq, notify::ANONYMOUS, waker)
+    }
+
+    #[cfg(feature = "async")]
+    fn poll_changed_keyed(&self, last_seq: usize, key: usize, waker: &Waker) -> Poll<(T, usize)> {
+        let (val, seq) = self.read_versioned();
+        if seq != last_seq {
+            return Poll::Ready((val, seq));
+        }
+
+        // A write landing here is the classic lost wakeup, which the
+        // failpoint lets tests force.
+        fail_point!("seqlock::changed::before_register");
+        self.wakers.register(key, waker);
+
+        // Check again in case a write completed before the waker was
+        // registered, since that write would not have woken us.
+        let (val, seq) = self.read_versioned();
+        if seq != last_seq {
+            Poll::Ready((val, seq))
+        } else {
+            Poll::Pending
+        }
+    }
+
+    /// Returns a future which resolves once the sequence number differs from
+    /// `seen_seq`, yielding the new value and its sequence number.
+    ///
+    /// Pass the sequence number returned by `read_versioned` or by a previous
+    /// `changed` to wait for the next write. If several writes happen before
+    /// the future is polled, only the latest value is returned.
+    ///
+    /// Dropping the future before it completes, e.g. when a timeout expires,
+    /// removes its waker, so abandoned futures don't pile up on a lock which
+    /// is rarely written.
+    ///
+    /// Writers wake waiting futures when they publish a value. With the
+    /// `async` feature this adds a `SeqCst` fence and a relaxed load to every
+    /// write, and only takes the slow path when a waker is registered.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use seqlock::SeqLock;
+    /// use std::thread;
+    ///
+    /// let lock = SeqLock::new(0);
+    /// let (_, seq) = lock.read_versioned();
+    /// thread::scope(|s| {
+    ///     s.spawn(|| lock.write(1));
+    ///     let (value, new_seq) = futures::executor::block_on(lock.changed(seq));
+    ///     assert_eq!(value, 1);
+    ///     assert_ne!(new_seq, seq);
+    /// });
+    /// ```
+    #[cfg(feature = "async")]
+    #[inline]
+    pub fn changed(&self, seen_seq: usize) -> Changed<'_, T> {
+        Changed::new(self, seen_seq, self.wakers.new_key())
+    }
+
+    /// Returns a stream which yields the current value, and then each newly
+    /// published value.
+    ///
+    /// The stream conflates writes: it only ever yields the latest value, so
+    /// if several writes happen between two polls then the intermediate
+    /// values are skipped. Every value yielded was published after the
+    /// previous one, and the same version is never yielded twice. The stream
+    /// never ends.
+    ///
+    /// The stream shares its wake-up mechanism with [`changed`], and removes
+    /// its waker when it is dropped.
+    ///
+    /// [`changed`]: SeqLock::changed
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use futures::StreamExt;
+    /// use seqlock::SeqLock;
+    /// use std::thread;
+    ///
+    /// let lock = SeqLock::new(0);
+    /// thread::scope(|s| {
+    ///     let mut stream = lock.stream();
+    ///     assert_eq!(futures::executor::block_on(stream.next()), Some(0));
+    ///
+    ///     s.spawn(|| lock.write(1));
+    ///     assert_eq!(futures::executor::block_on(stream.next()), Some(1));
+    /// });
+    /// ```
+    #[cfg(feature = "async")]
+    #[inline]
+    pub fn stream(&self) -> SnapshotStream<'_, T> {
+        SnapshotStream::new(self, self.wakers.new_key())
+    }
+
+    /// Returns a sink which publishes each item it receives to this
+    /// `SeqLock`.
+    ///
+    /// Readers only ever see the latest item, so items which are overwritten
+    /// before anyone reads them are effectively dropped. `poll_ready` waits
+    /// asynchronously for the writer mutex, which it then holds until the
+    /// next item is sent; flushing does nothing since every item is published
+    /// as soon as it is sent.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use futures::{stream, StreamExt};
+    /// use seqlock::SeqLock;
+    ///
+    /// let lock = SeqLock::new(0);
+    /// let items = stream::iter(1..=10).map(Ok);
+    /// futures::executor::block_on(items.forward(lock.sink())).unwrap();
+    /// assert_eq!(lock.read(), 10);
+    /// ```
+    #[cfg(all(feature = "async", not(feature = "critical-section")))]
+    #[inline]
+    pub fn sink(&self) -> SeqLockSink<'_, T> {
+        SeqLockSink::new(self)
+    }
+}
+
+impl<T: Copy + Default, S: Strategy> Default for SeqLock<T, S> {
+    #[inline]
+    fn default() -> SeqLock<T, S> {
+        SeqLock::with_strategy(Default::default())
+    }
+}
+
+/// Compares the values of two `SeqLock`s.
+///
+/// Each lock is read separately, so the comparison is not atomic: if either
+/// lock is written concurrently, the result reflects one consistent snapshot
+/// of each lock, but the two snapshots may not have existed at the same time.
+/// This even applies when comparing a lock with itself.
+impl<T: Copy + PartialEq, S: Strategy> PartialEq for SeqLock<T, S> {
+    #[inline]
+    fn eq(&self, other: &SeqLock<T, S>) -> bool {
+        self.read() == other.read()
+    }
+}
+
+impl<T: Copy + Eq, S: Strategy> Eq for SeqLock<T, S> {}
+
+/// Compares the values of two `SeqLock`s.
+///
+/// As with `PartialEq`, each lock is read separately, so the two snapshots
+/// which are compared may not have existed at the same time.
+///
+/// # Examples
+///
+/// ```
+/// use seqlock::SeqLock;
+///
+/// let a = SeqLock::new(1);
+/// let b = SeqLock::new(2);
+/// assert!(a < b);
+/// b.write(0);
+/// assert!(a > b);
+///
+/// let mut locks = vec![SeqLock::new(3), SeqLock::new(1), SeqLock::new(2)];
+/// locks.sort();
+/// assert_eq!(locks.iter().map(SeqLock::read).collect::<Vec<_>>(), [1, 2, 3]);
+/// ```
+impl<T: Copy + PartialOrd, S: Strategy> PartialOrd for SeqLock<T, S> {
+    #[inline]
+    fn partial_cmp(&self, other: &SeqLock<T, S>) -> Option<cmp::Ordering> {
+        self.read().partial_cmp(&other.read())
+    }
+}
+
+impl<T: Copy + Ord, S: Strategy> Ord for SeqLock<T, S> {
+    #[inline]
+    fn cmp(&self, other: &SeqLock<T, S>) -> cmp::Ordering {
+        self.read().cmp(&other.read())
+    }
+}
+
+impl<T: Copy + fmt::Debug, S: Strategy> fmt::Debug for SeqLock<T, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "SeqLock {{ data: {:?} }}", &self.read())
+    }
+}
+
+impl<T, S: Strategy> fmt::Pointer for SeqLock<T, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Pointer::fmt(&(self as *const Self), f)
+    }
+}
+
+#[cfg(not(feature = "critical-section"))]
+impl<'a, T: Copy + 'a, S: Strategy> SeqLockGuard<'a, T, S> {
+    /// Ends the write and returns the value that was written.
+    ///
+    /// The value is taken from the guard, so the `SeqLock` isn't read again.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use seqlock::SeqLock;
+    ///
+    /// let lock = SeqLock::new(1);
+    /// let mut w = lock.lock_write();
+    /// *w += 1;
+    /// assert_eq!(w.finish_and_read(), 2);
+    /// assert_eq!(lock.read_versioned(), (2, 2));
+    /// ```
+    #[inline]
+    pub fn finish_and_read(self) -> T {
+        *self
+    }
+
+    /// Ends the write and turns the guard into a snapshot of the value that
+    /// was written.
+    ///
+    /// This suits threads which alternate between writing a value and
+    /// reading it back: the snapshot starts out holding the written value at
+    /// the sequence number it was published with, without reading the
+    /// `SeqLock` again, and [`ReadSnapshot::refresh`] or
+    /// [`ReadSnapshot::is_stale`] then tell whether anyone else has written
+    /// since.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use seqlock::SeqLock;
+    ///
+    /// let lock = SeqLock::new(1);
+    /// let mut w = lock.lock_write();
+    /// *w = 2;
+    /// let mut snapshot = w.downgrade();
+    /// assert_eq!((*snapshot, snapshot.seq()), (2, 2));
+    /// assert!(!snapshot.is_stale());
+    ///
+    /// lock.write(3);
+    /// assert!(snapshot.refresh());
+    /// assert_eq!(*snapshot, 3);
+    /// ```
+    #[inline]
+    pub fn downgrade(self) -> ReadSnapshot<'a, T, S> {
+        let (seqlock, value) = (self.write.seqlock, *self);
+        let seq = self.write.seq.wrapping_add(1);
+        drop(self);
+        ReadSnapshot::from_parts(seqlock, value, seq)
+    }
+}
+
+#[cfg(not(feature = "critical-section"))]
+impl<'a, T: Copy + 'a, S: Strategy> Deref for SeqLockGuard<'a, T, S> {
+    type Target = T;
+    #[inline]
+    fn deref(&self) -> &T {
+        self.write.get()
+    }
+}
+
+#[cfg(not(feature = "critical-section"))]
+impl<'a, T: Copy + 'a, S: Strategy> DerefMut for SeqLockGuard<'a, T, S> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut T {
+        self.write.get_mut()
+    }
+}
+
+#[cfg(not(feature = "critical-section"))]
+impl<'a, T: Copy + 'a, S: Strategy> SeqLockPreviousGuard<'a, T, S> {
+    /// Returns the value as it was when the guard was taken, before any
+    /// modification made through it.
+    #[inline]
+    pub fn previous(&self) -> &T {
+        &self.previous
+    }
+
+    /// Ends the write and returns the value that was written.
+    #[inline]
+    pub fn finish_and_read(self) -> T {
+        self.guard.finish_and_read()
+    }
+}
+
+#[cfg(not(feature = "critical-section"))]
+impl<'a, T: Copy + 'a, S: Strategy> Deref for SeqLockPreviousGuard<'a, T, S> {
+    type Target = T;
+    #[inline]
+    fn deref(&self) -> &T {
+        &self.guard
+    }
+}
+
+#[cfg(not(feature = "critical-section"))]
+impl<'a, T: Copy + 'a, S: Strategy> DerefMut for SeqLockPreviousGuard<'a, T, S> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.guard
+    }
+}
+
+/// A write in progress, which is ended when dropped, even if the writer
+/// panics.
+///
+/// Under Miri, ThreadSanitizer, loom and shuttle, and with the
+/// `atomic-memcpy` feature, the writer modifies a private copy of the data,
+/// which is copied into the `SeqLock` when the write ends. This way concurrent
+/// readers never access the data while a mutable reference to it exists, and
+/// all accesses racing with readers are atomic.
+struct WriteScope<'a, T, S: Strategy = Seq> {
+    seqlock: &'a SeqLock<T, S>,
+    seq: usize,
+    #[cfg(any(miri, tsan, kani, feature = "atomic-memcpy", loom, shuttle))]
+    value: MaybeUninit<T>,
+}
+
+impl<'a, T, S: Strategy> WriteScope<'a, T, S> {
+    /// Begins a write. The caller must ensure that no other writer is active.
+    #[inline]
+    unsafe fn begin(seqlock: &'a SeqLock<T, S>, caller: location::Caller) -> WriteScope<'a, T, S> {
+        WriteScope {
+            seq: seqlock.begin_write(caller),
+            #[cfg(any(miri, tsan, kani, feature = "atomic-memcpy", loom, shuttle))]
+            value: seqlock.data.load(),
+            seqlock,
+        }
+    }
+
+    #[cfg_attr(feature = "critical-section", allow(dead_code))]
+    #[inline]
+    fn get(&self) -> &T {
+        #[cfg(not(any(miri, tsan, kani, feature = "atomic-memcpy", loom, shuttle)))]
+        unsafe {
+            &*self.seqlock.data.as_ptr()
+        }
+        #[cfg(any(miri, tsan, kani, feature = "atomic-memcpy", loom, shuttle))]
+        unsafe {
+            self.value.assume_init_ref()
+        }
+    }
+
+    #[inline]
+    fn get_mut(&mut self) -> &mut T {
+        #[cfg(not(any(miri, tsan, kani, feature = "atomic-memcpy", loom, shuttle)))]
+        unsafe {
+            &mut *self.seqlock.data.as_ptr()
+        }
+        #[cfg(any(miri, tsan, kani, feature = "atomic-memcpy", loom, shuttle))]
+        unsafe {
+            self.value.assume_init_mut()
+        }
+    }
+}
+
+impl<T, S: Strategy> Drop for WriteScope<'_, T, S> {
+    #[inline]
+    fn drop(&mut self) {
+        #[cfg(any(miri, tsan, kani, feature = "atomic-memcpy", loom, shuttle))]
+        unsafe {
+            self.seqlock.data.store(self.value.as_ptr());
+        }
         self.seqlock.end_write(self.seq);
     }
 }
@@ -0,0 +1,59 @@
+//! The call site of the last write, recorded by the `write-location` feature.
+
+#[cfg(feature = "write-location")]
+use crate::sync::{order, AtomicPtr, Ordering};
+#[cfg(feature = "write-location")]
+use core::panic::Location;
+
+/// The call site of a write, handed down from the public method which
+/// started it. This is empty without the `write-location` feature.
+#[derive(Clone, Copy)]
+pub(crate) struct Caller {
+    #[cfg(feature = "write-location")]
+    location: &'static Location<'static>,
+}
+
+impl Caller {
+    /// Returns the caller of the function this is called from, which must
+    /// itself track its caller for this to be outside the crate.
+    #[cfg_attr(feature = "write-location", track_caller)]
+    #[inline(always)]
+    pub(crate) fn get() -> Caller {
+        Caller {
+            #[cfg(feature = "write-location")]
+            location: Location::caller(),
+        }
+    }
+}
+
+#[cfg(feature = "write-location")]
+pub(crate) struct LastWriter {
+    // Null until the first write.
+    location: AtomicPtr<Location<'static>>,
+}
+
+#[cfg(feature = "write-location")]
+impl LastWriter {
+    const_fn! {
+        #[inline]
+        pub(crate) fn new() -> LastWriter {
+            LastWriter {
+                location: AtomicPtr::new(core::ptr::null_mut()),
+            }
+        }
+    }
+
+    /// Called by a writer once it has made the sequence number odd.
+    #[inline]
+    pub(crate) fn record(&self, caller: Caller) {
+        let location = caller.location as *const Location<'static>;
+        self.location
+            .store(location.cast_mut(), order(Ordering::Relaxed));
+    }
+
+    #[inline]
+    pub(crate) fn get(&self) -> Option<&'static Location<'static>> {
+        // Only ever set to a `&'static Location`.
+        unsafe { self.location.load(order(Ordering::Relaxed)).as_ref() }
+    }
+}
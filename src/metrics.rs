@@ -0,0 +1,171 @@
+//! Activity counters enabled by the `metrics` feature.
+
+use crate::sync::{order, AtomicU64, Ordering};
+use core::time::Duration;
+
+/// A snapshot of the activity counters of a `SeqLock`.
+///
+/// The fields are gathered one after the other and are therefore not
+/// mutually consistent if the lock is being used concurrently.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SeqLockStats {
+    /// Number of writes which have completed.
+    pub writes: u64,
+    /// Number of times a reader had to retry because of a concurrent write.
+    pub read_retries: u64,
+    /// Current value of the sequence counter.
+    pub current_seq: usize,
+    /// Whether a writer currently holds the lock.
+    pub write_in_progress: bool,
+}
+
+pub(crate) struct Counters {
+    writes: AtomicU64,
+    read_retries: AtomicU64,
+    writer_waits: AtomicU64,
+    timer: WriteTimer,
+}
+
+impl Counters {
+    const_fn! {
+        #[inline]
+        pub(crate) fn new() -> Counters {
+            Counters {
+                writes: AtomicU64::new(0),
+                read_retries: AtomicU64::new(0),
+                writer_waits: AtomicU64::new(0),
+                timer: WriteTimer::new(),
+            }
+        }
+    }
+
+    /// Called by a writer once it has made the sequence number odd.
+    ///
+    /// # Safety
+    ///
+    /// Other writers must be excluded until the matching `record_write`.
+    #[inline]
+    pub(crate) unsafe fn record_write_start(&self) {
+        self.timer.start();
+    }
+
+    /// Called by a writer once it has published its write.
+    ///
+    /// # Safety
+    ///
+    /// Other writers must still be excluded.
+    #[inline]
+    pub(crate) unsafe fn record_write(&self) {
+        self.timer.stop();
+        self.writes.fetch_add(1, order(Ordering::Relaxed));
+    }
+
+    #[inline]
+    pub(crate) fn max_write_duration(&self) -> Duration {
+        self.timer.max()
+    }
+
+    #[inline]
+    pub(crate) fn record_read_retry(&self) {
+        self.read_retries.fetch_add(1, order(Ordering::Relaxed));
+    }
+
+    /// Called by a writer which found the writer mutex held, before waiting
+    /// for it.
+    #[inline]
+    pub(crate) fn record_writer_wait(&self) {
+        self.writer_waits.fetch_add(1, order(Ordering::Relaxed));
+    }
+
+    #[inline]
+    pub(crate) fn writer_waits(&self) -> u64 {
+        self.writer_waits.load(order(Ordering::Relaxed))
+    }
+
+    #[inline]
+    pub(crate) fn stats(&self, seq: usize) -> SeqLockStats {
+        SeqLockStats {
+            writes: self.writes.load(order(Ordering::Relaxed)),
+            read_retries: self.read_retries.load(order(Ordering::Relaxed)),
+            current_seq: seq,
+            write_in_progress: seq & 1 != 0,
+        }
+    }
+}
+
+// `Instant` needs the standard library, and panics on wasm32-unknown-unknown.
+#[cfg(all(
+    feature = "std",
+    not(all(target_family = "wasm", target_os = "unknown"))
+))]
+struct WriteTimer {
+    // Only accessed by the writer, which excludes other writers.
+    started: core::cell::UnsafeCell<Option<std::time::Instant>>,
+    max_nanos: AtomicU64,
+}
+
+#[cfg(all(
+    feature = "std",
+    not(all(target_family = "wasm", target_os = "unknown"))
+))]
+impl WriteTimer {
+    const_fn! {
+        #[inline]
+        fn new() -> WriteTimer {
+            WriteTimer {
+                started: core::cell::UnsafeCell::new(None),
+                max_nanos: AtomicU64::new(0),
+            }
+        }
+    }
+
+    #[inline]
+    unsafe fn start(&self) {
+        *self.started.get() = Some(std::time::Instant::now());
+    }
+
+    #[inline]
+    unsafe fn stop(&self) {
+        if let Some(started) = (*self.started.get()).take() {
+            let nanos = u64::try_from(started.elapsed().as_nanos()).unwrap_or(u64::MAX);
+            // Writers are excluded, so nothing else updates the maximum.
+            if nanos > self.max_nanos.load(order(Ordering::Relaxed)) {
+                self.max_nanos.store(nanos, order(Ordering::Relaxed));
+            }
+        }
+    }
+
+    #[inline]
+    fn max(&self) -> Duration {
+        Duration::from_nanos(self.max_nanos.load(order(Ordering::Relaxed)))
+    }
+}
+
+// Without a clock, writes aren't timed.
+#[cfg(not(all(
+    feature = "std",
+    not(all(target_family = "wasm", target_os = "unknown"))
+)))]
+struct WriteTimer;
+
+#[cfg(not(all(
+    feature = "std",
+    not(all(target_family = "wasm", target_os = "unknown"))
+)))]
+impl WriteTimer {
+    #[inline]
+    const fn new() -> WriteTimer {
+        WriteTimer
+    }
+
+    #[inline]
+    unsafe fn start(&self) {}
+
+    #[inline]
+    unsafe fn stop(&self) {}
+
+    #[inline]
+    fn max(&self) -> Duration {
+        Duration::ZERO
+    }
+}
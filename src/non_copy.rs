@@ -0,0 +1,111 @@
+//! Values which may be copied bit for bit without being `Copy`.
+
+use crate::{location, SeqLock, Strategy};
+use core::convert::Infallible;
+use core::mem::MaybeUninit;
+
+/// Marks types whose values may be duplicated by copying their bytes, even
+/// though they don't implement `Copy`.
+///
+/// A reader of a `SeqLock` copies the value out while a writer may be
+/// replacing it, and only keeps the copy if no write raced with it. Every
+/// value which is read is therefore a byte-for-byte duplicate of the stored
+/// one, and old values are overwritten without being dropped. This is what
+/// `Copy` promises, but a type may not be able to implement `Copy`, e.g.
+/// because it is meant to be moved around explicitly in the rest of the
+/// program. Implementing this trait for it allows it to be stored in a
+/// `SeqLock` with `SeqLock::new_bitwise`, and accessed with
+/// `read_bitwise` and `write_bitwise`.
+///
+/// Every `Copy` type implements this trait.
+///
+/// # Safety
+///
+/// A bitwise copy of a value must be a valid value which is independent of
+/// the original, which rules out references and pointers to owned data or to
+/// the value itself. The type must not need to be dropped, as checked by
+/// `core::mem::needs_drop`, since the stored values never are.
+///
+/// When the data is copied with atomics (see the crate documentation), the
+/// type must also have no padding bytes and no pointers at all, like any
+/// other value stored in a `SeqLock`.
+///
+/// # Examples
+///
+/// ```
+/// use seqlock::{SeqLock, SeqlockSafe};
+///
+/// // A measurement which shouldn't be duplicated by accident.
+/// struct Sample {
+///     time: u64,
+///     value: u64,
+/// }
+///
+/// unsafe impl SeqlockSafe for Sample {}
+///
+/// let lock = SeqLock::new_bitwise(Sample { time: 0, value: 1 });
+/// lock.write_bitwise(Sample { time: 1, value: 2 });
+/// let sample = lock.read_bitwise();
+/// assert_eq!((sample.time, sample.value), (1, 2));
+/// ```
+pub unsafe trait SeqlockSafe {}
+
+unsafe impl<T: Copy> SeqlockSafe for T {}
+
+impl<T: SeqlockSafe> SeqLock<T> {
+    const_fn! {
+        /// Creates a new SeqLock with the given initial value, which need not
+        /// be `Copy`.
+        ///
+        /// Using this with a `T` which needs to be dropped, which
+        /// `SeqlockSafe` rules out, fails to compile:
+        ///
+        /// ```compile_fail
+        /// use seqlock::{SeqLock, SeqlockSafe};
+        ///
+        /// struct Noisy;
+        /// impl Drop for Noisy {
+        ///     fn drop(&mut self) {}
+        /// }
+        /// unsafe impl SeqlockSafe for Noisy {}
+        ///
+        /// let _ = SeqLock::new_bitwise(Noisy);
+        /// ```
+        #[inline]
+        pub fn new_bitwise(val: T) -> SeqLock<T> {
+            const {
+                assert!(
+                    !core::mem::needs_drop::<T>(),
+                    "SeqlockSafe types must not need to be dropped"
+                )
+            };
+            SeqLock::with_parts(val, 0)
+        }
+    }
+}
+
+impl<T: SeqlockSafe, S: Strategy> SeqLock<T, S> {
+    /// Reads the value protected by the `SeqLock`, returning a bitwise copy
+    /// of it.
+    ///
+    /// This is the same as `read`, for types which are `SeqlockSafe` but not
+    /// `Copy`.
+    #[inline]
+    pub fn read_bitwise(&self) -> T {
+        let (val, _): (MaybeUninit<T>, _) =
+            self.read_versioned_with(|| unsafe { self.data.load() });
+        // An untorn copy of a `SeqlockSafe` value is a valid value.
+        unsafe { val.assume_init() }
+    }
+
+    /// Replaces the value protected by the `SeqLock`.
+    ///
+    /// This is the same as `write`, for types which are `SeqlockSafe` but not
+    /// `Copy`. The previous value is overwritten without being dropped.
+    #[inline]
+    #[cfg_attr(feature = "write-location", track_caller)]
+    pub fn write_bitwise(&self, val: T) {
+        let Ok(()) =
+            self.write_exclusive::<_, Infallible>(location::Caller::get(), || Ok((val, ())));
+    }
+}
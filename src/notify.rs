@@ -0,0 +1,102 @@
+//! Waker registration used to notify pollers when a `SeqLock` is written.
+
+use crate::sync::{fence, order, AtomicBool, AtomicUsize, Mutex, Ordering};
+use alloc::vec::Vec;
+use core::task::Waker;
+
+/// Registration key shared by one-shot pollers, which are never unregistered.
+pub(crate) const ANONYMOUS: usize = 0;
+
+/// A set of wakers waiting for the next write to a `SeqLock`.
+pub(crate) struct WakerSet {
+    // Fast-path flag checked by writers so that publishing a value with no
+    // registered wakers only costs a single relaxed load.
+    has_wakers: AtomicBool,
+    // Each waker is tagged with the key of the poller which registered it, so
+    // that long-lived pollers can remove their own entry when dropped without
+    // affecting other pollers in the same task.
+    wakers: Mutex<Vec<(usize, Waker)>>,
+    next_key: AtomicUsize,
+}
+
+impl WakerSet {
+    #[inline]
+    pub(crate) const fn new() -> WakerSet {
+        WakerSet {
+            has_wakers: AtomicBool::new(false),
+            wakers: Mutex::new(Vec::new()),
+            next_key: AtomicUsize::new(ANONYMOUS + 1),
+        }
+    }
+
+    /// Allocates a key for a poller which will later call `unregister`.
+    #[inline]
+    pub(crate) fn new_key(&self) -> usize {
+        self.next_key.fetch_add(1, order(Ordering::Relaxed))
+    }
+
+    /// Registers a waker to be woken by the next write.
+    ///
+    /// The caller must re-check the sequence number after this returns,
+    /// otherwise a write which completed just before registration would be
+    /// missed.
+    pub(crate) fn register(&self, key: usize, waker: &Waker) {
+        {
+            let mut wakers = self.wakers.lock();
+            match wakers
+                .iter_mut()
+                .find(|(k, w)| *k == key && (key != ANONYMOUS || w.will_wake(waker)))
+            {
+                // A keyed poller may have moved to a different task.
+                Some((_, w)) => {
+                    if !w.will_wake(waker) {
+                        *w = waker.clone();
+                    }
+                }
+                None => wakers.push((key, waker.clone())),
+            }
+            self.has_wakers.store(true, order(Ordering::Relaxed));
+        }
+
+        // This pairs with the fence in `wake_all`: either the writer sees our
+        // flag, or our subsequent re-check of the sequence number sees the
+        // writer's store.
+        fence(order(Ordering::SeqCst));
+    }
+
+    /// Removes the waker registered with `key`, if it hasn't been woken yet.
+    pub(crate) fn unregister(&self, key: usize) {
+        if !self.has_wakers.load(order(Ordering::Relaxed)) {
+            return;
+        }
+        let mut wakers = self.wakers.lock();
+        wakers.retain(|(k, _)| *k != key);
+        if wakers.is_empty() {
+            self.has_wakers.store(false, order(Ordering::Relaxed));
+        }
+    }
+
+    /// Wakes all registered wakers. Must be called after the new sequence
+    /// number has been stored.
+    #[inline]
+    pub(crate) fn wake_all(&self) {
+        fence(order(Ordering::SeqCst));
+        if self.has_wakers.load(order(Ordering::Relaxed)) {
+            self.wake_all_slow();
+        }
+    }
+
+    #[cold]
+    fn wake_all_slow(&self) {
+        let wakers = {
+            let mut wakers = self.wakers.lock();
+            self.has_wakers.store(false, order(Ordering::Relaxed));
+            core::mem::take(&mut *wakers)
+        };
+
+        // Wake outside the lock in case a waker re-registers itself.
+        for (_, waker) in wakers {
+            waker.wake();
+        }
+    }
+}
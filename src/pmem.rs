@@ -0,0 +1,58 @@
+//! Cache line flushing used to make writes durable on persistent memory.
+
+use crate::sync::order;
+use core::arch::asm;
+#[cfg(target_arch = "x86")]
+use core::arch::x86::{__cpuid, __cpuid_count, _mm_clflush, _mm_sfence};
+#[cfg(target_arch = "x86_64")]
+use core::arch::x86_64::{__cpuid, __cpuid_count, _mm_clflush, _mm_sfence};
+use core::sync::atomic::{AtomicU8, Ordering};
+
+#[cfg(not(any(target_arch = "x86", target_arch = "x86_64")))]
+compile_error!("the `pmem` feature is only supported on x86 and x86_64");
+
+// Conservative flush granularity. Flushing the same line twice is harmless.
+const CACHE_LINE_SIZE: usize = 64;
+
+/// Flushes every cache line covering `len` bytes starting at `ptr` to the
+/// persistence domain and waits for the flushes to complete.
+#[inline]
+pub(crate) fn persist(ptr: *const u8, len: usize) {
+    if len == 0 {
+        return;
+    }
+
+    // clflushopt is weakly ordered and much cheaper than clflush when flushing
+    // several lines, but isn't available on all CPUs.
+    let clflushopt = has_clflushopt();
+    let offset = ptr as usize & (CACHE_LINE_SIZE - 1);
+    let first_line = ptr.wrapping_sub(offset);
+    for line_offset in (0..offset + len).step_by(CACHE_LINE_SIZE) {
+        let line = first_line.wrapping_add(line_offset);
+        unsafe {
+            if clflushopt {
+                asm!("clflushopt [{}]", in(reg) line, options(nostack, preserves_flags));
+            } else {
+                _mm_clflush(line);
+            }
+        }
+    }
+    unsafe { _mm_sfence() };
+}
+
+#[inline]
+fn has_clflushopt() -> bool {
+    // 0 = not yet detected, 1 = unsupported, 2 = supported
+    static CACHED: AtomicU8 = AtomicU8::new(0);
+    match CACHED.load(order(Ordering::Relaxed)) {
+        0 => {
+            // CPUID.(EAX=07H, ECX=0):EBX.CLFLUSHOPT[bit 23]
+            #[allow(unused_unsafe)]
+            let supported =
+                unsafe { __cpuid(0).eax >= 7 && __cpuid_count(7, 0).ebx & (1 << 23) != 0 };
+            CACHED.store(1 + supported as u8, order(Ordering::Relaxed));
+            supported
+        }
+        cached => cached == 2,
+    }
+}
@@ -0,0 +1,230 @@
+//! Cached reader-side snapshots of a `SeqLock`, and copies of its value
+//! through either kind of reference.
+
+use crate::{Seq, SeqLock, Strategy};
+use core::cell::Cell;
+use core::fmt;
+use core::ops::Deref;
+
+/// A consistent copy of the value in a `SeqLock`, cached for repeated access.
+///
+/// This is created by [`SeqLock::read_guard`]. Dereferencing it is just a
+/// plain memory access: it does not look at the `SeqLock` again, and so does
+/// not observe later writes until [`refresh`](ReadSnapshot::refresh) is
+/// called. It does not block writers in any way.
+pub struct ReadSnapshot<'a, T, S: Strategy = Seq> {
+    seqlock: &'a SeqLock<T, S>,
+    value: T,
+    seq: usize,
+}
+
+impl<'a, T: Copy, S: Strategy> ReadSnapshot<'a, T, S> {
+    #[inline]
+    pub(crate) fn new(seqlock: &'a SeqLock<T, S>) -> ReadSnapshot<'a, T, S> {
+        let (value, seq) = seqlock.read_versioned();
+        ReadSnapshot {
+            seqlock,
+            value,
+            seq,
+        }
+    }
+
+    /// Creates a snapshot of `value`, which was published at `seq`.
+    #[cfg(not(feature = "critical-section"))]
+    #[inline]
+    pub(crate) fn from_parts(
+        seqlock: &'a SeqLock<T, S>,
+        value: T,
+        seq: usize,
+    ) -> ReadSnapshot<'a, T, S> {
+        ReadSnapshot {
+            seqlock,
+            value,
+            seq,
+        }
+    }
+
+    /// Re-reads the value from the `SeqLock`, replacing the cached copy.
+    ///
+    /// Returns `true` if the `SeqLock` was written since the snapshot was
+    /// last taken.
+    #[inline]
+    pub fn refresh(&mut self) -> bool {
+        let (value, seq) = self.seqlock.read_versioned();
+        self.value = value;
+        let changed = seq != self.seq;
+        self.seq = seq;
+        changed
+    }
+}
+
+impl<T, S: Strategy> ReadSnapshot<'_, T, S> {
+    /// Returns the sequence number the cached value was read at.
+    #[inline]
+    pub fn seq(&self) -> usize {
+        self.seq
+    }
+
+    /// Returns whether the `SeqLock` has been written, or is being written,
+    /// since the snapshot was taken.
+    ///
+    /// This only loads the sequence number, so it is cheaper than a
+    /// `refresh`. The result may be out of date as soon as it is returned.
+    #[inline]
+    pub fn is_stale(&self) -> bool {
+        self.seqlock.current_seq() != self.seq
+    }
+}
+
+impl<T, S: Strategy> Deref for ReadSnapshot<'_, T, S> {
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+impl<T: fmt::Debug, S: Strategy> fmt::Debug for ReadSnapshot<'_, T, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ReadSnapshot")
+            .field("value", &self.value)
+            .field("seq", &self.seq)
+            .finish()
+    }
+}
+
+/// Reads from one version of the value in a `SeqLock`.
+///
+/// This is passed to the closure given to [`SeqLock::read_consistent`],
+/// which is run again if any of its reads overlaps a write.
+pub struct ConsistentReader<'a, T, S: Strategy = Seq> {
+    seqlock: &'a SeqLock<T, S>,
+    seq: usize,
+    // The copy made with writers excluded, for `MutexRef`.
+    locked: Option<T>,
+    torn: Cell<bool>,
+}
+
+impl<'a, T: Copy, S: Strategy> ConsistentReader<'a, T, S> {
+    #[inline]
+    pub(crate) fn new(
+        seqlock: &'a SeqLock<T, S>,
+        seq: usize,
+        locked: Option<T>,
+    ) -> ConsistentReader<'a, T, S> {
+        ConsistentReader {
+            seqlock,
+            seq,
+            locked,
+            torn: Cell::new(false),
+        }
+    }
+
+    /// Copies the value and returns the result of `f` on it.
+    ///
+    /// The whole value is copied on every call, not just the part that `f`
+    /// looks at.
+    ///
+    /// `f` always gets a consistent value. If the value was written since
+    /// the closure passed to `read_consistent` started, that value is from a
+    /// later version, and the closure's result is discarded.
+    #[inline]
+    pub fn map<U>(&self, f: impl FnOnce(&T) -> U) -> U {
+        if let Some(value) = &self.locked {
+            return f(value);
+        }
+        if !self.torn.get() {
+            if let Some(value) = self.seqlock.copy_at(self.seq) {
+                return f(&value);
+            }
+            self.torn.set(true);
+        }
+        f(&self.seqlock.read())
+    }
+
+    #[inline]
+    pub(crate) fn is_torn(&self) -> bool {
+        self.torn.get()
+    }
+}
+
+impl<T, S: Strategy> ConsistentReader<'_, T, S> {
+    /// Returns the sequence number of the version being read.
+    #[inline]
+    pub fn seq(&self) -> usize {
+        self.seq
+    }
+}
+
+impl<T, S: Strategy> fmt::Debug for ConsistentReader<'_, T, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ConsistentReader")
+            .field("seq", &self.seq)
+            .finish_non_exhaustive()
+    }
+}
+
+/// A shared or exclusive reference to a `SeqLock`, which generic code can
+/// take a copy of the value through in the cheapest way available.
+///
+/// Through `&SeqLock` this is a normal `read`. Through `&mut SeqLock` no
+/// writer can be active, so the value is copied directly, as with `get_mut`,
+/// without looking at the sequence number. This is a sealed trait
+/// implemented for just these two.
+///
+/// # Examples
+///
+/// ```
+/// use seqlock::{SeqLock, Snapshot};
+///
+/// fn sum(lock: impl Snapshot<Value = [u32; 4]>) -> u32 {
+///     lock.snapshot().iter().sum()
+/// }
+///
+/// let mut lock = SeqLock::new([1, 2, 3, 4]);
+/// assert_eq!(sum(&lock), 10);
+/// assert_eq!(sum(&mut lock), 10);
+/// ```
+pub trait Snapshot: sealed::Sealed {
+    /// The type of the value in the `SeqLock`.
+    type Value;
+
+    /// Returns a consistent copy of the value in the `SeqLock`.
+    fn snapshot(self) -> Self::Value;
+}
+
+impl<T: Copy, S: Strategy> Snapshot for &SeqLock<T, S> {
+    type Value = T;
+
+    #[inline]
+    fn snapshot(self) -> T {
+        self.read()
+    }
+}
+
+impl<T: Copy, S: Strategy> Snapshot for &mut SeqLock<T, S> {
+    type Value = T;
+
+    #[inline]
+    fn snapshot(self) -> T {
+        #[cfg(not(any(loom, shuttle)))]
+        {
+            *self.get_mut()
+        }
+
+        // The data is stored in atomics, see `get_mut`.
+        #[cfg(any(loom, shuttle))]
+        {
+            self.read()
+        }
+    }
+}
+
+mod sealed {
+    use crate::{SeqLock, Strategy};
+
+    pub trait Sealed {}
+    impl<T, S: Strategy> Sealed for &SeqLock<T, S> {}
+    impl<T, S: Strategy> Sealed for &mut SeqLock<T, S> {}
+}
@@ -0,0 +1,146 @@
+//! A mutex-free `SeqLock` for values with a single writer.
+
+use crate::access::DataCell;
+use crate::sync::AtomicUsize;
+use core::fmt;
+
+/// A sequential lock without a writer mutex, for values which are only ever
+/// written by one thread at a time.
+///
+/// This is intended for configuration which is written once at startup (or
+/// rarely, from a single thread) and read from everywhere afterwards, such as
+/// a `static`. Readers use the same protocol as [`SeqLock`], but since there
+/// is no mutex, writes are `unsafe`: the caller must guarantee that no other
+/// write runs concurrently.
+///
+/// [`SeqLock`]: crate::SeqLock
+///
+/// # Examples
+///
+/// ```
+/// use seqlock::SeqLockStatic;
+/// use std::thread;
+///
+/// #[derive(Clone, Copy, Debug, PartialEq)]
+/// struct Config {
+///     timeout_ms: u32,
+///     retries: u32,
+/// }
+///
+/// static CONFIG: SeqLockStatic<Config> = SeqLockStatic::new(Config {
+///     timeout_ms: 100,
+///     retries: 3,
+/// });
+///
+/// // Only the main thread writes to `CONFIG`.
+/// unsafe {
+///     CONFIG.write(Config {
+///         timeout_ms: 500,
+///         retries: 5,
+///     })
+/// };
+///
+/// let readers: Vec<_> = (0..4)
+///     .map(|_| thread::spawn(|| CONFIG.read()))
+///     .collect();
+/// for reader in readers {
+///     assert_eq!(reader.join().unwrap(), Config { timeout_ms: 500, retries: 5 });
+/// }
+/// ```
+pub struct SeqLockStatic<T> {
+    seq: AtomicUsize,
+    data: DataCell<T>,
+}
+
+unsafe impl<T: Send> Send for SeqLockStatic<T> {}
+unsafe impl<T: Send> Sync for SeqLockStatic<T> {}
+
+impl<T: Copy> SeqLockStatic<T> {
+    const_fn! {
+        /// Creates a new `SeqLockStatic` with the given initial value.
+        #[inline]
+        pub fn new(val: T) -> SeqLockStatic<T> {
+            SeqLockStatic {
+                seq: AtomicUsize::new(0),
+                data: DataCell::new(val),
+            }
+        }
+    }
+
+    /// Reads the value protected by the `SeqLockStatic`.
+    ///
+    /// If the writer is currently modifying the value then the calling thread
+    /// will wait until the write is complete.
+    #[inline]
+    pub fn read(&self) -> T {
+        self.read_versioned().0
+    }
+
+    /// Reads the value along with the sequence number it was read at.
+    ///
+    /// See `SeqLock::read_versioned`.
+    #[inline]
+    pub fn read_versioned(&self) -> (T, usize) {
+        crate::read_versioned(&self.seq, &self.data)
+    }
+
+    /// Replaces the value protected by the `SeqLockStatic`.
+    ///
+    /// # Safety
+    ///
+    /// No other call to `write` may run concurrently with this one, on any
+    /// thread. Concurrent reads are fine.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use seqlock::SeqLockStatic;
+    /// use std::thread;
+    ///
+    /// static LOCK: SeqLockStatic<[u64; 8]> = SeqLockStatic::new([0; 8]);
+    ///
+    /// thread::scope(|s| {
+    ///     // The only writer.
+    ///     s.spawn(|| {
+    ///         for i in 1..=100 {
+    ///             unsafe { LOCK.write([i; 8]) };
+    ///         }
+    ///     });
+    ///     for _ in 0..2 {
+    ///         s.spawn(|| loop {
+    ///             let data = LOCK.read();
+    ///             assert!(data.iter().all(|&x| x == data[0]));
+    ///             if data[0] == 100 {
+    ///                 break;
+    ///             }
+    ///         });
+    ///     }
+    /// });
+    /// ```
+    #[inline]
+    pub unsafe fn write(&self, val: T) {
+        // The caller guarantees that there are no other writers.
+        let seq = crate::begin_write(&self.seq);
+        self.data.store(&val);
+        crate::end_write(&self.seq, seq);
+    }
+
+    /// Consumes this `SeqLockStatic`, returning the underlying data.
+    #[inline]
+    pub fn into_inner(self) -> T {
+        self.data.into_inner()
+    }
+}
+
+impl<T: Copy + Default> Default for SeqLockStatic<T> {
+    #[inline]
+    fn default() -> SeqLockStatic<T> {
+        SeqLockStatic::new(Default::default())
+    }
+}
+
+impl<T: Copy + fmt::Debug> fmt::Debug for SeqLockStatic<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "SeqLockStatic {{ data: {:?} }}", &self.read())
+    }
+}
@@ -0,0 +1,103 @@
+//! Strategies selecting how the readers of a `SeqLock` are synchronized.
+
+/// How the readers of a `SeqLock` are synchronized with its writers.
+///
+/// This is a sealed trait implemented by [`Seq`], the sequence lock
+/// algorithm, and [`MutexRef`], a deliberately simple reference
+/// implementation in which readers take the writer lock. Both provide the
+/// same results and the same API, except for methods which depend on the
+/// timing of the sequence number, such as `read_watchdog` and the async
+/// change notifications, which are only available with `Seq`.
+///
+/// `MutexRef` is meant for bisecting bugs: flipping the strategy parameter
+/// of a `SeqLock` (typically in a type alias) and rebuilding tells whether
+/// a problem is caused by the optimistic reads of the sequence lock. It is
+/// much slower than `Seq` and offers no other advantage.
+///
+/// The strategy is a type parameter so that the read path doesn't have to
+/// branch on it. Where it has to be chosen at runtime, an enum over the
+/// strategies gives a uniform API at the cost of a branch per operation:
+///
+/// ```
+/// use seqlock::{MutexRef, Seq, SeqLock};
+///
+/// enum AnyLock<T: Copy> {
+///     Seq(SeqLock<T, Seq>),
+///     MutexRef(SeqLock<T, MutexRef>),
+/// }
+///
+/// impl<T: Copy> AnyLock<T> {
+///     fn new(val: T, reference: bool) -> Self {
+///         if reference {
+///             AnyLock::MutexRef(SeqLock::with_strategy(val))
+///         } else {
+///             AnyLock::Seq(SeqLock::with_strategy(val))
+///         }
+///     }
+///
+///     fn read(&self) -> T {
+///         match self {
+///             AnyLock::Seq(lock) => lock.read(),
+///             AnyLock::MutexRef(lock) => lock.read(),
+///         }
+///     }
+///
+///     fn write(&self, val: T) {
+///         match self {
+///             AnyLock::Seq(lock) => lock.write(val),
+///             AnyLock::MutexRef(lock) => lock.write(val),
+///         }
+///     }
+/// }
+///
+/// for reference in [false, true] {
+///     let lock = AnyLock::new([0u8; 64], reference);
+///     lock.write([1; 64]);
+///     assert_eq!(lock.read(), [1; 64]);
+/// }
+/// ```
+pub trait Strategy: sealed::Sealed {
+    // Whether readers take the writer lock instead of validating their copy
+    // with the sequence number.
+    #[doc(hidden)]
+    const LOCKED_READS: bool;
+}
+
+/// The sequence lock algorithm, the default strategy of `SeqLock`.
+///
+/// Readers copy the data without synchronizing with writers and retry if
+/// the sequence number shows that a write overlapped the copy.
+#[derive(Debug)]
+pub enum Seq {}
+
+/// A reference strategy in which every read takes the writer lock.
+///
+/// Reads exclude, and wait for, writers, and are therefore never torn or
+/// retried. With the `critical-section` feature readers enter the critical
+/// section instead. See [`Strategy`] for what this is useful for.
+///
+/// # Examples
+///
+/// ```
+/// use seqlock::{MutexRef, SeqLock};
+///
+/// let lock = SeqLock::<_, MutexRef>::with_strategy([1, 2, 3]);
+/// lock.write([4, 5, 6]);
+/// assert_eq!(lock.read_versioned(), ([4, 5, 6], 2));
+/// ```
+#[derive(Debug)]
+pub enum MutexRef {}
+
+impl Strategy for Seq {
+    const LOCKED_READS: bool = false;
+}
+
+impl Strategy for MutexRef {
+    const LOCKED_READS: bool = true;
+}
+
+mod sealed {
+    pub trait Sealed {}
+    impl Sealed for super::Seq {}
+    impl Sealed for super::MutexRef {}
+}
@@ -0,0 +1,381 @@
+//! Synchronization primitives used by the lock, selected based on the enabled
+//! features.
+//!
+//! With the `std` feature the writer mutex is a `parking_lot::Mutex` and
+//! readers yield to the OS scheduler while a write is in progress. Without it
+//! the writer mutex is a simple spinlock and readers simply spin.
+//!
+//! WebAssembly always uses the spinlock and spinning readers, because
+//! blocking is not allowed on the main thread of a browser.
+//!
+//! With the `portable-atomic` feature all atomic types and fences come from
+//! the `portable-atomic` crate, which supports targets lacking native atomic
+//! read-modify-write operations.
+//!
+//! Targets without an atomic compare-exchange otherwise need the
+//! `critical-section` feature. The writer then doesn't use a mutex, and the
+//! spinlock used elsewhere in the crate takes its lock inside a critical
+//! section, which only needs atomic loads and stores.
+//!
+//! When built with `--cfg loom` or `--cfg shuttle`, the atomics, the writer
+//! mutex and the readers' yield all come from that model checker instead, so
+//! that the protocol can be tested under it. Both provide the same API, so
+//! they share a single shim through the `model` alias.
+//!
+//! Under Kani, which doesn't model threads, readers instead call into the
+//! simulated writer of the proof harnesses, see `verification`.
+
+#[cfg(not(any(
+    target_has_atomic = "8",
+    feature = "portable-atomic",
+    feature = "critical-section"
+)))]
+compile_error!(
+    "this target has no atomic compare-exchange: enable the `critical-section` \
+     or the `portable-atomic` feature of seqlock"
+);
+
+#[cfg(loom)]
+pub(crate) use ::loom as model;
+#[cfg(shuttle)]
+pub(crate) use ::shuttle as model;
+
+#[cfg(all(not(any(loom, shuttle)), not(feature = "portable-atomic")))]
+mod atomic {
+    pub(crate) use core::sync::atomic::AtomicBool;
+    #[cfg(feature = "write-location")]
+    pub(crate) use core::sync::atomic::AtomicPtr;
+    #[cfg(feature = "metrics")]
+    pub(crate) use core::sync::atomic::AtomicU64;
+    pub(crate) use core::sync::atomic::{fence, AtomicUsize, Ordering};
+}
+#[cfg(all(not(any(loom, shuttle)), feature = "portable-atomic"))]
+mod atomic {
+    pub(crate) use portable_atomic::AtomicBool;
+    #[cfg(feature = "write-location")]
+    pub(crate) use portable_atomic::AtomicPtr;
+    #[cfg(feature = "metrics")]
+    pub(crate) use portable_atomic::AtomicU64;
+    pub(crate) use portable_atomic::{fence, AtomicUsize, Ordering};
+}
+
+#[cfg(any(loom, shuttle))]
+mod atomic {
+    pub(crate) use super::model::sync::atomic::AtomicBool;
+    #[cfg(feature = "write-location")]
+    pub(crate) use super::model::sync::atomic::AtomicPtr;
+    #[cfg(feature = "metrics")]
+    pub(crate) use super::model::sync::atomic::AtomicU64;
+    pub(crate) use super::model::sync::atomic::{fence, AtomicUsize, Ordering};
+}
+
+pub(crate) use self::atomic::*;
+
+/// Returns the ordering an atomic operation should use, given the one it
+/// needs. With the `seqcst` feature every operation is `SeqCst` instead.
+///
+/// This is a debugging aid for telling memory ordering bugs apart from other
+/// bugs. It doesn't make any stronger guarantee to users of the crate.
+#[inline(always)]
+pub(crate) const fn order(order: Ordering) -> Ordering {
+    #[cfg(feature = "seqcst")]
+    {
+        let _ = order;
+        Ordering::SeqCst
+    }
+    #[cfg(not(feature = "seqcst"))]
+    order
+}
+
+// The writer doesn't use a mutex with the `critical-section` feature, but
+// other parts of the crate still might.
+#[cfg(any(loom, shuttle))]
+pub(crate) use self::model_mutex::{Mutex, MutexGuard};
+#[cfg(all(
+    not(any(loom, shuttle)),
+    any(not(feature = "std"), target_family = "wasm")
+))]
+#[allow(unused_imports)]
+pub(crate) use self::spin::{Mutex, MutexGuard};
+#[cfg(all(not(any(loom, shuttle)), feature = "std", not(target_family = "wasm")))]
+#[allow(unused_imports)]
+pub(crate) use parking_lot::{Mutex, MutexGuard};
+
+// The mutex serializing writers. With the `async` feature this is an async
+// mutex so that the blocking and async write paths exclude each other.
+#[cfg(all(not(feature = "async"), not(seqlock_unsafe_assume_single_writer)))]
+#[allow(unused_imports)]
+pub(crate) use self::{Mutex as WriterMutex, MutexGuard as WriterGuard};
+// With `--cfg seqlock_unsafe_assume_single_writer` writers are only checked
+// for reentrancy.
+#[cfg(seqlock_unsafe_assume_single_writer)]
+pub(crate) use self::unsync::{Mutex as WriterMutex, MutexGuard as WriterGuard};
+#[cfg(all(feature = "async", not(feature = "critical-section")))]
+pub(crate) use async_lock::futures::Lock as WriterLock;
+#[cfg(feature = "async")]
+#[allow(unused_imports)]
+pub(crate) use async_lock::{Mutex as WriterMutex, MutexGuard as WriterGuard};
+
+/// Locks the writer mutex, blocking the current thread.
+#[cfg(not(feature = "critical-section"))]
+#[inline]
+pub(crate) fn lock_writer(mutex: &WriterMutex<()>) -> WriterGuard<'_, ()> {
+    #[cfg(not(feature = "async"))]
+    return mutex.lock();
+
+    #[cfg(all(feature = "async", feature = "std", not(target_family = "wasm")))]
+    return mutex.lock_blocking();
+
+    // Blocking on the async mutex requires an OS, so spin instead.
+    #[cfg(all(feature = "async", any(not(feature = "std"), target_family = "wasm")))]
+    loop {
+        if let Some(guard) = mutex.try_lock() {
+            return guard;
+        }
+        core::hint::spin_loop();
+    }
+}
+
+/// Called by readers while waiting for a writer to finish.
+#[inline]
+pub(crate) fn relax() {
+    // Yield to give the writer a chance to finish. Writing is expected to be
+    // relatively rare anyways so this isn't too performance critical.
+    #[cfg(all(
+        not(any(loom, shuttle, kani)),
+        feature = "std",
+        not(target_family = "wasm")
+    ))]
+    std::thread::yield_now();
+    #[cfg(all(
+        not(any(loom, shuttle, kani)),
+        any(not(feature = "std"), target_family = "wasm")
+    ))]
+    core::hint::spin_loop();
+
+    // The model checkers require spin loops to yield so that they can
+    // schedule the writer.
+    #[cfg(any(loom, shuttle))]
+    model::thread::yield_now();
+
+    // Kani doesn't model threads, so run the simulated writer instead.
+    #[cfg(kani)]
+    crate::verification::yield_now();
+}
+
+/// Called by readers which busy-wait for a writer without yielding.
+#[inline]
+pub(crate) fn spin() {
+    #[cfg(not(any(loom, shuttle, kani)))]
+    core::hint::spin_loop();
+
+    // The model checkers still need a chance to schedule the writer.
+    #[cfg(any(loom, shuttle, kani))]
+    relax();
+}
+
+/// Called by readers between the steps of a read. Under Kani this is where
+/// the simulated writer may preempt them, otherwise it does nothing.
+#[cfg(kani)]
+pub(crate) use crate::verification::preempt;
+#[cfg(not(kani))]
+#[inline(always)]
+pub(crate) fn preempt() {}
+
+#[cfg(any(loom, shuttle))]
+mod model_mutex {
+    use super::model;
+
+    /// The model checker's mutex, with the same interface as
+    /// `parking_lot::Mutex`.
+    pub(crate) struct Mutex<T>(model::sync::Mutex<T>);
+
+    pub(crate) type MutexGuard<'a, T> = model::sync::MutexGuard<'a, T>;
+
+    impl<T> Mutex<T> {
+        #[inline]
+        pub(crate) fn new(val: T) -> Mutex<T> {
+            Mutex(model::sync::Mutex::new(val))
+        }
+
+        #[inline]
+        pub(crate) fn lock(&self) -> MutexGuard<'_, T> {
+            self.0.lock().unwrap()
+        }
+
+        #[inline]
+        pub(crate) fn try_lock(&self) -> Option<MutexGuard<'_, T>> {
+            self.0.try_lock().ok()
+        }
+    }
+}
+
+#[cfg(all(
+    not(any(loom, shuttle)),
+    any(not(feature = "std"), target_family = "wasm")
+))]
+mod spin {
+    use super::{order, AtomicBool, Ordering};
+    use core::cell::UnsafeCell;
+    use core::ops::{Deref, DerefMut};
+
+    /// A minimal spinlock with the same interface as `parking_lot::Mutex`.
+    pub(crate) struct Mutex<T> {
+        locked: AtomicBool,
+        data: UnsafeCell<T>,
+    }
+
+    unsafe impl<T: Send> Send for Mutex<T> {}
+    unsafe impl<T: Send> Sync for Mutex<T> {}
+
+    pub(crate) struct MutexGuard<'a, T> {
+        mutex: &'a Mutex<T>,
+    }
+
+    unsafe impl<T: Sync> Sync for MutexGuard<'_, T> {}
+
+    impl<T> Mutex<T> {
+        #[inline]
+        pub(crate) const fn new(val: T) -> Mutex<T> {
+            Mutex {
+                locked: AtomicBool::new(false),
+                data: UnsafeCell::new(val),
+            }
+        }
+
+        #[inline]
+        pub(crate) fn lock(&self) -> MutexGuard<'_, T> {
+            loop {
+                if let Some(guard) = self.try_lock() {
+                    return guard;
+                }
+
+                // Wait until the lock looks free before retrying the
+                // compare-exchange, to avoid bouncing the cache line.
+                while self.locked.load(order(Ordering::Relaxed)) {
+                    core::hint::spin_loop();
+                }
+            }
+        }
+
+        #[cfg(any(target_has_atomic = "8", feature = "portable-atomic"))]
+        #[inline]
+        pub(crate) fn try_lock(&self) -> Option<MutexGuard<'_, T>> {
+            self.locked
+                .compare_exchange(
+                    false,
+                    true,
+                    order(Ordering::Acquire),
+                    order(Ordering::Relaxed),
+                )
+                .ok()
+                .map(|_| MutexGuard { mutex: self })
+        }
+
+        // Without a native compare-exchange, the check and the store are
+        // made atomic by a critical section instead.
+        #[cfg(not(any(target_has_atomic = "8", feature = "portable-atomic")))]
+        #[inline]
+        pub(crate) fn try_lock(&self) -> Option<MutexGuard<'_, T>> {
+            critical_section::with(|_| {
+                if self.locked.load(order(Ordering::Relaxed)) {
+                    return None;
+                }
+                self.locked.store(true, order(Ordering::Relaxed));
+                Some(MutexGuard { mutex: self })
+            })
+        }
+    }
+
+    impl<T> Deref for MutexGuard<'_, T> {
+        type Target = T;
+        #[inline]
+        fn deref(&self) -> &T {
+            unsafe { &*self.mutex.data.get() }
+        }
+    }
+
+    impl<T> DerefMut for MutexGuard<'_, T> {
+        #[inline]
+        fn deref_mut(&mut self) -> &mut T {
+            unsafe { &mut *self.mutex.data.get() }
+        }
+    }
+
+    impl<T> Drop for MutexGuard<'_, T> {
+        #[inline]
+        fn drop(&mut self) {
+            self.mutex.locked.store(false, order(Ordering::Release));
+        }
+    }
+}
+
+#[cfg(seqlock_unsafe_assume_single_writer)]
+mod unsync {
+    use core::cell::{Cell, UnsafeCell};
+    use core::ops::{Deref, DerefMut};
+
+    /// A writer lock for `--cfg seqlock_unsafe_assume_single_writer`, with
+    /// the same interface as `parking_lot::Mutex`. It doesn't synchronize
+    /// with other threads, and only keeps a single thread from locking it
+    /// twice.
+    pub(crate) struct Mutex<T> {
+        locked: Cell<bool>,
+        data: UnsafeCell<T>,
+    }
+
+    pub(crate) struct MutexGuard<'a, T> {
+        mutex: &'a Mutex<T>,
+    }
+
+    impl<T> Mutex<T> {
+        #[inline]
+        pub(crate) const fn new(val: T) -> Mutex<T> {
+            Mutex {
+                locked: Cell::new(false),
+                data: UnsafeCell::new(val),
+            }
+        }
+
+        #[inline]
+        pub(crate) fn lock(&self) -> MutexGuard<'_, T> {
+            // Waiting would deadlock, since the only thread which could
+            // unlock the mutex is this one.
+            match self.try_lock() {
+                Some(guard) => guard,
+                None => panic!("SeqLock writer locked while already locked by this thread"),
+            }
+        }
+
+        #[inline]
+        pub(crate) fn try_lock(&self) -> Option<MutexGuard<'_, T>> {
+            if self.locked.replace(true) {
+                None
+            } else {
+                Some(MutexGuard { mutex: self })
+            }
+        }
+    }
+
+    impl<T> Deref for MutexGuard<'_, T> {
+        type Target = T;
+        #[inline]
+        fn deref(&self) -> &T {
+            unsafe { &*self.mutex.data.get() }
+        }
+    }
+
+    impl<T> DerefMut for MutexGuard<'_, T> {
+        #[inline]
+        fn deref_mut(&mut self) -> &mut T {
+            unsafe { &mut *self.mutex.data.get() }
+        }
+    }
+
+    impl<T> Drop for MutexGuard<'_, T> {
+        #[inline]
+        fn drop(&mut self) {
+            self.mutex.locked.set(false);
+        }
+    }
+}
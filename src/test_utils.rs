@@ -0,0 +1,221 @@
+//! Helpers for stress testing `SeqLock` and types wrapping it, enabled by the
+//! `test-utils` feature.
+//!
+//! Values are [`patterned_payload`]s: every word is derived from an id, so a
+//! torn copy is detected by [`assert_untorn`]. The [`stress`] driver runs
+//! readers and writers against any lock through closures and checks every
+//! value read, both for tearing and with [`WriteOrder`].
+//!
+//! ```
+//! use seqlock::test_utils::{patterned_payload, stress};
+//! use seqlock::SeqLock;
+//! use std::time::Duration;
+//!
+//! let lock = SeqLock::new(patterned_payload::<8>(0));
+//! let report = stress(
+//!     4,
+//!     2,
+//!     Duration::from_millis(100),
+//!     1000,
+//!     || lock.read(),
+//!     |val| lock.write(val),
+//! );
+//! assert_eq!(lock.current_seq() as u64, 2 * report.writes);
+//! ```
+
+use std::panic;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+use std::vec::Vec;
+
+// Odd, so that every word of a payload differs from the id.
+const SALT: u64 = 0x9e37_79b9_7f4a_7c15;
+
+/// Returns the payload with the given id.
+///
+/// Word `i` is computed from the id and `i`, with word 0 holding the id
+/// itself, so that any mix of words from two payloads is detected by
+/// [`payload_id`].
+#[inline]
+pub fn patterned_payload<const K: usize>(id: u64) -> [u64; K] {
+    let mut payload = [0; K];
+    for (i, word) in payload.iter_mut().enumerate() {
+        *word = id.rotate_left(7 * i as u32) ^ SALT.wrapping_mul(i as u64);
+    }
+    payload
+}
+
+/// Returns the id of `payload`, or `None` if it isn't a
+/// [`patterned_payload`], e.g. because it was torn.
+#[inline]
+pub fn payload_id<const K: usize>(payload: &[u64; K]) -> Option<u64> {
+    let id = payload.first().copied().unwrap_or(0);
+    (*payload == patterned_payload(id)).then_some(id)
+}
+
+/// Returns the id of `payload`, panicking if it isn't a
+/// [`patterned_payload`].
+#[track_caller]
+pub fn assert_untorn<const K: usize>(payload: &[u64; K]) -> u64 {
+    match payload_id(payload) {
+        Some(id) => id,
+        None => panic!("torn payload {payload:x?}"),
+    }
+}
+
+/// Checks the values seen by one reader of a single-value register, such as
+/// a `SeqLock`.
+///
+/// Each writer's writes are numbered from 0 in the order it made them. A
+/// reader must see the writes of a given writer in that order, and must not
+/// see the initial value again once it has seen a write.
+#[derive(Clone, Debug, Default)]
+pub struct WriteOrder {
+    latest: Vec<Option<u64>>,
+    seen_write: bool,
+}
+
+impl WriteOrder {
+    /// Creates a checker for a reader which hasn't seen anything yet.
+    #[inline]
+    pub fn new() -> WriteOrder {
+        WriteOrder::default()
+    }
+
+    /// Records that the reader saw the initial value.
+    #[track_caller]
+    pub fn observe_initial(&mut self) {
+        assert!(!self.seen_write, "initial value seen after a write");
+    }
+
+    /// Records that the reader saw write number `index` of `writer`.
+    #[track_caller]
+    pub fn observe(&mut self, writer: usize, index: u64) {
+        if writer >= self.latest.len() {
+            self.latest.resize(writer + 1, None);
+        }
+        let latest = &mut self.latest[writer];
+        if let Some(prev) = *latest {
+            assert!(
+                index >= prev,
+                "write {index} of writer {writer} seen after write {prev}"
+            );
+        }
+        *latest = Some(index);
+        self.seen_write = true;
+    }
+}
+
+/// Operation counts returned by [`stress`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct StressReport {
+    /// Number of reads made by all readers.
+    pub reads: u64,
+    /// Number of writes made by all writers.
+    pub writes: u64,
+}
+
+// The id of write `index` of `writer`. Id 0 is the initial value.
+const INDEX_BITS: u32 = 48;
+
+fn write_id(writer: usize, index: u64) -> u64 {
+    (writer as u64 + 1) << INDEX_BITS | index
+}
+
+/// Runs `readers` threads calling `read` and `writers` threads calling
+/// `write`, and checks every value read.
+///
+/// The lock must initially hold `patterned_payload(0)`. Each writer passes
+/// `ops` distinct payloads to `write`, or fewer if `duration` elapses
+/// first. Readers keep reading until all writers are done, checking that
+/// every value is untorn, was actually written, and is consistent with
+/// [`WriteOrder`]. Each reader's last read starts after the last write
+/// returned. A `duration` too large to be represented as an `Instant`, such
+/// as `Duration::MAX`, means that there is no time limit.
+///
+/// Panics in `read` or `write`, and failed checks, are propagated to the
+/// caller once all threads have stopped.
+pub fn stress<const K: usize, R, W>(
+    readers: usize,
+    writers: usize,
+    duration: Duration,
+    ops: u64,
+    read: R,
+    write: W,
+) -> StressReport
+where
+    R: Fn() -> [u64; K] + Sync,
+    W: Fn([u64; K]) + Sync,
+{
+    assert!(ops < 1 << INDEX_BITS, "too many operations");
+    let deadline = Instant::now().checked_add(duration);
+    let expired = || deadline.is_some_and(|deadline| Instant::now() >= deadline);
+    let (read, write) = (&read, &write);
+
+    let finished = AtomicUsize::new(0);
+
+    thread::scope(|s| {
+        let writer_threads: Vec<_> = (0..writers)
+            .map(|w| {
+                let finished = &finished;
+                s.spawn(move || {
+                    // Counted even if `write` panics, so that readers stop.
+                    let _finished = Finished(finished);
+                    let mut index = 0;
+                    while index < ops && !expired() {
+                        write(patterned_payload(write_id(w, index)));
+                        index += 1;
+                    }
+                    index
+                })
+            })
+            .collect();
+
+        let reader_threads: Vec<_> = (0..readers)
+            .map(|_| {
+                s.spawn(|| {
+                    let mut order = WriteOrder::new();
+                    let mut reads = 0;
+                    loop {
+                        // Check whether the writers are done before reading,
+                        // so that the last read follows the last write.
+                        let done = finished.load(Ordering::Acquire) == writers;
+                        let id = assert_untorn(&read());
+                        reads += 1;
+                        let writer = (id >> INDEX_BITS) as usize;
+                        let index = id & ((1 << INDEX_BITS) - 1);
+                        if id == 0 {
+                            order.observe_initial();
+                        } else if (1..=writers).contains(&writer) && index < ops {
+                            order.observe(writer - 1, index);
+                        } else {
+                            panic!("read {id:#x}, which was never written");
+                        }
+                        if done {
+                            break reads;
+                        }
+                    }
+                })
+            })
+            .collect();
+
+        let writes = writer_threads.into_iter().map(join).sum();
+        let reads = reader_threads.into_iter().map(join).sum();
+        StressReport { reads, writes }
+    })
+}
+
+struct Finished<'a>(&'a AtomicUsize);
+
+impl Drop for Finished<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_add(1, Ordering::Release);
+    }
+}
+
+fn join(thread: thread::ScopedJoinHandle<'_, u64>) -> u64 {
+    thread
+        .join()
+        .unwrap_or_else(|payload| panic::resume_unwind(payload))
+}
@@ -0,0 +1,132 @@
+//! A `SeqLock` over storage owned by someone else.
+
+use crate::access::DataCell;
+use crate::sync::AtomicUsize;
+#[cfg(not(feature = "critical-section"))]
+use crate::sync::{self, WriterMutex};
+use core::cell::UnsafeCell;
+use core::fmt;
+
+/// Sequence lock semantics over an `UnsafeCell` borrowed from elsewhere.
+///
+/// A `SeqLockView` owns the sequence number and the writer mutex, but not
+/// the data, which stays wherever it already lives, e.g. in a structure
+/// defined by another crate. Reads and writes behave like those of
+/// [`SeqLock`], and the view can be shared between threads in the same way.
+///
+/// [`SeqLock`]: crate::SeqLock
+///
+/// # Examples
+///
+/// ```
+/// use seqlock::SeqLockView;
+/// use std::cell::UnsafeCell;
+/// use std::thread;
+///
+/// let cell = UnsafeCell::new([0u64; 4]);
+/// // Nothing else accesses `cell` while the view exists.
+/// let view = unsafe { SeqLockView::over_cell(&cell) };
+/// thread::scope(|s| {
+///     s.spawn(|| view.write([1; 4]));
+///     let val = view.read();
+///     assert!(val == [0; 4] || val == [1; 4]);
+/// });
+/// assert_eq!(view.read_versioned(), ([1; 4], 2));
+/// // The view is no longer used, so the cell can be accessed directly.
+/// assert_eq!(cell.into_inner(), [1; 4]);
+/// ```
+pub struct SeqLockView<'a, T> {
+    seq: AtomicUsize,
+    #[cfg(not(feature = "critical-section"))]
+    mutex: WriterMutex<()>,
+    data: &'a DataCell<T>,
+}
+
+unsafe impl<T: Send> Send for SeqLockView<'_, T> {}
+unsafe impl<T: Send> Sync for SeqLockView<'_, T> {}
+
+impl<'a, T: Copy> SeqLockView<'a, T> {
+    /// Creates a view which reads and writes the value in `cell`, starting
+    /// at sequence number 0.
+    ///
+    /// # Safety
+    ///
+    /// For as long as the view exists, the value in `cell` must only be
+    /// accessed through it: there may be no other reads or writes through
+    /// the cell's pointer, no references to its contents, and no other view
+    /// over the same cell, since each view has its own sequence number.
+    /// Once the view is dropped the cell can be used directly again.
+    ///
+    /// The cell must contain a valid value of `T`. The restrictions on `T`
+    /// which apply to `SeqLock` in configurations using atomic copies apply
+    /// here too.
+    #[inline]
+    pub unsafe fn over_cell(cell: &'a UnsafeCell<T>) -> SeqLockView<'a, T> {
+        SeqLockView {
+            seq: AtomicUsize::new(0),
+            #[cfg(not(feature = "critical-section"))]
+            mutex: WriterMutex::new(()),
+            data: DataCell::from_cell(cell),
+        }
+    }
+
+    /// Reads the value in the cell.
+    ///
+    /// If a writer is currently modifying the value then the calling thread
+    /// will wait until the write is complete.
+    #[inline]
+    pub fn read(&self) -> T {
+        self.read_versioned().0
+    }
+
+    /// Reads the value along with the sequence number it was read at.
+    ///
+    /// See `SeqLock::read_versioned`.
+    #[inline]
+    pub fn read_versioned(&self) -> (T, usize) {
+        crate::read_versioned(&self.seq, self.data)
+    }
+
+    /// Replaces the value in the cell.
+    ///
+    /// This acquires the view's writer mutex for the duration of the store,
+    /// or enters a critical section if the `critical-section` feature is
+    /// enabled.
+    #[inline]
+    pub fn write(&self, val: T) {
+        self.update(|data| *data = val);
+    }
+
+    /// Modifies the value in the cell, returning the result of `f`.
+    ///
+    /// `f` runs on a copy of the value with writers excluded, and readers are
+    /// only disturbed once it returns. If `f` panics nothing is written.
+    #[inline]
+    pub fn update<R>(&self, f: impl FnOnce(&mut T) -> R) -> R {
+        let exclusive = || {
+            // Other writers are excluded so the data can't change under us.
+            let mut val = unsafe { self.data.load().assume_init() };
+            let res = f(&mut val);
+
+            let seq = crate::begin_write(&self.seq);
+            unsafe { self.data.store(&val) };
+            crate::end_write(&self.seq, seq);
+            res
+        };
+
+        #[cfg(not(feature = "critical-section"))]
+        {
+            let _guard = sync::lock_writer(&self.mutex);
+            exclusive()
+        }
+
+        #[cfg(feature = "critical-section")]
+        critical_section::with(|_| exclusive())
+    }
+}
+
+impl<T: Copy + fmt::Debug> fmt::Debug for SeqLockView<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "SeqLockView {{ data: {:?} }}", &self.read())
+    }
+}
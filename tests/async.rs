@@ -0,0 +1,353 @@
+//! Async writers racing with blocking readers and writers on tokio runtimes.
+
+#![cfg(all(
+    feature = "async",
+    not(feature = "critical-section"),
+    not(target_family = "wasm")
+))]
+
+use seqlock::SeqLock;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn async_writers_sync_readers() {
+    const WRITERS: u64 = 4;
+    const WRITES: u64 = if cfg!(miri) { 10 } else { 500 };
+
+    let lock = Arc::new(SeqLock::new([0u64; 8]));
+    let done = Arc::new(AtomicBool::new(false));
+    let readers: Vec<_> = (0..2)
+        .map(|_| {
+            let lock = lock.clone();
+            let done = done.clone();
+            thread::spawn(move || {
+                while !done.load(Ordering::Relaxed) {
+                    let data = lock.read();
+                    assert!(data.iter().all(|&x| x == data[0]));
+                }
+            })
+        })
+        .collect();
+
+    let writers: Vec<_> = (0..WRITERS)
+        .map(|_| {
+            let lock = lock.clone();
+            tokio::spawn(async move {
+                for _ in 0..WRITES {
+                    let mut w = lock.lock_write_async().await;
+                    let next = w[0] + 1;
+                    for x in w.iter_mut() {
+                        *x = next;
+                        // Holding the guard across an await point requires it
+                        // to be `Send`.
+                        tokio::task::yield_now().await;
+                    }
+                }
+            })
+        })
+        .collect();
+
+    // A blocking writer shares the same mutex.
+    let blocking = {
+        let lock = lock.clone();
+        tokio::task::spawn_blocking(move || {
+            for _ in 0..WRITES {
+                let mut w = lock.lock_write();
+                let next = w[0] + 1;
+                *w = [next; 8];
+            }
+        })
+    };
+
+    for writer in writers {
+        writer.await.unwrap();
+    }
+    blocking.await.unwrap();
+    done.store(true, Ordering::Relaxed);
+    for reader in readers {
+        reader.join().unwrap();
+    }
+
+    let total = (WRITERS + 1) * WRITES;
+    assert_eq!(lock.read_versioned(), ([total; 8], 2 * total as usize));
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn async_writers_on_single_threaded_runtime() {
+    const WRITERS: u64 = 4;
+    const WRITES: u64 = if cfg!(miri) { 10 } else { 200 };
+
+    let lock = Arc::new(SeqLock::new([0u64; 4]));
+    let writers: Vec<_> = (0..WRITERS)
+        .map(|_| {
+            let lock = lock.clone();
+            tokio::spawn(async move {
+                for _ in 0..WRITES {
+                    // Waiting for the lock suspends the task instead of
+                    // blocking the only executor thread.
+                    let mut w = lock.lock_write_async().await;
+                    let next = w[0] + 1;
+                    w[0] = next;
+                    tokio::task::yield_now().await;
+                    w[1..].fill(next);
+                    drop(w);
+                    tokio::task::yield_now().await;
+                }
+            })
+        })
+        .collect();
+    for writer in writers {
+        writer.await.unwrap();
+    }
+
+    let total = WRITERS * WRITES;
+    assert_eq!(lock.read_versioned(), ([total; 4], 2 * total as usize));
+}
+
+#[tokio::test]
+async fn try_lock_write_interoperates() {
+    let lock = SeqLock::new(0);
+    let guard = lock.lock_write_async().await;
+    assert!(lock.try_lock_write().is_none());
+    drop(guard);
+
+    let guard = lock.try_lock_write().unwrap();
+    let pending = tokio::time::timeout(
+        std::time::Duration::from_millis(10),
+        lock.lock_write_async(),
+    );
+    assert!(pending.await.is_err());
+    drop(guard);
+
+    // The cancelled lock attempt did not touch the sequence number.
+    assert_eq!(lock.read_versioned(), (0, 4));
+    *lock.lock_write_async().await = 1;
+    assert_eq!(lock.read_versioned(), (1, 6));
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn changed_wakes_after_blocking_write() {
+    let lock = Arc::new(SeqLock::new(0));
+    let (_, seq) = lock.read_versioned();
+
+    let waiter = {
+        let lock = lock.clone();
+        tokio::spawn(async move { lock.changed(seq).await })
+    };
+
+    // Give the task a chance to register its waker before writing.
+    tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+    let writer = {
+        let lock = lock.clone();
+        thread::spawn(move || lock.write(42))
+    };
+
+    let (value, new_seq) = tokio::time::timeout(std::time::Duration::from_secs(10), waiter)
+        .await
+        .expect("changed() was not woken")
+        .unwrap();
+    assert_eq!(value, 42);
+    assert_eq!(new_seq, seq + 2);
+    writer.join().unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn changed_does_not_miss_racing_write() {
+    for _ in 0..if cfg!(miri) { 10 } else { 1000 } {
+        let lock = Arc::new(SeqLock::new(0u32));
+        let (_, seq) = lock.read_versioned();
+
+        // The write races with the first poll of `changed`.
+        let writer = {
+            let lock = lock.clone();
+            thread::spawn(move || lock.write(1))
+        };
+        let (value, _) =
+            tokio::time::timeout(std::time::Duration::from_secs(10), lock.changed(seq))
+                .await
+                .expect("lost wakeup");
+        assert_eq!(value, 1);
+        writer.join().unwrap();
+    }
+}
+
+#[test]
+fn parked_poller_keeps_up_with_writer() {
+    use std::task::{Poll, Wake, Waker};
+    use std::time::{Duration, Instant};
+
+    struct ThreadWaker(thread::Thread);
+
+    impl Wake for ThreadWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    const WRITES: u32 = if cfg!(miri) { 20 } else { 100_000 };
+    let lock = SeqLock::new(0u32);
+    let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+    thread::scope(|s| {
+        s.spawn(|| {
+            for i in 1..=WRITES {
+                lock.write(i);
+            }
+        });
+        // Every write races with registration. A lost wakeup leaves the
+        // poller parked although the value has changed.
+        let mut seq = 0;
+        loop {
+            match lock.poll_changed(seq, &waker) {
+                Poll::Ready((WRITES, _)) => break,
+                Poll::Ready((_, new_seq)) => seq = new_seq,
+                Poll::Pending => {
+                    let timeout = Duration::from_secs(10);
+                    let start = Instant::now();
+                    thread::park_timeout(timeout);
+                    let timed_out = start.elapsed() >= timeout;
+                    assert!(!timed_out || lock.current_seq() == seq, "lost wakeup");
+                }
+            }
+        }
+    });
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn stream_slow_consumer_sees_increasing_values() {
+    use futures::StreamExt;
+
+    const WRITES: u64 = if cfg!(miri) { 20 } else { 2000 };
+
+    let lock = Arc::new(SeqLock::new(0u64));
+    let writer = {
+        let lock = lock.clone();
+        thread::spawn(move || {
+            for i in 1..=WRITES {
+                lock.write(i);
+            }
+        })
+    };
+
+    let mut stream = lock.stream();
+    let mut last = None;
+    while let Some(value) = stream.next().await {
+        // Conflation may skip values, but never repeats or reorders them.
+        assert!(last.is_none_or(|last| value > last));
+        last = Some(value);
+        if value == WRITES {
+            break;
+        }
+        tokio::task::yield_now().await;
+    }
+    writer.join().unwrap();
+}
+
+#[test]
+fn dropping_stream_unregisters_waker() {
+    use futures::task::{waker, ArcWake};
+    use std::pin::Pin;
+    use std::task::{Context, Poll};
+
+    struct CountingWaker;
+    impl ArcWake for CountingWaker {
+        fn wake_by_ref(_: &Arc<Self>) {}
+    }
+
+    let lock = SeqLock::new(0);
+    let inner = Arc::new(CountingWaker);
+    let waker = waker(inner.clone());
+    let mut cx = Context::from_waker(&waker);
+
+    let mut stream = lock.stream();
+    let mut changed = lock.changed(lock.read_versioned().1);
+    assert_eq!(
+        futures::Stream::poll_next(Pin::new(&mut stream), &mut cx),
+        Poll::Ready(Some(0))
+    );
+    assert!(futures::Stream::poll_next(Pin::new(&mut stream), &mut cx).is_pending());
+    assert!(std::future::Future::poll(Pin::new(&mut changed), &mut cx).is_pending());
+    // Held by `inner`, `waker`, and one registration each.
+    assert_eq!(Arc::strong_count(&inner), 4);
+
+    // Dropping the stream leaves the registration of the `changed` future
+    // from the same task in place.
+    drop(stream);
+    assert_eq!(Arc::strong_count(&inner), 3);
+    lock.write(1);
+    assert_eq!(Arc::strong_count(&inner), 2);
+    assert_eq!(
+        std::future::Future::poll(Pin::new(&mut changed), &mut cx),
+        Poll::Ready((1, 2))
+    );
+}
+
+#[test]
+fn dropping_changed_unregisters_waker() {
+    use futures::task::{waker, ArcWake};
+    use std::pin::Pin;
+    use std::task::Context;
+
+    struct CountingWaker;
+    impl ArcWake for CountingWaker {
+        fn wake_by_ref(_: &Arc<Self>) {}
+    }
+
+    let lock = SeqLock::new(0);
+    let inner = Arc::new(CountingWaker);
+    let waker = waker(inner.clone());
+    let mut cx = Context::from_waker(&waker);
+    let seq = lock.read_versioned().1;
+
+    // Futures abandoned without a write, as by a timeout which keeps
+    // expiring, don't accumulate registrations.
+    for _ in 0..1000 {
+        let mut changed = lock.changed(seq);
+        assert!(std::future::Future::poll(Pin::new(&mut changed), &mut cx).is_pending());
+        // Held by `inner`, `waker`, and the registration of `changed`.
+        assert_eq!(Arc::strong_count(&inner), 3);
+    }
+    assert_eq!(Arc::strong_count(&inner), 2);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn stream_forwarded_into_sink() {
+    use futures::{stream, StreamExt};
+
+    const ITEMS: u64 = if cfg!(miri) { 20 } else { 2000 };
+
+    let lock = Arc::new(SeqLock::new([0u64; 8]));
+    let done = Arc::new(AtomicBool::new(false));
+    let reader = {
+        let lock = lock.clone();
+        let done = done.clone();
+        thread::spawn(move || {
+            while !done.load(Ordering::Relaxed) {
+                let data = lock.read();
+                assert!(data.iter().all(|&x| x == data[0]));
+            }
+        })
+    };
+
+    // Hold the writer mutex while the sink starts up, so that it has to
+    // wait for it.
+    let (locked_tx, locked_rx) = std::sync::mpsc::channel();
+    let blocking = {
+        let lock = lock.clone();
+        thread::spawn(move || {
+            let _guard = lock.lock_write();
+            locked_tx.send(()).unwrap();
+            thread::sleep(std::time::Duration::from_millis(10));
+        })
+    };
+    locked_rx.recv().unwrap();
+
+    let items = stream::iter(1..=ITEMS).map(|i| Ok([i; 8]));
+    items.forward(lock.sink()).await.unwrap();
+    blocking.join().unwrap();
+    done.store(true, Ordering::Relaxed);
+    reader.join().unwrap();
+
+    assert_eq!(lock.read(), [ITEMS; 8]);
+}
@@ -0,0 +1,34 @@
+//! Readers waiting for a writer give up once they are cancelled.
+
+#![cfg(not(feature = "critical-section"))]
+
+use seqlock::SeqLock;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::Duration;
+
+#[test]
+fn cancel_aborts_read_behind_stuck_writer() {
+    let lock = SeqLock::new(5);
+    let cancel = AtomicBool::new(false);
+    let _guard = lock.lock_write();
+    thread::scope(|s| {
+        let reader = s.spawn(|| lock.read_cancellable(&cancel));
+        thread::sleep(Duration::from_millis(if cfg!(miri) { 1 } else { 20 }));
+        cancel.store(true, Ordering::Relaxed);
+        assert_eq!(reader.join().unwrap(), None);
+    });
+}
+
+#[test]
+fn uncancelled_read_sees_finished_write() {
+    let lock = SeqLock::new(5);
+    let cancel = AtomicBool::new(false);
+    thread::scope(|s| {
+        let mut guard = lock.lock_write();
+        let reader = s.spawn(|| lock.read_cancellable(&cancel));
+        *guard = 6;
+        drop(guard);
+        assert_eq!(reader.join().unwrap(), Some(6));
+    });
+}
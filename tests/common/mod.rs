@@ -0,0 +1,27 @@
+//! A mock critical-section implementation for host-side tests, which counts
+//! how often it is entered.
+
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+struct MockCriticalSection;
+critical_section::set_impl!(MockCriticalSection);
+
+pub static LOCKED: AtomicBool = AtomicBool::new(false);
+pub static ENTERED: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl critical_section::Impl for MockCriticalSection {
+    unsafe fn acquire() -> bool {
+        while LOCKED
+            .compare_exchange_weak(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            std::hint::spin_loop();
+        }
+        ENTERED.fetch_add(1, Ordering::Relaxed);
+        true
+    }
+
+    unsafe fn release(_: bool) {
+        LOCKED.store(false, Ordering::Release);
+    }
+}
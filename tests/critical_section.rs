@@ -0,0 +1,45 @@
+//! Host-side tests of the `critical-section` writer.
+
+#![cfg(feature = "critical-section")]
+
+mod common;
+
+use common::{ENTERED, LOCKED};
+use seqlock::test_utils::{patterned_payload, stress};
+use seqlock::SeqLock;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+
+// The call counter is global, so run everything in a single test.
+#[test]
+fn critical_section_writer() {
+    let lock = SeqLock::new([0u64; 8]);
+
+    lock.write([1; 8]);
+    assert_eq!(ENTERED.load(Ordering::Relaxed), 1);
+    assert_eq!(lock.read(), [1; 8]);
+
+    let old = lock.update(|data| {
+        assert!(LOCKED.load(Ordering::Relaxed));
+        let old = data[0];
+        *data = [2; 8];
+        old
+    });
+    assert_eq!(old, 1);
+    assert_eq!(ENTERED.load(Ordering::Relaxed), 2);
+    assert_eq!(lock.read_versioned(), ([2; 8], 4));
+
+    // An "interrupt handler" thread writes while readers check for tearing.
+    const WRITES: u64 = if cfg!(miri) { 100 } else { 10_000 };
+    let lock = SeqLock::new(patterned_payload::<8>(0));
+    let report = stress(
+        2,
+        1,
+        Duration::MAX,
+        WRITES,
+        || lock.read(),
+        |val| lock.write(val),
+    );
+    assert_eq!(report.writes, WRITES);
+    assert_eq!(ENTERED.load(Ordering::Relaxed), 2 + WRITES as usize);
+}
@@ -0,0 +1,82 @@
+//! Smoke test of the GDB pretty-printers embedded by `debugger_visualizer`.
+//!
+//! This runs its own binary under `gdb` in batch mode, stopping in `stop` to
+//! print a `SeqLock` before and during a write. It is skipped if `gdb` is not
+//! installed.
+
+use seqlock::SeqLock;
+use std::hint::black_box;
+use std::process::Command;
+
+const DEBUGGEE_ARG: &str = "--seqlock-debuggee";
+
+#[inline(never)]
+fn stop() {
+    black_box(());
+}
+
+fn debuggee() {
+    let lock = black_box(SeqLock::new(42u32));
+    stop();
+
+    #[cfg(not(feature = "critical-section"))]
+    {
+        let guard = black_box(lock.lock_write());
+        stop();
+        drop(guard);
+    }
+    black_box(&lock);
+}
+
+fn main() {
+    if std::env::args().any(|arg| arg == DEBUGGEE_ARG) {
+        debuggee();
+        return;
+    }
+    if cfg!(miri) || !cfg!(target_os = "linux") || cfg!(feature = "critical-section") {
+        return;
+    }
+    if Command::new("gdb").arg("--version").output().is_err() {
+        eprintln!("gdb not found, skipping the debugger visualizer test");
+        return;
+    }
+
+    let exe = std::env::current_exe().unwrap();
+    let output = Command::new("gdb")
+        .args(["-nx", "-batch"])
+        .args(["-iex", "set auto-load safe-path /"])
+        .args(["-ex", "break debugger::stop"])
+        .args(["-ex", "run", "-ex", "up", "-ex", "print lock"])
+        .args(["-ex", "continue", "-ex", "up", "-ex", "print lock"])
+        .args(["-ex", "print guard", "-ex", "continue"])
+        .arg("--args")
+        .arg(&exe)
+        .arg(DEBUGGEE_ARG)
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "gdb failed:\n{stdout}");
+
+    // Each `print` produces a line starting with `$N = `.
+    let printed: Vec<&str> = stdout
+        .lines()
+        .filter(|line| line.starts_with('$'))
+        .collect();
+    assert_eq!(printed.len(), 3, "unexpected gdb output:\n{stdout}");
+
+    let idle = printed[0];
+    assert!(idle.contains("SeqLock"), "{idle}");
+    assert!(idle.contains("42") && idle.contains("false"), "{idle}");
+
+    let writing = printed[1];
+    assert!(
+        writing.contains("42") && writing.contains("true"),
+        "{writing}"
+    );
+
+    let guard = printed[2];
+    assert!(
+        guard.contains("SeqLockGuard") && guard.contains("42"),
+        "{guard}"
+    );
+}
@@ -0,0 +1,73 @@
+//! Host-side tests of the embassy `SeqLock` with contending async writers.
+
+#![cfg(feature = "embassy")]
+
+mod common;
+
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use futures::executor::block_on;
+use seqlock::embassy::SeqLock;
+use seqlock::test_utils::{patterned_payload, stress};
+use std::future::Future;
+use std::time::Duration;
+
+#[test]
+fn contending_async_writers() {
+    const WRITERS: usize = 4;
+    const WRITES: u64 = if cfg!(miri) { 20 } else { 1000 };
+
+    let lock = SeqLock::<CriticalSectionRawMutex, _>::new(patterned_payload::<8>(0));
+    let report = stress(
+        2,
+        WRITERS,
+        Duration::MAX,
+        WRITES,
+        || lock.read(),
+        |val| {
+            block_on(async {
+                let mut w = lock.lock_write_async().await;
+                // Write the words one at a time so that readers would notice
+                // overlapping writers.
+                for (x, new) in w.iter_mut().zip(val) {
+                    *x = new;
+                }
+            })
+        },
+    );
+
+    // Every write happened under mutual exclusion.
+    assert_eq!(report.writes, WRITERS as u64 * WRITES);
+    assert_eq!(lock.read_versioned().1 as u64, 2 * report.writes);
+}
+
+#[test]
+fn try_lock_write_excludes_async_writer() {
+    let lock = SeqLock::<CriticalSectionRawMutex, u32>::new(0);
+    let guard = block_on(lock.lock_write_async());
+    assert!(lock.try_lock_write().is_none());
+    drop(guard);
+
+    let mut guard = lock.try_lock_write().unwrap();
+    *guard = 1;
+    drop(guard);
+    assert_eq!(lock.read(), 1);
+}
+
+#[test]
+fn cancelled_lock_leaves_sequence_untouched() {
+    let lock = SeqLock::<CriticalSectionRawMutex, u32>::new(0);
+    let guard = lock.try_lock_write().unwrap();
+    {
+        // Polling once registers interest in the mutex, then the future is
+        // dropped before it acquires it.
+        let mut fut = Box::pin(lock.lock_write_async());
+        let mut cx = std::task::Context::from_waker(std::task::Waker::noop());
+        assert!(fut.as_mut().poll(&mut cx).is_pending());
+    }
+    drop(guard);
+    assert_eq!(lock.read_versioned(), (0, 2));
+
+    // The mutex is still usable by other writers.
+    *block_on(lock.lock_write_async()) = 1;
+    assert_eq!(lock.read_versioned(), (1, 4));
+}
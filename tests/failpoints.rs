@@ -0,0 +1,247 @@
+//! Interleavings which are hard to hit under stress, forced with the
+//! `failpoints` feature. Failpoints are global, so each test holds a
+//! `FailScenario` to run alone.
+
+#![cfg(feature = "failpoints")]
+
+#[cfg(feature = "critical-section")]
+mod common;
+
+use fail::FailScenario;
+use seqlock::{ReadKind, ReadMiss, SeqLock};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+
+// Configures `name` to run `f` with the number of previous calls.
+fn on_call(name: &str, f: impl Fn(usize) + Send + Sync + 'static) -> Arc<AtomicUsize> {
+    let calls = Arc::new(AtomicUsize::new(0));
+    let counter = calls.clone();
+    fail::cfg_callback(name, move || f(counter.fetch_add(1, Ordering::Relaxed))).unwrap();
+    calls
+}
+
+#[test]
+fn write_between_seq1_and_copy_is_retried() {
+    let scenario = FailScenario::setup();
+    let lock = Arc::new(SeqLock::new([1u64; 4]));
+    let writer = lock.clone();
+    let calls = on_call("seqlock::read::after_seq1", move |n| {
+        if n == 0 {
+            writer.write([2; 4]);
+        }
+    });
+    assert_eq!(lock.read_versioned(), ([2; 4], 2));
+    assert_eq!(calls.load(Ordering::Relaxed), 2);
+    scenario.teardown();
+}
+
+#[test]
+fn write_between_copy_and_seq2_is_retried() {
+    let scenario = FailScenario::setup();
+    let lock = Arc::new(SeqLock::new([1u64; 4]));
+    let writer = lock.clone();
+    // The first copy is complete but stale by the time seq2 is loaded.
+    let calls = on_call("seqlock::read::after_copy", move |n| {
+        if n == 0 {
+            writer.write([2; 4]);
+        }
+    });
+    assert_eq!(lock.read_versioned(), ([2; 4], 2));
+    assert_eq!(calls.load(Ordering::Relaxed), 2);
+    scenario.teardown();
+}
+
+#[test]
+fn reader_waits_for_unpublished_write() {
+    let scenario = FailScenario::setup();
+    let lock = SeqLock::new(1u32);
+    let (started_tx, started_rx) = mpsc::channel();
+    let (release_tx, release_rx) = mpsc::channel::<()>();
+    let release_rx = Mutex::new(release_rx);
+    // Hold the writer between writing the data and publishing it until the
+    // reader is waiting for it.
+    on_call("seqlock::write::before_publish", move |_| {
+        started_tx.send(()).unwrap();
+        release_rx.lock().unwrap().recv().unwrap();
+    });
+    let release_tx = Mutex::new(release_tx);
+    let waits = on_call("seqlock::read::wait", move |n| {
+        if n == 0 {
+            release_tx.lock().unwrap().send(()).unwrap();
+        }
+    });
+    thread::scope(|s| {
+        s.spawn(|| lock.write(2));
+        started_rx.recv().unwrap();
+        assert_eq!(lock.read_versioned(), (2, 2));
+    });
+    assert!(waits.load(Ordering::Relaxed) >= 1);
+    scenario.teardown();
+}
+
+#[test]
+fn instrumented_read_reports_tear() {
+    let scenario = FailScenario::setup();
+    let lock = Arc::new(SeqLock::new(1u32));
+    let writer = lock.clone();
+    on_call("seqlock::read::after_seq1", move |n| {
+        if n == 0 {
+            writer.write(2);
+        }
+    });
+    assert_eq!(lock.read_instrumented(), (2, ReadKind::RetriedAfterTear(1)));
+    scenario.teardown();
+}
+
+#[test]
+fn instrumented_read_reports_active_writer() {
+    let scenario = FailScenario::setup();
+    let lock = SeqLock::new(1u32);
+    let (started_tx, started_rx) = mpsc::channel();
+    let (release_tx, release_rx) = mpsc::channel::<()>();
+    let release_rx = Mutex::new(release_rx);
+    on_call("seqlock::write::before_publish", move |_| {
+        started_tx.send(()).unwrap();
+        release_rx.lock().unwrap().recv().unwrap();
+    });
+    let release_tx = Mutex::new(release_tx);
+    on_call("seqlock::read::wait", move |n| {
+        if n == 0 {
+            release_tx.lock().unwrap().send(()).unwrap();
+        }
+    });
+    thread::scope(|s| {
+        s.spawn(|| lock.write(2));
+        started_rx.recv().unwrap();
+        match lock.read_instrumented() {
+            (2, ReadKind::RetriedAfterWrite(n)) => assert!(n >= 1),
+            other => panic!("unexpected read {other:?}"),
+        }
+    });
+    scenario.teardown();
+}
+
+#[test]
+fn watchdog_reports_writer_stuck_before_publishing() {
+    let scenario = FailScenario::setup();
+    let lock = Arc::new(SeqLock::new(1u32));
+    let reader = lock.clone();
+    let stuck = Arc::new(Mutex::new(None));
+    let result = stuck.clone();
+    on_call("seqlock::write::before_publish", move |_| {
+        *result.lock().unwrap() = Some(reader.read_watchdog(3));
+    });
+    let waits = on_call("seqlock::read::wait", |_| {});
+    lock.write(2);
+    let err = stuck.lock().unwrap().take().unwrap().unwrap_err();
+    assert_eq!(err.seq(), 1);
+    assert_eq!(waits.load(Ordering::Relaxed), 3);
+    assert_eq!(lock.read_watchdog(3), Ok(2));
+    scenario.teardown();
+}
+
+#[test]
+fn reader_sees_odd_sequence_after_begin() {
+    let scenario = FailScenario::setup();
+    let lock = Arc::new(SeqLock::new(1u32));
+    let reader = lock.clone();
+    let seen = Arc::new(AtomicUsize::new(0));
+    let result = seen.clone();
+    on_call("seqlock::write::after_begin", move |_| {
+        result.store(reader.current_seq(), Ordering::Relaxed);
+    });
+    lock.write(2);
+    assert_eq!(seen.load(Ordering::Relaxed), 1);
+    assert_eq!(lock.current_seq(), 2);
+    scenario.teardown();
+}
+
+#[test]
+fn classified_read_reports_active_writer() {
+    let scenario = FailScenario::setup();
+    let lock = Arc::new(SeqLock::new(1u32));
+    let reader = lock.clone();
+    let result = Arc::new(Mutex::new(None));
+    let miss = result.clone();
+    on_call("seqlock::write::after_begin", move |_| {
+        *miss.lock().unwrap() = Some(reader.try_read_classified());
+    });
+    lock.write(2);
+    assert_eq!(
+        result.lock().unwrap().take(),
+        Some(Err(ReadMiss::WriterActive))
+    );
+    assert_eq!(lock.try_read_classified(), Ok(2));
+    scenario.teardown();
+}
+
+#[test]
+fn classified_read_reports_torn_copy() {
+    let scenario = FailScenario::setup();
+    let lock = Arc::new(SeqLock::new([1u64; 4]));
+    let writer = lock.clone();
+    let calls = on_call("seqlock::read::after_copy", move |n| {
+        if n == 0 {
+            writer.write([2; 4]);
+        }
+    });
+    // A single attempt, which isn't retried.
+    assert_eq!(lock.try_read_classified(), Err(ReadMiss::Torn));
+    assert_eq!(calls.load(Ordering::Relaxed), 1);
+    assert_eq!(lock.try_read_classified(), Ok([2; 4]));
+    scenario.teardown();
+}
+
+// Discarded reads still go through the copy, rather than stopping at the
+// sequence number.
+#[test]
+fn unused_read_still_copies() {
+    let scenario = FailScenario::setup();
+    let lock = SeqLock::new([1u64; 4]);
+    let copies = on_call("seqlock::read::after_copy", |_| {});
+    for _ in 0..3 {
+        lock.read();
+    }
+    let _ = lock.read_versioned();
+    assert_eq!(copies.load(Ordering::Relaxed), 4);
+    scenario.teardown();
+}
+
+#[test]
+fn versioned_attempt_misses_torn_copy() {
+    let scenario = FailScenario::setup();
+    let lock = Arc::new(SeqLock::new([1u64; 4]));
+    let writer = lock.clone();
+    on_call("seqlock::read::after_copy", move |n| {
+        if n == 0 {
+            writer.write([2; 4]);
+        }
+    });
+    assert_eq!(lock.try_read_versioned(), None);
+    let (val, seq) = lock.try_read_versioned().unwrap();
+    assert_eq!((val, seq), ([2; 4], 2));
+    assert_eq!(seq & 1, 0);
+    scenario.teardown();
+}
+
+#[cfg(feature = "async")]
+#[test]
+fn write_before_waker_registration_is_not_missed() {
+    use std::task::{Poll, Waker};
+
+    let scenario = FailScenario::setup();
+    let lock = Arc::new(SeqLock::new(1u32));
+    let writer = lock.clone();
+    // The write happens after the poll has seen no change, but before its
+    // waker is registered, so it wakes nobody: only the re-check after
+    // registering can notice it.
+    let calls = on_call("seqlock::changed::before_register", move |n| {
+        if n == 0 {
+            writer.write(2);
+        }
+    });
+    assert_eq!(lock.poll_changed(0, Waker::noop()), Poll::Ready((2, 2)));
+    assert_eq!(calls.load(Ordering::Relaxed), 1);
+    scenario.teardown();
+}
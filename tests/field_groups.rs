@@ -0,0 +1,61 @@
+//! Locks for separate groups of fields of one struct, which writers update
+//! independently, must not make readers of the other groups retry.
+
+#[cfg(feature = "critical-section")]
+mod common;
+
+use seqlock::test_utils::{assert_untorn, patterned_payload};
+use seqlock::{ReadKind, SeqLock};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+
+const WRITES: u64 = if cfg!(miri) { 20 } else { 20_000 };
+
+#[repr(align(128))]
+struct Group<T>(SeqLock<T>);
+
+struct Fields {
+    a: Group<[u64; 8]>,
+    b: Group<[u64; 8]>,
+}
+
+#[test]
+fn disjoint_writes_cause_no_cross_retries() {
+    let fields = Fields {
+        a: Group(SeqLock::new(patterned_payload(0))),
+        b: Group(SeqLock::new(patterned_payload(0))),
+    };
+    let done = [AtomicBool::new(false), AtomicBool::new(false)];
+    thread::scope(|s| {
+        // Each group has its own writer, and its own reader which is the
+        // only one allowed to be disturbed by it.
+        for (group, done) in [&fields.a, &fields.b].into_iter().zip(&done) {
+            s.spawn(move || {
+                for i in 1..=WRITES {
+                    group.0.write(patterned_payload(i));
+                }
+                done.store(true, Ordering::Release);
+            });
+            s.spawn(move || {
+                while !done.load(Ordering::Acquire) {
+                    assert_untorn(&group.0.read());
+                }
+            });
+        }
+        // Only the writer of `a` writes while this reader reads `b`.
+        s.spawn(|| {
+            let b = &fields.b.0;
+            while !done[1].load(Ordering::Acquire) {
+                thread::yield_now();
+            }
+            let (_, seq) = b.read_versioned();
+            while !done[0].load(Ordering::Acquire) {
+                let (val, kind) = b.read_instrumented();
+                assert_eq!(kind, ReadKind::FirstTry);
+                assert_eq!(assert_untorn(&val), WRITES);
+            }
+            assert_eq!(b.current_seq(), seq);
+        });
+    });
+    assert_eq!(fields.a.0.read_versioned().1 as u64, WRITES * 2);
+}
@@ -0,0 +1,96 @@
+//! Members of a `SeqLockGroup`, which are written together and read on
+//! their own.
+
+#[cfg(feature = "critical-section")]
+mod common;
+
+use seqlock::test_utils::{assert_untorn, patterned_payload};
+use seqlock::SeqLockGroup;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+
+const WRITES: u64 = if cfg!(miri) { 20 } else { 10_000 };
+
+#[test]
+fn grouped_writes_are_atomic_per_member() {
+    let group = SeqLockGroup::new([patterned_payload::<8>(0); 4]);
+    let done = AtomicBool::new(false);
+    thread::scope(|s| {
+        s.spawn(|| {
+            for i in 1..=WRITES {
+                group.update_all(|values| *values = [patterned_payload(i); 4]);
+            }
+            done.store(true, Ordering::Relaxed);
+        });
+        for member in group.members() {
+            let done = &done;
+            s.spawn(move || {
+                let mut last = 0;
+                while !done.load(Ordering::Relaxed) {
+                    let (val, seq) = member.read_versioned();
+                    // Every write to the group is one write to each member.
+                    let id = assert_untorn(&val);
+                    assert_eq!(seq as u64, 2 * id);
+                    assert!(id >= last);
+                    last = id;
+                }
+            });
+        }
+    });
+    for member in group.members() {
+        assert_eq!(
+            member.read_versioned(),
+            (patterned_payload(WRITES), 2 * WRITES as usize)
+        );
+    }
+}
+
+#[test]
+fn panicking_update_writes_nothing() {
+    let group = SeqLockGroup::new([1, 2]);
+    let res = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        group.update_all(|values| {
+            values[0] = 3;
+            panic!("abandoned");
+        })
+    }));
+    assert!(res.is_err());
+    assert_eq!(group.members()[0].read_versioned(), (1, 0));
+    assert_eq!(
+        group.update_all(|values| std::mem::replace(values, [4, 5])),
+        [1, 2]
+    );
+    assert_eq!(group.members()[1].read_versioned(), (5, 2));
+}
+
+#[cfg(not(feature = "critical-section"))]
+#[test]
+fn guard_publishes_every_member_once() {
+    let group = SeqLockGroup::new([0u32; 3]);
+    thread::scope(|s| {
+        let mut guard = group.lock_write_all();
+        let reader = s.spawn(|| group.members()[2].read_versioned());
+        guard[0] = 1;
+        guard[2] = 3;
+        drop(guard);
+        assert!(matches!(reader.join().unwrap(), (0, 0) | (3, 2)));
+    });
+    let values = group.members().each_ref().map(|m| m.read_versioned());
+    assert_eq!(values, [(1, 2), (0, 2), (3, 2)]);
+    assert_eq!(
+        format!("{group:?}"),
+        "SeqLockGroup { members: [SeqLockMember { data: 1 }, SeqLockMember { data: 0 }, SeqLockMember { data: 3 }] }"
+    );
+}
+
+static STATIC_GROUP: SeqLockGroup<u32, 2> = SeqLockGroup::new([1, 2]);
+
+#[test]
+fn const_construction() {
+    STATIC_GROUP.update_all(|values| values.reverse());
+    let values = STATIC_GROUP
+        .members()
+        .each_ref()
+        .map(|m| m.read_versioned());
+    assert_eq!(values, [(2, 2), (1, 2)]);
+}
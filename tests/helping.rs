@@ -0,0 +1,63 @@
+//! `read_helping` on an oversubscribed system, with many more threads than
+//! cores and writers which are often preempted in the middle of a write, and
+//! against a writer which never stops writing.
+
+#![cfg(not(any(feature = "critical-section", seqlock_unsafe_assume_single_writer)))]
+
+use seqlock::test_utils::{assert_untorn, patterned_payload};
+use seqlock::SeqLock;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+
+const WRITES: u64 = if cfg!(miri) { 5 } else { 1000 };
+const READS: usize = if cfg!(miri) { 5 } else { 1000 };
+
+#[test]
+fn terminates_under_oversubscription() {
+    let cores = thread::available_parallelism().map_or(1, |n| n.get());
+    let lock = SeqLock::new(patterned_payload::<32>(0));
+    thread::scope(|s| {
+        for _ in 0..2 {
+            s.spawn(|| {
+                for i in 1..=WRITES {
+                    let mut guard = lock.lock_write();
+                    *guard = patterned_payload(i);
+                    // Give readers every chance to run mid-write.
+                    thread::yield_now();
+                }
+            });
+        }
+        let readers = if cfg!(miri) { 2 } else { 4 * cores };
+        for _ in 0..readers {
+            s.spawn(|| {
+                for _ in 0..READS {
+                    assert_untorn(&lock.read_helping());
+                }
+            });
+        }
+    });
+    assert_eq!(lock.current_seq() as u64, 2 * 2 * WRITES);
+}
+
+#[test]
+fn completes_under_saturating_writer() {
+    let lock = SeqLock::new(patterned_payload::<32>(0));
+    let done = AtomicBool::new(false);
+    thread::scope(|s| {
+        // Never leaves the sequence number even for long, so that plain
+        // reads would mostly fail.
+        s.spawn(|| {
+            let mut i = 0;
+            while !done.load(Ordering::Relaxed) {
+                i += 1;
+                let mut guard = lock.lock_write();
+                *guard = patterned_payload(i);
+                thread::yield_now();
+            }
+        });
+        for _ in 0..READS {
+            assert_untorn(&lock.read_helping());
+        }
+        done.store(true, Ordering::Relaxed);
+    });
+}
@@ -0,0 +1,181 @@
+//! Model checks of the `SeqLock` protocol with loom.
+//!
+//! Run with `RUSTFLAGS="--cfg loom" cargo test --test loom --release`.
+
+#![cfg(loom)]
+
+use loom::sync::atomic::{AtomicBool, Ordering};
+use loom::sync::Arc;
+use loom::thread;
+use seqlock::{SeqLock, SeqLockStatic};
+
+#[test]
+fn reader_never_sees_torn_write() {
+    loom::model(|| {
+        let lock = Arc::new(SeqLock::new((0usize, 0usize)));
+
+        let writer = {
+            let lock = lock.clone();
+            thread::spawn(move || {
+                // The two words of the payload are published by separate
+                // atomic stores, which the reader may observe in between.
+                let mut guard = lock.lock_write();
+                guard.0 = 1;
+                guard.1 = 2;
+            })
+        };
+
+        let reader = {
+            let lock = lock.clone();
+            thread::spawn(move || match lock.read_versioned() {
+                ((0, 0), 0) | ((1, 2), 2) => {}
+                torn => panic!("torn read {torn:?}"),
+            })
+        };
+
+        writer.join().unwrap();
+        reader.join().unwrap();
+        assert_eq!(lock.read(), (1, 2));
+    });
+}
+
+#[test]
+fn static_reader_never_sees_torn_write() {
+    loom::model(|| {
+        let lock = Arc::new(SeqLockStatic::new((0usize, 0usize)));
+
+        let writer = {
+            let lock = lock.clone();
+            // The only writer.
+            thread::spawn(move || unsafe { lock.write((1, 2)) })
+        };
+
+        let reader = {
+            let lock = lock.clone();
+            thread::spawn(move || match lock.read_versioned() {
+                ((0, 0), 0) | ((1, 2), 2) => {}
+                torn => panic!("torn read {torn:?}"),
+            })
+        };
+
+        writer.join().unwrap();
+        reader.join().unwrap();
+        assert_eq!(lock.read(), (1, 2));
+    });
+}
+
+#[test]
+fn writers_are_exclusive() {
+    loom::model(|| {
+        let lock = Arc::new(SeqLock::new(0usize));
+
+        let writers: Vec<_> = (0..2)
+            .map(|_| {
+                let lock = lock.clone();
+                thread::spawn(move || {
+                    lock.update(|val| {
+                        let next = *val + 1;
+                        thread::yield_now();
+                        *val = next;
+                    });
+                })
+            })
+            .collect();
+
+        // A concurrent `try_lock_write` either fails, or excludes the other
+        // writers while it's held.
+        if let Some(mut guard) = lock.try_lock_write() {
+            let next = *guard + 1;
+            thread::yield_now();
+            *guard = next;
+        } else {
+            lock.update(|val| *val += 1);
+        }
+
+        for writer in writers {
+            writer.join().unwrap();
+        }
+        assert_eq!(lock.read_versioned(), (3, 6));
+    });
+}
+
+#[test]
+fn publication_is_ordered() {
+    loom::model(|| {
+        let lock = Arc::new(SeqLock::new(0usize));
+        let published = Arc::new(AtomicBool::new(false));
+
+        let writer = {
+            let lock = lock.clone();
+            let published = published.clone();
+            thread::spawn(move || {
+                lock.write(1);
+                published.store(true, Ordering::Release);
+            })
+        };
+
+        // The sequence number identifies the value it was read with, and a
+        // write which happens-before the read is always visible.
+        let seen = published.load(Ordering::Acquire);
+        let (val, seq) = lock.read_versioned();
+        match seq {
+            0 => assert!(!seen && val == 0),
+            2 => assert_eq!(val, 1),
+            _ => panic!("unexpected sequence number {seq}"),
+        }
+        writer.join().unwrap();
+    });
+}
+
+// A standalone model of the read protocol, with the ordering of the first
+// sequence number load as a parameter. The writer is the one `SeqLock` uses.
+fn read_protocol_model(seq1_order: Ordering) {
+    use loom::sync::atomic::{fence, AtomicUsize};
+
+    loom::model(move || {
+        let seq = Arc::new(AtomicUsize::new(0));
+        let data = Arc::new([AtomicUsize::new(0), AtomicUsize::new(0)]);
+
+        let writer = {
+            let (seq, data) = (seq.clone(), data.clone());
+            thread::spawn(move || {
+                seq.store(1, Ordering::Relaxed);
+                fence(Ordering::Release);
+                data[0].store(1, Ordering::Relaxed);
+                data[1].store(2, Ordering::Relaxed);
+                seq.store(2, Ordering::Release);
+            })
+        };
+
+        let seq1 = seq.load(seq1_order);
+        if seq1 & 1 == 0 {
+            let val = (
+                data[0].load(Ordering::Relaxed),
+                data[1].load(Ordering::Relaxed),
+            );
+            fence(Ordering::Acquire);
+            let seq2 = seq.load(Ordering::Relaxed);
+            if seq1 == seq2 {
+                match (val, seq1) {
+                    ((0, 0), 0) | ((1, 2), 2) => {}
+                    torn => panic!("torn read {torn:?}"),
+                }
+            }
+        }
+        writer.join().unwrap();
+    });
+}
+
+#[test]
+fn read_protocol_with_acquire_first_load() {
+    read_protocol_model(Ordering::Acquire);
+}
+
+// The first load can't be relaxed: nothing then orders the data loads after
+// it, so they may return values older than the write which published `seq1`,
+// and the second load only catches copies which overlap a later write.
+#[test]
+#[should_panic(expected = "torn read")]
+fn read_protocol_with_relaxed_first_load() {
+    read_protocol_model(Ordering::Relaxed);
+}
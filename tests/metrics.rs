@@ -0,0 +1,61 @@
+//! The writer contention counted with the `metrics` feature.
+
+#![cfg(all(feature = "metrics", not(feature = "critical-section")))]
+
+use seqlock::SeqLock;
+use std::thread;
+
+const WRITES: u64 = if cfg!(miri) { 100 } else { 100_000 };
+
+#[test]
+fn uncontended_writes_are_not_counted() {
+    let lock = SeqLock::new(0u64);
+    for i in 0..10 {
+        lock.write(i);
+    }
+    lock.update(|val| *val += 1);
+    drop(lock.lock_write());
+
+    // A failed `try_lock_write` doesn't wait, so it isn't contention either.
+    let guard = lock.lock_write();
+    assert!(lock.try_lock_write().is_none());
+    drop(guard);
+
+    assert_eq!(lock.stats().writes, 13);
+    assert_eq!(lock.writer_contention_count(), 0);
+}
+
+#[test]
+fn blocked_writer_is_counted_once() {
+    let lock = SeqLock::new(0u64);
+    thread::scope(|s| {
+        let guard = lock.lock_write();
+        let writer = s.spawn(|| lock.update(|val| *val += 1));
+        while lock.writer_contention_count() == 0 {
+            thread::yield_now();
+        }
+        drop(guard);
+        writer.join().unwrap();
+    });
+    assert_eq!(lock.read(), 1);
+    assert_eq!(lock.writer_contention_count(), 1);
+}
+
+#[test]
+fn competing_writers() {
+    let lock = SeqLock::new(0u64);
+    thread::scope(|s| {
+        for _ in 0..2 {
+            s.spawn(|| {
+                for _ in 0..WRITES {
+                    lock.update(|val| *val += 1);
+                }
+            });
+        }
+    });
+
+    // Every write took the mutex once, and at most all of them had to wait.
+    assert_eq!(lock.read(), 2 * WRITES);
+    assert_eq!(lock.stats().writes, 2 * WRITES);
+    assert!(lock.writer_contention_count() <= 2 * WRITES);
+}
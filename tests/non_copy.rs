@@ -0,0 +1,66 @@
+//! A type which is `SeqlockSafe` without being `Copy`.
+
+#[cfg(feature = "critical-section")]
+mod common;
+
+use seqlock::test_utils::{assert_untorn, patterned_payload};
+use seqlock::{SeqLock, SeqlockSafe};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+
+const WRITES: u64 = if cfg!(miri) { 20 } else { 10_000 };
+
+// Deliberately neither `Copy` nor `Clone`.
+#[derive(Debug, PartialEq)]
+struct Sample {
+    payload: [u64; 8],
+}
+
+unsafe impl SeqlockSafe for Sample {}
+
+#[test]
+fn reads_and_writes() {
+    let lock = SeqLock::new_bitwise(Sample {
+        payload: patterned_payload(0),
+    });
+    assert_eq!(lock.read_bitwise().payload, patterned_payload(0));
+    lock.write_bitwise(Sample {
+        payload: patterned_payload(1),
+    });
+    assert_eq!(lock.read_bitwise().payload, patterned_payload(1));
+    assert_eq!(lock.current_seq(), 2);
+}
+
+#[test]
+fn concurrent_reads_are_untorn() {
+    let lock = SeqLock::new_bitwise(Sample {
+        payload: patterned_payload(0),
+    });
+    let done = AtomicBool::new(false);
+    thread::scope(|s| {
+        s.spawn(|| {
+            for i in 1..=WRITES {
+                lock.write_bitwise(Sample {
+                    payload: patterned_payload(i),
+                });
+            }
+            done.store(true, Ordering::Relaxed);
+        });
+        let mut last = 0;
+        while !done.load(Ordering::Relaxed) {
+            let id = assert_untorn(&lock.read_bitwise().payload);
+            assert!(id >= last);
+            last = id;
+        }
+    });
+    assert_eq!(lock.read_bitwise().payload, patterned_payload(WRITES));
+}
+
+#[test]
+fn const_construction() {
+    static LOCK: SeqLock<Sample> = SeqLock::new_bitwise(Sample { payload: [0; 8] });
+    LOCK.write_bitwise(Sample {
+        payload: patterned_payload(1),
+    });
+    assert_eq!(LOCK.read_bitwise().payload, patterned_payload(1));
+}
@@ -0,0 +1,94 @@
+//! The ordering of the sequence number accesses, checked on real threads.
+//!
+//! The writer's `Release` store of the even sequence number pairs with the
+//! reader's `Acquire` load of it, so a read which sees a sequence number also
+//! sees the stores of the write which published it. The writer's `Release`
+//! fence after making the sequence number odd pairs with the reader's
+//! `Acquire` fence before its second load, so a copy which saw any store of a
+//! later write is retried. Together, a validated read returns both words of
+//! one write, and the sequence number it returns is the one of that write.
+//!
+//! The same pairing makes a write release everything the writer did before
+//! it to readers which get its value.
+//!
+//! Unlike the loom models this runs without a special build, so it guards
+//! the orderings on whatever CPU CI runs on.
+
+#[cfg(feature = "critical-section")]
+mod common;
+
+use seqlock::SeqLock;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+
+const WRITES: usize = if cfg!(miri) { 100 } else { 2_000_000 };
+
+#[derive(Clone, Copy, Debug)]
+struct Pair {
+    a: usize,
+    b: usize,
+}
+
+#[test]
+fn two_word_reads_never_tear() {
+    let lock = Arc::new(SeqLock::new(Pair { a: 0, b: !0 }));
+    let done = Arc::new(AtomicBool::new(false));
+
+    let writer = {
+        let (lock, done) = (lock.clone(), done.clone());
+        thread::spawn(move || {
+            for i in 1..=WRITES {
+                lock.write(Pair { a: i, b: !i });
+            }
+            done.store(true, Ordering::Release);
+        })
+    };
+
+    let mut last = 0;
+    while !done.load(Ordering::Acquire) {
+        let (pair, seq) = lock.read_versioned();
+        assert_eq!(pair.b, !pair.a, "torn read {pair:?}");
+        // Write `i` publishes sequence number `2 * i`.
+        assert_eq!(seq, 2 * pair.a, "{pair:?} read with sequence number {seq}");
+        assert!(
+            pair.a >= last,
+            "read went backwards from {last} to {pair:?}"
+        );
+        last = pair.a;
+    }
+    writer.join().unwrap();
+
+    // Everything the writer did happens-before `done` was seen.
+    assert_eq!(lock.read_versioned().1, 2 * WRITES);
+}
+
+// A reader which gets a value also sees what the writer did before writing
+// it, through the release store publishing it.
+#[test]
+fn writes_release_earlier_accesses() {
+    let lock = Arc::new(SeqLock::new(0usize));
+    let before = Arc::new(AtomicUsize::new(0));
+    let done = Arc::new(AtomicBool::new(false));
+
+    let writer = {
+        let (lock, before, done) = (lock.clone(), before.clone(), done.clone());
+        thread::spawn(move || {
+            for i in 1..=WRITES {
+                before.store(i, Ordering::Relaxed);
+                lock.write(i);
+            }
+            done.store(true, Ordering::Release);
+        })
+    };
+
+    while !done.load(Ordering::Acquire) {
+        let val = lock.read();
+        let seen = before.load(Ordering::Relaxed);
+        assert!(
+            seen >= val,
+            "read {val} but only saw {seen} stored before it"
+        );
+    }
+    writer.join().unwrap();
+}
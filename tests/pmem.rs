@@ -0,0 +1,58 @@
+//! Writes going through the cache line flushes of the `pmem` feature.
+
+#![cfg(all(feature = "pmem", target_arch = "x86_64", not(miri)))]
+
+#[cfg(feature = "critical-section")]
+mod common;
+
+use seqlock::test_utils::{assert_untorn, patterned_payload};
+use seqlock::SeqLock;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+
+#[test]
+fn writes_spanning_cache_lines_are_flushed() {
+    // Unaligned within its cache lines, so that the flushes cover a partial
+    // line at both ends.
+    #[repr(C)]
+    #[derive(Clone, Copy)]
+    struct Unaligned {
+        head: [u8; 24],
+        payload: [u64; 32],
+    }
+
+    let lock = SeqLock::new(Unaligned {
+        head: [0; 24],
+        payload: patterned_payload(0),
+    });
+    lock.write(Unaligned {
+        head: [1; 24],
+        payload: patterned_payload(1),
+    });
+    let val = lock.read();
+    assert_eq!((val.head, val.payload), ([1; 24], patterned_payload(1)));
+    assert_eq!(lock.current_seq(), 2);
+}
+
+#[test]
+fn concurrent_reads_of_flushed_writes_are_untorn() {
+    const WRITES: u64 = 2000;
+
+    let lock = SeqLock::new(patterned_payload::<16>(0));
+    let done = AtomicBool::new(false);
+    thread::scope(|s| {
+        s.spawn(|| {
+            for i in 1..=WRITES {
+                lock.update(|val| *val = patterned_payload(i));
+            }
+            done.store(true, Ordering::Release);
+        });
+        while !done.load(Ordering::Acquire) {
+            assert_untorn(&lock.read());
+        }
+    });
+    assert_eq!(
+        lock.read_versioned(),
+        (patterned_payload(WRITES), 2 * WRITES as usize)
+    );
+}
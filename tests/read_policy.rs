@@ -0,0 +1,84 @@
+//! Each `ReadPolicy` of `read_with_policy`, with and without a writer
+//! holding the lock.
+
+// `ReadPolicy::Timeout` needs `Instant`, which wasm32-unknown-unknown lacks.
+#![cfg(all(
+    feature = "std",
+    not(feature = "critical-section"),
+    not(all(target_family = "wasm", target_os = "unknown"))
+))]
+
+use seqlock::{ReadMiss, ReadOutcome, ReadPolicy, SeqLock};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const POLICIES: [ReadPolicy; 5] = [
+    ReadPolicy::Block,
+    ReadPolicy::TryOnce,
+    ReadPolicy::Spin(10),
+    ReadPolicy::Yield(10),
+    ReadPolicy::Timeout(Duration::from_millis(10)),
+];
+
+#[test]
+fn every_policy_reads_unlocked_value() {
+    let lock = SeqLock::new(5);
+    lock.write(6);
+    for policy in POLICIES {
+        assert_eq!(
+            lock.read_with_policy(policy),
+            ReadOutcome::Read(6),
+            "{policy:?}"
+        );
+    }
+}
+
+#[test]
+fn bounded_policies_give_up_on_active_writer() {
+    let lock = SeqLock::new(5);
+    let _guard = lock.lock_write();
+    for policy in [
+        ReadPolicy::TryOnce,
+        ReadPolicy::Spin(10),
+        ReadPolicy::Yield(10),
+    ] {
+        assert_eq!(
+            lock.read_with_policy(policy),
+            ReadOutcome::GaveUp(ReadMiss::WriterActive),
+            "{policy:?}"
+        );
+    }
+    assert_eq!(lock.read_with_policy(ReadPolicy::Spin(0)).ok(), None);
+}
+
+#[test]
+fn timeout_waits_before_giving_up() {
+    let lock = SeqLock::new(5);
+    let _guard = lock.lock_write();
+    let start = Instant::now();
+    let timeout = Duration::from_millis(20);
+    assert_eq!(
+        lock.read_with_policy(ReadPolicy::Timeout(timeout)),
+        ReadOutcome::TimedOut
+    );
+    assert!(start.elapsed() >= timeout);
+}
+
+#[test]
+fn waiting_policies_see_finished_write() {
+    for policy in [
+        ReadPolicy::Block,
+        ReadPolicy::Spin(u32::MAX),
+        ReadPolicy::Yield(u32::MAX),
+        ReadPolicy::Timeout(Duration::from_secs(60)),
+    ] {
+        let lock = SeqLock::new(5);
+        thread::scope(|s| {
+            let mut guard = lock.lock_write();
+            let reader = s.spawn(|| lock.read_with_policy(policy));
+            *guard = 6;
+            drop(guard);
+            assert_eq!(reader.join().unwrap(), ReadOutcome::Read(6), "{policy:?}");
+        });
+    }
+}
@@ -0,0 +1,84 @@
+//! Readers and writers hammering a multi-word payload, mainly as a target for
+//! ThreadSanitizer, which must not report any race in the crate:
+//!
+//! ```sh
+//! RUSTFLAGS="-Zsanitizer=thread" cargo +nightly test -Zbuild-std \
+//!     --target x86_64-unknown-linux-gnu --test sanitizer
+//! ```
+
+#[cfg(feature = "critical-section")]
+mod common;
+
+use seqlock::test_utils::{patterned_payload, stress};
+use seqlock::SeqLock;
+use std::time::Duration;
+
+const WRITERS: usize = 2;
+const READERS: usize = 4;
+const WRITES: u64 = if cfg!(miri) { 10 } else { 10_000 };
+
+#[test]
+fn readers_and_writers_hammer_payload() {
+    let lock = SeqLock::new(patterned_payload::<16>(0));
+    let report = stress(
+        READERS,
+        WRITERS,
+        Duration::MAX,
+        WRITES,
+        || lock.read(),
+        |val| {
+            if val[0] % 2 == 0 {
+                lock.write(val);
+            } else {
+                lock.update(|old| *old = val);
+            }
+        },
+    );
+    assert_eq!(report.writes, WRITERS as u64 * WRITES);
+    assert_eq!(lock.current_seq() as u64, report.writes * 2);
+}
+
+#[test]
+fn single_unchecked_writer() {
+    let lock = SeqLock::new(patterned_payload::<16>(0));
+    let report = stress(
+        READERS,
+        1,
+        Duration::MAX,
+        WRITES,
+        || lock.read(),
+        // With one writer, `stress` only writes from a single thread.
+        |val| unsafe { lock.write_unchecked(|old| *old = val) },
+    );
+    assert_eq!(report.writes, WRITES);
+    assert_eq!(lock.current_seq() as u64, report.writes * 2);
+}
+
+// Payloads as large as their alignment, which are copied with a single
+// atomic access on both sides. Every byte of a value written is the same.
+macro_rules! word_payload {
+    ($test:ident, $ty:ty) => {
+        #[test]
+        fn $test() {
+            let lock = SeqLock::new(<$ty>::from_ne_bytes([0; std::mem::size_of::<$ty>()]));
+            std::thread::scope(|s| {
+                for _ in 0..READERS {
+                    s.spawn(|| loop {
+                        let bytes = lock.read().to_ne_bytes();
+                        assert!(bytes.iter().all(|&b| b == bytes[0]), "torn read {bytes:?}");
+                        if bytes[0] == u8::MAX {
+                            break;
+                        }
+                    });
+                }
+                for b in 1..=u8::MAX {
+                    lock.write(<$ty>::from_ne_bytes([b; std::mem::size_of::<$ty>()]));
+                }
+            });
+        }
+    };
+}
+
+word_payload!(u16_payload, u16);
+word_payload!(u32_payload, u32);
+word_payload!(u64_payload, u64);
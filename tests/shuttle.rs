@@ -0,0 +1,167 @@
+//! Randomized concurrency tests of the `SeqLock` protocol with shuttle.
+//!
+//! Run with `RUSTFLAGS="--cfg shuttle" cargo test --test shuttle --release`.
+//! Each test runs `SHUTTLE_ITERATIONS` random schedules, 1000 by default.
+
+#![cfg(shuttle)]
+
+use seqlock::SeqLock;
+use shuttle::sync::Arc;
+use shuttle::thread;
+
+fn check(f: impl Fn() + Send + Sync + 'static) {
+    let iterations = std::env::var("SHUTTLE_ITERATIONS")
+        .ok()
+        .map(|n| n.parse().expect("SHUTTLE_ITERATIONS must be a number"))
+        .unwrap_or(1000);
+    shuttle::check_random(f, iterations);
+}
+
+// A payload whose last word is a checksum of the others, so that a reader can
+// tell whether it got a consistent snapshot.
+fn payload(n: u64) -> [u64; 4] {
+    let words = [n, n.wrapping_mul(31), !n];
+    [words[0], words[1], words[2], checksum(&words)]
+}
+
+fn checksum(words: &[u64]) -> u64 {
+    words.iter().fold(0xcbf2_9ce4_8422_2325, |acc, &w| {
+        (acc ^ w).wrapping_mul(0x100_0000_01b3)
+    })
+}
+
+fn assert_consistent(val: &[u64; 4]) {
+    assert_eq!(val[3], checksum(&val[..3]), "torn read {val:?}");
+}
+
+#[test]
+fn mixed_readers_and_writers_see_consistent_snapshots() {
+    check(|| {
+        let lock = Arc::new(SeqLock::new(payload(0)));
+
+        let writers: Vec<_> = (0..2)
+            .map(|w| {
+                let lock = lock.clone();
+                thread::spawn(move || {
+                    for i in 0..3 {
+                        lock.write(payload(w * 100 + i));
+                    }
+                })
+            })
+            .collect();
+
+        let readers: Vec<_> = (0..3)
+            .map(|_| {
+                let lock = lock.clone();
+                thread::spawn(move || {
+                    let mut last_seq = 0;
+                    for _ in 0..3 {
+                        let (val, seq) = lock.read_versioned();
+                        assert_consistent(&val);
+                        assert_eq!(seq & 1, 0);
+                        assert!(seq >= last_seq);
+                        last_seq = seq;
+                    }
+                })
+            })
+            .collect();
+
+        for thread in writers.into_iter().chain(readers) {
+            thread.join().unwrap();
+        }
+        assert_consistent(&lock.read());
+        assert_eq!(lock.current_seq(), 12);
+    });
+}
+
+#[test]
+fn try_lock_write_is_exclusive() {
+    check(|| {
+        let lock = Arc::new(SeqLock::new(payload(0)));
+
+        let writer = {
+            let lock = lock.clone();
+            thread::spawn(move || {
+                let mut guard = lock.lock_write();
+                thread::yield_now();
+                *guard = payload(1);
+            })
+        };
+
+        // Either the other writer holds the lock, or it has not started or
+        // has finished writing.
+        match lock.try_lock_write() {
+            Some(mut guard) => {
+                assert_consistent(&guard);
+                *guard = payload(2);
+            }
+            None => lock.write(payload(2)),
+        }
+
+        writer.join().unwrap();
+        assert_consistent(&lock.read());
+        assert_eq!(lock.current_seq(), 4);
+    });
+}
+
+#[test]
+fn read_watchdog_gives_up_only_on_an_active_writer() {
+    check(|| {
+        let lock = Arc::new(SeqLock::new(payload(0)));
+
+        let writer = {
+            let lock = lock.clone();
+            thread::spawn(move || {
+                lock.update(|val| {
+                    thread::yield_now();
+                    *val = payload(1);
+                });
+            })
+        };
+
+        match lock.read_watchdog(2) {
+            Ok(val) => assert_consistent(&val),
+            Err(stuck) => assert_eq!(stuck.seq() & 1, 1),
+        }
+
+        writer.join().unwrap();
+        assert_eq!(lock.read_watchdog(2).unwrap(), payload(1));
+    });
+}
+
+#[test]
+fn guard_drop_ends_write_before_unlocking() {
+    check(|| {
+        let lock = Arc::new(SeqLock::new(payload(0)));
+
+        let writers: Vec<_> = (0..2)
+            .map(|w| {
+                let lock = lock.clone();
+                thread::spawn(move || {
+                    let mut guard = lock.lock_write();
+                    // If the previous writer released the mutex before
+                    // ending its write, this would still be even.
+                    assert_eq!(lock.current_seq() & 1, 1);
+                    *guard = payload(w + 1);
+                })
+            })
+            .collect();
+
+        // Dropping a guard publishes its write even while other writers are
+        // waiting for the mutex.
+        let (val, seq) = {
+            let mut guard = lock.lock_write();
+            *guard = payload(3);
+            drop(guard);
+            lock.read_versioned()
+        };
+        assert_consistent(&val);
+        assert_eq!(seq & 1, 0);
+
+        for writer in writers {
+            writer.join().unwrap();
+        }
+        assert_consistent(&lock.read());
+        assert_eq!(lock.current_seq(), 6);
+    });
+}
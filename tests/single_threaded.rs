@@ -0,0 +1,63 @@
+//! The writer lock of `--cfg seqlock_unsafe_assume_single_writer`, which only
+//! excludes reentrant writers. Run with
+//! `RUSTFLAGS="--cfg seqlock_unsafe_assume_single_writer" cargo test --test single_threaded`:
+//! the flag is unsound for the other tests, which write from several threads.
+
+#![cfg(seqlock_unsafe_assume_single_writer)]
+
+use seqlock::SeqLock;
+use std::thread;
+
+#[test]
+fn writes_are_bracketed() {
+    let lock = SeqLock::new([0u64; 8]);
+    {
+        let mut guard = lock.lock_write();
+        assert_eq!(lock.current_seq(), 1);
+        guard[0] = 1;
+    }
+    assert_eq!(lock.read_versioned(), ([1, 0, 0, 0, 0, 0, 0, 0], 2));
+    lock.write([2; 8]);
+    lock.update(|val| val[7] = 3);
+    assert_eq!(lock.read_versioned(), ([2, 2, 2, 2, 2, 2, 2, 3], 6));
+}
+
+#[test]
+fn try_lock_excludes_reentrant_writer() {
+    let lock = SeqLock::new(0u32);
+    let guard = lock.try_lock_write().unwrap();
+    assert!(lock.try_lock_write().is_none());
+    drop(guard);
+    *lock.try_lock_write().unwrap() = 1;
+    // Both guards published a write.
+    assert_eq!(lock.read_versioned(), (1, 4));
+}
+
+#[test]
+#[should_panic(expected = "already locked by this thread")]
+fn reentrant_lock_panics() {
+    let lock = SeqLock::new(0u32);
+    let _guard = lock.lock_write();
+    lock.write(1);
+}
+
+#[test]
+fn readers_on_other_threads() {
+    const WRITES: u64 = if cfg!(miri) { 10 } else { 10_000 };
+    let lock = SeqLock::new([0u64; 8]);
+    thread::scope(|s| {
+        for _ in 0..2 {
+            s.spawn(|| loop {
+                let val = lock.read();
+                assert!(val.iter().all(|&x| x == val[0]));
+                if val[0] == WRITES {
+                    break;
+                }
+            });
+        }
+        // The only writer.
+        for i in 1..=WRITES {
+            lock.write([i; 8]);
+        }
+    });
+}
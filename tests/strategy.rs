@@ -0,0 +1,637 @@
+//! The same tests run against every `Strategy`, so that `MutexRef` stays
+//! equivalent to `Seq`.
+
+#[cfg(feature = "critical-section")]
+mod common;
+
+use seqlock::test_utils::{assert_untorn, patterned_payload, payload_id, stress};
+use seqlock::{SeqLock, Snapshot, Strategy};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+const WRITES: u64 = if cfg!(miri) { 10 } else { 2000 };
+
+fn reads_and_writes<S: Strategy>() {
+    let lock = SeqLock::<_, S>::with_strategy(1u32);
+    assert_eq!(lock.read_versioned(), (1, 0));
+    lock.prewarm();
+    assert_eq!(lock.read_versioned(), (1, 0));
+    assert!(!lock.has_been_written());
+    lock.write(2);
+    assert!(lock.has_been_written());
+    assert_eq!(lock.read_versioned(), (2, 2));
+    assert_eq!(lock.update(|x| std::mem::replace(x, 3)), 2);
+    assert_eq!(lock.read_latest(), (3, 4));
+
+    assert!(!lock.set_if_ne(3));
+    assert!(lock.set_if_ne(4));
+    assert_eq!(lock.swap_if(|&x| x == 0, 5), Err(4));
+    assert_eq!(lock.swap_if(|&x| x == 4, 5), Ok(4));
+    assert_eq!(lock.current_seq(), 8);
+    assert_eq!(lock.replace_if_ne(5), None);
+    assert_eq!(lock.current_seq(), 8);
+    assert_eq!(lock.replace_if_ne(6), Some(5));
+    assert_eq!(lock.read_versioned(), (6, 10));
+
+    let snapshot = lock.read_maybe_uninit();
+    assert_eq!(unsafe { snapshot.assume_init() }, 6);
+    assert_eq!(lock.into_parts(), (6, 10));
+}
+
+#[cfg(not(feature = "critical-section"))]
+fn guards<S: Strategy>() {
+    let lock = SeqLock::<_, S>::with_strategy([1u64; 4]);
+    let mut guard = lock.lock_write();
+    assert!(lock.try_lock_write().is_none());
+    guard[0] = 2;
+    assert_eq!(*guard, [2, 1, 1, 1]);
+    drop(guard);
+    *lock.try_lock_write().unwrap() = [3; 4];
+    assert_eq!(lock.read_versioned(), ([3; 4], 4));
+}
+
+#[cfg(not(feature = "critical-section"))]
+fn paired_transfers<S: Strategy>() {
+    const TRANSFERS: i64 = if cfg!(miri) { 20 } else { 2000 };
+    let accounts = [
+        SeqLock::<_, S>::with_strategy(1000i64),
+        SeqLock::<_, S>::with_strategy(1000i64),
+    ];
+    std::thread::scope(|s| {
+        // Transfers in both directions take the locks with the arguments in
+        // opposite orders, which must not deadlock.
+        for (from, to) in [(0, 1), (1, 0)] {
+            let accounts = &accounts;
+            s.spawn(move || {
+                for i in 0..TRANSFERS {
+                    let (mut a, mut b) = accounts[from].lock_write_pair(&accounts[to]);
+                    *a -= i % 7;
+                    *b += i % 7;
+                }
+            });
+        }
+    });
+    assert_eq!(accounts[0].read() + accounts[1].read(), 2000);
+    assert_eq!(accounts[0].read(), accounts[1].read());
+    assert_eq!(accounts[0].current_seq() as i64, 4 * TRANSFERS);
+
+    let res = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        let _ = accounts[0].lock_write_pair(&accounts[0]);
+    }));
+    assert!(res.is_err());
+}
+
+#[cfg(not(feature = "critical-section"))]
+fn spinning_try_lock<S: Strategy>() {
+    let lock = SeqLock::<_, S>::with_strategy(0u32);
+    let guard = lock.lock_write();
+    assert!(lock.try_lock_write_spin(10).is_none());
+    drop(guard);
+    assert_eq!(lock.current_seq(), 2);
+
+    // A writer which only holds the lock briefly is eventually waited out.
+    let held = AtomicBool::new(false);
+    std::thread::scope(|s| {
+        s.spawn(|| {
+            let mut guard = lock.lock_write();
+            held.store(true, Ordering::Release);
+            std::thread::sleep(Duration::from_millis(1));
+            *guard = 1;
+        });
+        while !held.load(Ordering::Acquire) {
+            std::hint::spin_loop();
+        }
+        let mut guard = lock
+            .try_lock_write_spin(u32::MAX)
+            .expect("spinning never acquired the lock");
+        assert_eq!(*guard, 1);
+        *guard = 2;
+    });
+    assert_eq!(lock.read_versioned(), (2, 6));
+}
+
+#[cfg(not(feature = "critical-section"))]
+fn write_then_read<S: Strategy>() {
+    let lock = SeqLock::<_, S>::with_strategy(0u64);
+    let mut guard = lock.lock_write();
+    *guard += 1;
+    assert_eq!(guard.finish_and_read(), 1);
+
+    // A thread alternating between writing and reading back, with another
+    // writer interleaved.
+    std::thread::scope(|s| {
+        s.spawn(|| {
+            for _ in 0..WRITES {
+                lock.update(|val| *val += 1 << 32);
+            }
+        });
+        for i in 1..=WRITES {
+            let mut guard = lock.lock_write();
+            *guard += 1;
+            let mut snapshot = guard.downgrade();
+            assert_eq!(*snapshot & 0xffff_ffff, i + 1);
+            if snapshot.refresh() {
+                assert_eq!(*snapshot & 0xffff_ffff, i + 1);
+            }
+        }
+    });
+    let snapshot = lock.lock_write().downgrade();
+    assert_eq!(*snapshot, WRITES << 32 | (WRITES + 1));
+    assert_eq!(snapshot.seq() as u64, 2 * (2 * WRITES + 2));
+    assert!(!snapshot.is_stale());
+}
+
+#[cfg(not(feature = "critical-section"))]
+fn previous_values<S: Strategy>() {
+    let lock = SeqLock::<_, S>::with_strategy([0u64; 4]);
+    let mut guard = lock.lock_write_with_previous();
+    guard[0] = 1;
+    assert_eq!(*guard.previous(), [0; 4]);
+    assert_eq!(guard.finish_and_read(), [1, 0, 0, 0]);
+    assert_eq!(lock.read_versioned(), ([1, 0, 0, 0], 2));
+
+    // Every writer sees the value left by the one before it, whether it
+    // wrote through the plain guard or the one keeping the previous value.
+    std::thread::scope(|s| {
+        s.spawn(|| {
+            for _ in 0..WRITES {
+                lock.lock_write()[1] += 1;
+            }
+        });
+        let mut last = [1, 0, 0, 0];
+        for _ in 0..WRITES {
+            let mut guard = lock.lock_write_with_previous();
+            let previous = *guard.previous();
+            assert_eq!(*guard, previous);
+            assert_eq!(previous[2], last[2]);
+            assert!(previous[1] >= last[1]);
+            guard[2] += 1;
+            last = *guard;
+        }
+    });
+    assert_eq!(lock.read(), [1, WRITES, WRITES, 0]);
+}
+
+fn snapshots<S: Strategy>() {
+    let lock = SeqLock::<_, S>::with_strategy(1u64);
+    let mut snapshot = lock.read_guard();
+    assert_eq!((*snapshot, snapshot.seq()), (1, 0));
+    lock.write(2);
+    assert!(snapshot.is_stale());
+    assert_eq!(*snapshot, 1);
+    assert!(snapshot.refresh());
+    assert_eq!((*snapshot, snapshot.seq()), (2, 2));
+    assert!(!snapshot.is_stale());
+
+    // A write in progress already makes the snapshot stale.
+    lock.update(|val| {
+        *val = 3;
+        assert!(snapshot.is_stale());
+    });
+    assert!(!lock.read_guard().is_stale());
+}
+
+fn batched_updates<S: Strategy>() {
+    const BATCHES: u64 = if cfg!(miri) { 10 } else { 1000 };
+    let lock = SeqLock::<_, S>::with_strategy([0u64; 16]);
+    let done = AtomicBool::new(false);
+    std::thread::scope(|s| {
+        s.spawn(|| {
+            for _ in 0..BATCHES {
+                lock.apply_batch(0..16, |val, i| val[i] += 1);
+            }
+            done.store(true, Ordering::Relaxed);
+        });
+        while !done.load(Ordering::Relaxed) {
+            // Every element is incremented by each batch.
+            let (val, seq) = lock.read_versioned();
+            assert_eq!(val, [seq as u64 / 2; 16]);
+        }
+    });
+    assert_eq!(lock.read_versioned(), ([BATCHES; 16], 2 * BATCHES as usize));
+}
+
+fn generic_snapshots<S: Strategy>() {
+    fn checked_id(lock: impl Snapshot<Value = [u64; 8]>) -> u64 {
+        assert_untorn(&lock.snapshot())
+    }
+
+    let mut lock = SeqLock::<_, S>::with_strategy(patterned_payload(1));
+    assert_eq!(checked_id(&lock), 1);
+    assert_eq!(checked_id(&mut lock), 1);
+    lock.write(patterned_payload(2));
+    assert_eq!(checked_id(&mut lock), 2);
+    std::thread::scope(|s| {
+        s.spawn(|| lock.write(patterned_payload(3)));
+        assert!(checked_id(&lock) >= 2);
+    });
+    assert_eq!(checked_id(&mut lock), 3);
+    assert_eq!(lock.current_seq(), 4);
+}
+
+fn const_spin_reads<S: Strategy>() {
+    let lock = SeqLock::<_, S>::with_strategy(patterned_payload::<8>(0));
+    let done = AtomicBool::new(false);
+    std::thread::scope(|s| {
+        s.spawn(|| {
+            for i in 1..=WRITES {
+                lock.write(patterned_payload(i));
+            }
+            done.store(true, Ordering::Relaxed);
+        });
+        let mut last = (0, 0);
+        while !done.load(Ordering::Relaxed) {
+            let ids = (
+                assert_untorn(&lock.read_const_spin::<0>()),
+                assert_untorn(&lock.read_const_spin::<1000>()),
+            );
+            assert!(ids.0 >= last.1 && ids.1 >= ids.0);
+            last = ids;
+        }
+    });
+    assert_eq!(payload_id(&lock.read_const_spin::<1000>()), Some(WRITES));
+}
+
+fn derived_reads<S: Strategy>() {
+    let lock = SeqLock::<_, S>::with_strategy(patterned_payload::<8>(0));
+    std::thread::scope(|s| {
+        s.spawn(|| {
+            for i in 1..=WRITES {
+                lock.write(patterned_payload(i));
+            }
+        });
+        let mut last = 0;
+        while last != WRITES {
+            // A field, and a value computed from the whole payload. The
+            // first word of a patterned payload is its id.
+            let id = lock.read_map(|val| val[0]);
+            let (first, computed) = lock.read_map(|val| (val[0], payload_id(val)));
+            assert_eq!(computed, Some(first));
+            assert!(id >= last && first >= id);
+            last = id;
+        }
+    });
+}
+
+fn traits<S: Strategy>() {
+    let lock = SeqLock::<u32, S>::default();
+    assert_eq!(lock.read(), 0);
+    lock.write(2);
+    assert_eq!(format!("{lock:?}"), "SeqLock { data: 2 }");
+    let other = SeqLock::<u32, S>::with_strategy(3);
+    assert!(lock < other && lock != other);
+    assert_eq!(
+        lock.cmp(&SeqLock::with_strategy(2)),
+        std::cmp::Ordering::Equal
+    );
+}
+
+fn concurrent<S: Strategy>() {
+    let lock = SeqLock::<_, S>::with_strategy(patterned_payload::<8>(0));
+    let report = stress(
+        2,
+        2,
+        Duration::MAX,
+        WRITES,
+        || lock.read(),
+        |val| lock.write(val),
+    );
+    assert_eq!(report.writes, 2 * WRITES);
+    assert_eq!(lock.current_seq() as u64, 2 * report.writes);
+}
+
+fn bulk_writes<S: Strategy>() {
+    let locks: Vec<_> = (0..4)
+        .map(|_| SeqLock::<_, S>::with_strategy(patterned_payload::<4>(0)))
+        .collect();
+    let done = AtomicBool::new(false);
+    std::thread::scope(|s| {
+        s.spawn(|| {
+            while !done.load(Ordering::Relaxed) {
+                for lock in &locks {
+                    assert_untorn(&lock.read());
+                }
+            }
+        });
+        for i in 1..=WRITES {
+            let values = [patterned_payload(i); 4];
+            SeqLock::write_all(&locks, &values);
+        }
+        done.store(true, Ordering::Relaxed);
+    });
+    for lock in &locks {
+        assert_eq!(
+            lock.read_versioned(),
+            (patterned_payload(WRITES), 2 * WRITES as usize)
+        );
+    }
+}
+
+fn bulk_write_length_mismatch<S: Strategy>() {
+    let locks = [
+        SeqLock::<_, S>::with_strategy(0u8),
+        SeqLock::with_strategy(0),
+    ];
+    let res = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        SeqLock::write_all(&locks, &[1, 2, 3])
+    }));
+    assert!(res.is_err());
+    assert_eq!(
+        locks.each_ref().map(|lock| lock.read_versioned()),
+        [(0, 0); 2]
+    );
+}
+
+#[cfg(not(any(loom, shuttle)))]
+fn freezing<S: Strategy>() {
+    let mut lock = SeqLock::<_, S>::with_strategy([1u32; 16]);
+    assert_eq!(lock.read_ref_frozen(), None);
+    lock.write([2; 16]);
+    lock.freeze();
+    lock.freeze();
+    assert!(lock.is_frozen());
+    let value = lock.read_ref_frozen().unwrap();
+    assert_eq!(*value, [2; 16]);
+
+    // Every kind of write is rejected without touching the value or the
+    // sequence number.
+    let writes: [&dyn Fn(); 5] = [
+        &|| lock.write([3; 16]),
+        &|| lock.update(|val| val[0] = 3),
+        &|| {
+            lock.set_if_ne([3; 16]);
+        },
+        &|| {
+            let _ = lock.swap_if(|_| true, [3; 16]);
+        },
+        &|| lock.copy_into(&lock),
+    ];
+    for write in writes {
+        let res = std::panic::catch_unwind(std::panic::AssertUnwindSafe(write));
+        assert!(res.is_err());
+    }
+    #[cfg(not(feature = "critical-section"))]
+    {
+        let res = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| lock.lock_write()));
+        assert!(res.is_err());
+    }
+    assert!(std::ptr::eq(value, lock.read_ref_frozen().unwrap()));
+    assert_eq!(lock.read_versioned(), ([2; 16], 2));
+
+    // A conditional write which turns out not to be needed is fine.
+    assert!(!lock.set_if_ne([2; 16]));
+    lock.get_mut()[0] = 4;
+    assert_eq!(lock.read()[0], 4);
+
+    // Once reset, the lock can be written again.
+    assert!(lock.has_been_written());
+    lock.reset()[0] = 5;
+    assert!(!lock.is_frozen());
+    assert!(!lock.has_been_written());
+    assert_eq!(lock.read_ref_frozen(), None);
+    lock.write([6; 16]);
+    assert_eq!(lock.read_versioned(), ([6; 16], 4));
+}
+
+fn scratch_reads<S: Strategy>() {
+    let lock = Box::new(SeqLock::<_, S>::with_strategy(patterned_payload::<512>(0)));
+    let report = stress(
+        2,
+        1,
+        Duration::MAX,
+        WRITES,
+        || {
+            let mut scratch = Box::new_uninit();
+            *lock.read_with_scratch(&mut scratch)
+        },
+        |val| lock.write(val),
+    );
+    assert_eq!(report.writes, WRITES);
+
+    // The scratch space can be reused, and its previous contents don't matter.
+    let mut scratch = Box::new(std::mem::MaybeUninit::new([u64::MAX; 512]));
+    assert_eq!(lock.read_with_scratch(&mut scratch), &lock.read());
+    lock.write(patterned_payload(1));
+    assert_eq!(lock.read_with_scratch(&mut scratch), &patterned_payload(1));
+}
+
+#[cfg(feature = "std")]
+fn batch_reads<S: Strategy>() {
+    let locks: Vec<_> = (0..8).map(|i| SeqLock::<_, S>::with_strategy(i)).collect();
+    let polled = SeqLock::read_all_versioned(&locks);
+    assert_eq!(polled, (0..8).map(|i| (i, 0)).collect::<Vec<_>>());
+    let mut seqs: Vec<_> = polled.iter().map(|&(_, seq)| seq).collect();
+    assert!(SeqLock::read_all_changed(&locks, &seqs).is_empty());
+
+    locks[1].write(10);
+    locks[6].write(60);
+    locks[6].write(61);
+    let changed = SeqLock::read_all_changed(&locks, &seqs);
+    assert_eq!(changed, [(1, 10, 2), (6, 61, 4)]);
+    for &(i, _, seq) in &changed {
+        seqs[i] = seq;
+    }
+    assert!(SeqLock::read_all_changed(&locks, &seqs).is_empty());
+
+    locks[0].write(0);
+    assert_eq!(SeqLock::read_all_changed(&locks, &seqs), [(0, 0, 2)]);
+    let res = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        SeqLock::read_all_changed(&locks, &seqs[1..])
+    }));
+    assert!(res.is_err());
+}
+
+#[cfg(feature = "std")]
+fn histories<S: Strategy>() {
+    let lock = SeqLock::<_, S>::with_strategy(0u32);
+    let (mut all, mut changes) = (Vec::new(), Vec::new());
+    let mut seq = lock.read_append_if_changed(&mut changes, usize::MAX);
+    for i in 1..=3 {
+        // Unchanged values are only recorded by `read_append`.
+        for _ in 0..2 {
+            lock.read_append(&mut all);
+            seq = lock.read_append_if_changed(&mut changes, seq);
+        }
+        lock.write(i);
+    }
+    seq = lock.read_append_if_changed(&mut changes, seq);
+    assert_eq!(all, [0, 0, 1, 1, 2, 2]);
+    assert_eq!(changes, [0, 1, 2, 3]);
+    assert_eq!(seq, 6);
+}
+
+#[cfg(feature = "std")]
+fn boxed_reads<S: Strategy>() {
+    #[derive(Clone, Copy)]
+    struct Large {
+        header: u64,
+        samples: [[u64; 8]; 256],
+        trailer: u64,
+    }
+
+    let large = |i: u64| Large {
+        header: i,
+        samples: [patterned_payload(i); 256],
+        trailer: !i,
+    };
+    let lock = Box::new(SeqLock::<_, S>::with_strategy(large(0)));
+    std::thread::scope(|s| {
+        s.spawn(|| {
+            for i in 1..=WRITES {
+                lock.write(large(i));
+            }
+        });
+        for _ in 0..WRITES {
+            let boxed = lock.read_boxed();
+            assert_eq!(boxed.trailer, !boxed.header);
+            for sample in &boxed.samples {
+                assert_eq!(assert_untorn(sample), boxed.header);
+            }
+        }
+    });
+
+    // Once writes are done, field by field equal to `read`.
+    let (boxed, read) = (lock.read_boxed(), lock.read());
+    assert_eq!((boxed.header, boxed.trailer), (read.header, read.trailer));
+    assert_eq!(boxed.samples, read.samples);
+}
+
+fn mirroring<S: Strategy>() {
+    let source = SeqLock::<_, S>::with_strategy(patterned_payload::<8>(0));
+    // One mirror uses the other strategy, to cover copies across them.
+    let mirrors = (
+        SeqLock::<_, seqlock::Seq>::with_strategy(patterned_payload(0)),
+        SeqLock::<_, seqlock::MutexRef>::with_strategy(patterned_payload(0)),
+    );
+    std::thread::scope(|s| {
+        s.spawn(|| {
+            for i in 1..=WRITES {
+                source.write(patterned_payload(i));
+            }
+        });
+        s.spawn(|| {
+            let mut last = (0, 0);
+            while last.1 != WRITES {
+                let ids = (
+                    assert_untorn(&mirrors.0.read()),
+                    assert_untorn(&mirrors.1.read()),
+                );
+                assert!(ids.0 >= last.0 && ids.1 >= last.1);
+                last = ids;
+            }
+        });
+        // The second mirror is written last, so both hold the final value
+        // once the first one does.
+        let mut last = 0;
+        while last != WRITES {
+            source.copy_into(&mirrors.0);
+            source.copy_into(&mirrors.1);
+            last = assert_untorn(&mirrors.0.read());
+        }
+    });
+    assert_eq!(mirrors.1.read(), source.read());
+}
+
+fn consistent_reads<S: Strategy>() {
+    // The second field is always the complement of the first.
+    let lock = SeqLock::<_, S>::with_strategy((0u64, !0u64));
+
+    // A write between the two reads makes the closure run again.
+    let mut runs = 0;
+    let (a, b) = lock.read_consistent(|reader| {
+        runs += 1;
+        let a = reader.map(|pair| pair.0);
+        if runs == 1 {
+            lock.write((1, !1));
+        }
+        (a, reader.map(|pair| pair.1))
+    });
+    assert_eq!(b, !a);
+    assert!(runs <= 2);
+
+    let done = AtomicBool::new(false);
+    std::thread::scope(|s| {
+        s.spawn(|| {
+            for i in 2..WRITES {
+                lock.write((i, !i));
+            }
+            done.store(true, Ordering::Relaxed);
+        });
+        while !done.load(Ordering::Relaxed) {
+            let (a, b) = lock
+                .read_consistent(|reader| (reader.map(|pair| pair.0), reader.map(|pair| pair.1)));
+            assert_eq!(b, !a);
+        }
+    });
+}
+
+fn bitwise_ops<S: Strategy>() {
+    let lock = SeqLock::<_, S>::with_strategy(0b1100u128 << 100);
+    assert_eq!(lock.fetch_or(0b1010 << 100), 0b1100 << 100);
+    assert_eq!(lock.fetch_and(0b0110 << 100), 0b1110 << 100);
+    assert_eq!(lock.fetch_xor(0b0011 << 100), 0b0110 << 100);
+    assert_eq!(lock.read_versioned(), (0b0101 << 100, 6));
+
+    // Each thread sets its own bit, and none of them are lost.
+    let flags = SeqLock::<_, S>::with_strategy(0u64);
+    std::thread::scope(|s| {
+        for bit in 0..8 {
+            let flags = &flags;
+            s.spawn(move || {
+                for _ in 0..WRITES / 8 {
+                    let prev = flags.fetch_xor(1 << bit);
+                    flags.fetch_xor(1 << bit);
+                    assert_eq!(prev & 1 << bit, 0);
+                }
+                flags.fetch_or(1 << bit);
+            });
+        }
+    });
+    assert_eq!(flags.read(), 0xff);
+}
+
+macro_rules! for_each_strategy {
+    ($($(#[$attr:meta])* $test:ident),*) => {
+        mod seq {
+            $($(#[$attr])* #[test] fn $test() { super::$test::<seqlock::Seq>() })*
+        }
+        mod mutex_ref {
+            $($(#[$attr])* #[test] fn $test() { super::$test::<seqlock::MutexRef>() })*
+        }
+    };
+}
+
+for_each_strategy!(
+    reads_and_writes,
+    #[cfg(not(feature = "critical-section"))]
+    guards,
+    #[cfg(not(feature = "critical-section"))]
+    write_then_read,
+    #[cfg(not(feature = "critical-section"))]
+    previous_values,
+    #[cfg(not(feature = "critical-section"))]
+    spinning_try_lock,
+    #[cfg(not(feature = "critical-section"))]
+    paired_transfers,
+    snapshots,
+    traits,
+    derived_reads,
+    const_spin_reads,
+    generic_snapshots,
+    batched_updates,
+    mirroring,
+    consistent_reads,
+    bitwise_ops,
+    concurrent,
+    bulk_writes,
+    bulk_write_length_mismatch,
+    #[cfg(feature = "std")]
+    batch_reads,
+    #[cfg(feature = "std")]
+    histories,
+    #[cfg(feature = "std")]
+    boxed_reads,
+    scratch_reads,
+    #[cfg(not(any(loom, shuttle)))]
+    freezing
+);
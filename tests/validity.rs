@@ -0,0 +1,232 @@
+//! Reads of types with invalid bit patterns, niches or padding, racing with
+//! writers. These are mostly useful under Miri, which checks that a torn copy
+//! is never turned into a value of the type.
+
+#[cfg(feature = "critical-section")]
+mod common;
+
+use seqlock::SeqLock;
+use std::num::{NonZeroU32, NonZeroU64};
+use std::thread;
+
+// Fewer iterations under Miri, which is slow.
+const ITERATIONS: u32 = if cfg!(miri) { 20 } else { 1000 };
+
+// Writes `f(1..=ITERATIONS)` from one thread while another reads, checking each
+// value read with `check`.
+fn race<T>(init: T, f: impl Fn(u32) -> T + Sync, check: impl Fn(T))
+where
+    T: Copy + Send,
+{
+    let lock = SeqLock::new(init);
+    thread::scope(|s| {
+        s.spawn(|| {
+            for i in 1..=ITERATIONS {
+                lock.write(f(i));
+            }
+        });
+        for _ in 0..ITERATIONS {
+            check(lock.read());
+        }
+    });
+    check(lock.read());
+}
+
+#[test]
+fn bool_and_char() {
+    race(
+        ([false; 4], ['a'; 4]),
+        |i| {
+            (
+                [i % 2 == 0; 4],
+                [char::from_u32(0x1f600 + i % 16).unwrap(); 4],
+            )
+        },
+        |(bools, chars)| {
+            assert!(bools.iter().all(|&x| x == bools[0]));
+            assert!(chars.iter().all(|&x| x == chars[0]));
+        },
+    );
+}
+
+#[test]
+fn nonzero_and_option() {
+    race(
+        [None::<NonZeroU32>; 4],
+        |i| [NonZeroU32::new(i % 3); 4],
+        |val| {
+            assert!(val.iter().all(|&x| x == val[0]));
+            assert!(val[0].is_none_or(|n| n.get() < 3));
+        },
+    );
+}
+
+// Every variant fills the whole enum, to avoid padding.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[repr(u32)]
+enum State {
+    Idle(u32),
+    Busy(NonZeroU32),
+    Done(Option<NonZeroU32>),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Color {
+    Red,
+    Green,
+    Blue,
+}
+
+#[test]
+fn enum_with_niches() {
+    race(
+        [State::Idle(0); 4],
+        |i| {
+            let state = match i % 3 {
+                0 => State::Idle(i),
+                1 => State::Busy(NonZeroU32::new(i).unwrap()),
+                _ => State::Done(NonZeroU32::new(i % 2)),
+            };
+            [state; 4]
+        },
+        |val| assert!(val.iter().all(|&x| x == val[0])),
+    );
+}
+
+#[test]
+fn fieldless_enum() {
+    race(
+        [Color::Red; 8],
+        |i| [[Color::Red, Color::Green, Color::Blue][i as usize % 3]; 8],
+        |val| assert!(val.iter().all(|&x| x == val[0])),
+    );
+}
+
+// The atomic copies used by Miri, TSan and `atomic-memcpy` don't preserve
+// pointer provenance.
+#[test]
+#[cfg_attr(any(miri, tsan, feature = "atomic-memcpy"), ignore)]
+fn references() {
+    static STRINGS: [&str; 3] = ["a", "bb", "ccc"];
+    race(
+        [STRINGS[0]; 4],
+        |i| [STRINGS[i as usize % 3]; 4],
+        |val| {
+            assert!(val.iter().all(|&x| x == val[0]));
+            assert!(STRINGS.contains(&val[0]));
+        },
+    );
+}
+
+// A lone thin reference is copied as a single pointer in every
+// configuration, so it keeps its provenance even under Miri.
+#[test]
+fn thin_reference() {
+    static VALUES: [u32; 3] = [1, 2, 3];
+    race(
+        &VALUES[0],
+        |i| &VALUES[i as usize % 3],
+        |val| assert!(VALUES.contains(val)),
+    );
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Padded {
+    a: u8,
+    b: u32,
+    c: u16,
+}
+
+// The atomic copies used by Miri, TSan and `atomic-memcpy` can't read padding.
+#[test]
+#[cfg_attr(any(miri, tsan, feature = "atomic-memcpy"), ignore)]
+fn padding() {
+    race(
+        [Padded { a: 0, b: 0, c: 0 }; 4],
+        |i| {
+            [Padded {
+                a: i as u8,
+                b: i,
+                c: i as u16,
+            }; 4]
+        },
+        |val| {
+            assert!(val.iter().all(|&x| x == val[0]));
+            assert_eq!(val[0].a, val[0].b as u8);
+        },
+    );
+}
+
+#[test]
+fn maybe_uninit_matches_read() {
+    let lock = SeqLock::new([0u32; 8]);
+    thread::scope(|s| {
+        s.spawn(|| {
+            for i in 1..=ITERATIONS {
+                lock.write([i; 8]);
+            }
+        });
+        for _ in 0..ITERATIONS {
+            let snapshot = lock.read_maybe_uninit();
+            // `[u32; 8]` has no padding, so every byte is initialized.
+            let bytes = unsafe { snapshot.as_ptr().cast::<[u8; 32]>().read() };
+            let val = unsafe { snapshot.assume_init() };
+            assert!(val.iter().all(|&x| x == val[0]));
+            assert!(bytes.chunks(4).all(|b| b == val[0].to_ne_bytes()));
+        }
+    });
+    let snapshot = lock.read_maybe_uninit();
+    let bytes = unsafe { snapshot.as_ptr().cast::<[u8; 32]>().read() };
+    let expected = unsafe { std::mem::transmute::<[u32; 8], [u8; 32]>(lock.read()) };
+    assert_eq!(bytes, expected);
+}
+
+#[test]
+fn option_helpers() {
+    // Both halves of the value are `i`. Unlike most `Option`s this has no
+    // uninitialized bytes when `None`, which atomic copies require.
+    let pair = |i: u32| NonZeroU64::new(u64::from(i) << 32 | u64::from(i)).unwrap();
+    let lock = SeqLock::new(None);
+    thread::scope(|s| {
+        s.spawn(|| {
+            for i in 1..=ITERATIONS {
+                assert_eq!(lock.set_value(pair(i)), None);
+                assert_eq!(lock.take_value(), Some(pair(i)));
+            }
+            lock.set_value(pair(ITERATIONS + 1));
+        });
+        for _ in 0..ITERATIONS {
+            if let Some(n) = lock.read() {
+                assert_eq!(n.get() >> 32, n.get() & 0xffff_ffff);
+            }
+            lock.is_some();
+        }
+    });
+    assert_eq!(lock.read(), Some(pair(ITERATIONS + 1)));
+    assert_eq!(lock.current_seq() as u32, 4 * ITERATIONS + 2);
+}
+
+// Every field but `a` is misaligned.
+#[derive(Clone, Copy)]
+#[repr(C, packed)]
+struct Packed {
+    a: u8,
+    b: u64,
+    c: u32,
+    d: NonZeroU32,
+}
+
+#[test]
+fn packed() {
+    let packed = |i: u32| Packed {
+        a: i as u8,
+        b: u64::from(i),
+        c: i,
+        d: NonZeroU32::new(i.max(1)).unwrap(),
+    };
+    race(packed(0), packed, |val| {
+        let Packed { a, b, c, d } = val;
+        assert_eq!((u64::from(c), c as u8), (b, a));
+        assert_eq!(d.get(), c.max(1));
+    });
+}
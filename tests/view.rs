@@ -0,0 +1,70 @@
+//! `SeqLockView` over a cell owned by another structure.
+
+#![cfg(not(any(loom, shuttle)))]
+
+#[cfg(feature = "critical-section")]
+mod common;
+
+use seqlock::test_utils::{patterned_payload, stress};
+use seqlock::SeqLockView;
+use std::cell::UnsafeCell;
+use std::time::Duration;
+
+const WRITES: u64 = if cfg!(miri) { 10 } else { 2000 };
+
+// Stands in for a structure from another crate which owns its storage.
+struct Shared<T> {
+    cell: UnsafeCell<T>,
+}
+
+unsafe impl<T: Send> Sync for Shared<T> {}
+
+#[test]
+fn view_over_shared_cell() {
+    let shared = Shared {
+        cell: UnsafeCell::new(patterned_payload::<8>(0)),
+    };
+    let view = unsafe { SeqLockView::over_cell(&shared.cell) };
+    let report = stress(
+        2,
+        2,
+        Duration::MAX,
+        WRITES,
+        || view.read(),
+        |val| view.write(val),
+    );
+    assert_eq!(report.writes, 2 * WRITES);
+    assert_eq!(view.read_versioned().1 as u64, 2 * report.writes);
+
+    // The view's borrow of the cell has ended.
+    let last = view.read();
+    assert_eq!(shared.cell.into_inner(), last);
+}
+
+#[test]
+fn views_can_be_recreated() {
+    let shared = Shared {
+        cell: UnsafeCell::new((1u32, 2u32)),
+    };
+    let view = unsafe { SeqLockView::over_cell(&shared.cell) };
+    assert_eq!(view.update(|val| std::mem::replace(val, (3, 4))), (1, 2));
+    assert_eq!(format!("{view:?}"), "SeqLockView { data: (3, 4) }");
+
+    // The value is kept by the cell, the sequence number by the view.
+    let view = unsafe { SeqLockView::over_cell(&shared.cell) };
+    assert_eq!(view.read_versioned(), ((3, 4), 0));
+}
+
+#[test]
+fn panicking_update_writes_nothing() {
+    let cell = UnsafeCell::new(1u64);
+    let view = unsafe { SeqLockView::over_cell(&cell) };
+    let res = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        view.update(|val| {
+            *val = 2;
+            panic!("update failed");
+        })
+    }));
+    assert!(res.is_err());
+    assert_eq!(view.read_versioned(), (1, 0));
+}
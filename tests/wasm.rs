@@ -0,0 +1,28 @@
+//! Smoke tests for WebAssembly, run with `wasm-bindgen-test-runner`.
+
+#![cfg(target_arch = "wasm32")]
+
+use seqlock::SeqLock;
+use wasm_bindgen_test::wasm_bindgen_test;
+
+#[wasm_bindgen_test]
+fn read_write() {
+    let lock = SeqLock::new([0u32; 4]);
+    {
+        let mut w = lock.lock_write();
+        w[3] = 1;
+    }
+    assert_eq!(lock.read(), [0, 0, 0, 1]);
+
+    lock.write([2; 4]);
+    assert_eq!(lock.read_versioned(), ([2; 4], 4));
+}
+
+#[wasm_bindgen_test]
+fn try_lock_write_does_not_block() {
+    let lock = SeqLock::new(0);
+    let guard = lock.try_lock_write().unwrap();
+    assert!(lock.try_lock_write().is_none());
+    drop(guard);
+    assert!(lock.try_lock_write().is_some());
+}
@@ -0,0 +1,70 @@
+//! Sequence numbers wrapping from `usize::MAX` to 0, using `from_parts` to
+//! start a lock just before the boundary.
+
+#[cfg(feature = "critical-section")]
+mod common;
+
+use seqlock::test_utils::{assert_untorn, patterned_payload};
+use seqlock::SeqLock;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+
+// The last even sequence number before the wrap.
+const LAST: usize = usize::MAX - 1;
+
+#[test]
+fn writes_wrap_to_zero() {
+    let lock = SeqLock::from_parts(0u64, LAST - 2);
+    assert!(!lock.has_been_written());
+    let mut snapshot = lock.read_guard();
+    lock.write(1);
+    assert_eq!(lock.read_versioned(), (1, LAST));
+    lock.write(2);
+    assert_eq!(lock.read_versioned(), (2, 0));
+    assert!(lock.has_been_written());
+    assert!(snapshot.is_stale());
+    assert!(snapshot.refresh());
+    assert_eq!(snapshot.seq(), 0);
+    lock.update(|val| *val += 1);
+    assert_eq!(lock.into_parts(), (3, 2));
+}
+
+#[cfg(not(feature = "critical-section"))]
+#[test]
+fn writer_is_active_at_usize_max() {
+    let lock = SeqLock::from_parts(0u64, LAST);
+    let mut guard = lock.lock_write();
+    assert_eq!(lock.current_seq(), usize::MAX);
+    assert!(lock.try_read_classified().is_err());
+    *guard = 1;
+    drop(guard);
+    assert_eq!(lock.read_versioned(), (1, 0));
+}
+
+#[test]
+fn reads_across_the_wrap_are_untorn() {
+    const WRITES: u64 = if cfg!(miri) { 20 } else { 10_000 };
+    // Wrap in the middle of the writes.
+    let start = 0usize.wrapping_sub(WRITES as usize);
+    let lock = SeqLock::from_parts(patterned_payload::<8>(0), start);
+    let done = AtomicBool::new(false);
+    thread::scope(|s| {
+        s.spawn(|| {
+            for i in 1..=WRITES {
+                lock.write(patterned_payload(i));
+            }
+            done.store(true, Ordering::Relaxed);
+        });
+        while !done.load(Ordering::Relaxed) {
+            let (val, seq) = lock.read_versioned();
+            assert_eq!(seq & 1, 0);
+            // Write `i` publishes `start + 2 * i`, with wrapping.
+            let id = assert_untorn(&val);
+            assert_eq!(seq, start.wrapping_add(2 * id as usize));
+        }
+    });
+    assert_eq!(
+        lock.read_versioned(),
+        (patterned_payload(WRITES), WRITES as usize)
+    );
+}
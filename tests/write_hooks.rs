@@ -0,0 +1,75 @@
+//! Write hooks observing publications from competing writers.
+
+#![cfg(feature = "write-hooks")]
+
+#[cfg(feature = "critical-section")]
+mod common;
+
+use seqlock::SeqLock;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+#[test]
+fn hook_sees_every_write_in_order() {
+    const WRITES: u64 = if cfg!(miri) { 20 } else { 2000 };
+
+    let lock = Arc::new(SeqLock::new([0u64; 4]));
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    lock.add_write_hook({
+        let seen = seen.clone();
+        Box::new(move |val: &[u64; 4]| seen.lock().unwrap().push(*val))
+    });
+
+    // Each writer tags its writes with its index, and counts how many writes
+    // have been published in total.
+    let writers: Vec<_> = (0..2)
+        .map(|i| {
+            let lock = lock.clone();
+            thread::spawn(move || {
+                for _ in 0..WRITES {
+                    lock.update(|val| {
+                        let n = val[0] + 1;
+                        *val = [n, i, 0, n];
+                    });
+                }
+            })
+        })
+        .collect();
+    for writer in writers {
+        writer.join().unwrap();
+    }
+
+    let seen = seen.lock().unwrap();
+    assert_eq!(seen.len() as u64, 2 * WRITES);
+    for (n, val) in (1..).zip(seen.iter()) {
+        assert_eq!(val[0], n);
+        assert_eq!(val[3], n);
+    }
+    for i in 0..2 {
+        assert_eq!(seen.iter().filter(|val| val[1] == i).count() as u64, WRITES);
+    }
+}
+
+#[test]
+fn hook_reads_published_value() {
+    let lock = Arc::new(SeqLock::new(0u32));
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let id = lock.add_write_hook({
+        let lock = lock.clone();
+        let seen = seen.clone();
+        Box::new(move |&val| {
+            assert_eq!(lock.read(), val);
+            seen.lock().unwrap().push(val);
+        })
+    });
+
+    lock.write(1);
+    lock.update(|val| *val = 2);
+    lock.set_if_ne(2);
+    lock.set_if_ne(3);
+    assert!(lock.remove_write_hook(id));
+    assert!(!lock.remove_write_hook(id));
+    lock.write(4);
+
+    assert_eq!(*seen.lock().unwrap(), [1, 2, 3]);
+}
@@ -0,0 +1,84 @@
+//! The call sites recorded with the `write-location` feature.
+
+#![cfg(feature = "write-location")]
+
+#[cfg(feature = "critical-section")]
+mod common;
+
+use seqlock::SeqLock;
+
+// Asserts that the last write to `lock` was made from `line` of this file.
+#[track_caller]
+fn assert_written_at<T: Copy>(lock: &SeqLock<T>, line: u32) {
+    let location = lock.last_writer_location().expect("no write recorded");
+    assert_eq!(location.file(), file!());
+    assert_eq!(location.line(), line);
+}
+
+#[test]
+fn records_write_methods() {
+    let lock = SeqLock::new(0u32);
+    assert!(lock.last_writer_location().is_none());
+
+    lock.write(1);
+    assert_written_at(&lock, line!() - 1);
+    lock.update(|val| *val += 1);
+    assert_written_at(&lock, line!() - 1);
+    assert!(lock.set_if_ne(3));
+    assert_written_at(&lock, line!() - 1);
+    assert_eq!(lock.swap_if(|&val| val == 3, 4), Ok(3));
+    assert_written_at(&lock, line!() - 1);
+    assert_eq!(lock.fetch_or(8), 4);
+    assert_written_at(&lock, line!() - 1);
+
+    let mirror = SeqLock::new(0);
+    lock.copy_into(&mirror);
+    assert_written_at(&mirror, line!() - 1);
+    SeqLock::write_all(std::slice::from_ref(&mirror), &[5]);
+    assert_written_at(&mirror, line!() - 1);
+}
+
+#[test]
+fn skipped_write_keeps_previous_location() {
+    let lock = SeqLock::new(1u32);
+    lock.write(2);
+    let written = line!() - 1;
+    assert!(!lock.set_if_ne(2));
+    assert_written_at(&lock, written);
+}
+
+#[cfg(not(feature = "critical-section"))]
+#[test]
+fn records_where_guard_was_taken() {
+    let lock = SeqLock::new(0u32);
+    let mut guard = lock.lock_write();
+    let taken = line!() - 1;
+    *guard = 1;
+    drop(guard);
+    assert_written_at(&lock, taken);
+
+    *lock.try_lock_write().unwrap() = 2;
+    assert_written_at(&lock, line!() - 1);
+}
+
+#[cfg(all(feature = "async", not(feature = "critical-section")))]
+#[test]
+fn records_async_writer() {
+    let lock = SeqLock::new(0u32);
+    futures::executor::block_on(async {
+        *lock.lock_write_async().await = 1;
+        assert_written_at(&lock, line!() - 1);
+    });
+}
+
+#[cfg(not(any(loom, shuttle)))]
+#[test]
+fn reset_clears_location() {
+    let mut lock = SeqLock::new(0u32);
+    lock.write(1);
+    assert!(lock.last_writer_location().is_some());
+    lock.reset();
+    assert!(lock.last_writer_location().is_none());
+    lock.write(2);
+    assert_written_at(&lock, line!() - 1);
+}
@@ -0,0 +1,24 @@
+//! `try_lock_write` never hands out two guards at once.
+
+#![cfg(not(feature = "critical-section"))]
+
+use crate::SeqLock;
+
+#[kani::proof]
+fn try_lock_write_is_exclusive() {
+    let lock = SeqLock::new(0u64);
+    let first = lock.try_lock_write();
+    assert!(first.is_some());
+
+    let release: bool = kani::any();
+    let first = if release {
+        drop(first);
+        None
+    } else {
+        first
+    };
+
+    let second = lock.try_lock_write();
+    assert!(first.is_none() || second.is_none());
+    assert_eq!(second.is_some(), release);
+}
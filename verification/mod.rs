@@ -0,0 +1,85 @@
+//! Kani proof harnesses for the `SeqLock` protocol.
+//!
+//! These are only compiled by `cargo kani`, which sets `cfg(kani)`. Kani
+//! doesn't model threads, so a concurrent writer is simulated instead: a
+//! write is split into steps, and readers call `preempt` between the steps
+//! of a read, which nondeterministically runs some of the remaining steps of
+//! the pending write. Kani explores every choice at every call, and therefore
+//! every interleaving of the reader with the writer.
+
+mod exclusion;
+mod parity;
+mod reads;
+
+use crate::SeqLock;
+use core::cell::UnsafeCell;
+
+/// A write of a pair of words, which a reader may observe between any two
+/// of its steps.
+struct PairWriter {
+    lock: *const SeqLock<[u64; 2]>,
+    new: [u64; 2],
+    step: u8,
+    seq: usize,
+}
+
+impl PairWriter {
+    /// Runs the next step of the write, returning `false` if it is done.
+    fn step(&mut self) -> bool {
+        let lock = unsafe { &*self.lock };
+        let words = lock.data.as_ptr() as *mut u64;
+        match self.step {
+            0 => self.seq = lock.begin_write(crate::location::Caller::get()),
+            1 => unsafe { words.write(self.new[0]) },
+            2 => unsafe { words.add(1).write(self.new[1]) },
+            3 => lock.end_write(self.seq),
+            _ => return false,
+        }
+        self.step += 1;
+        true
+    }
+}
+
+struct Pending(UnsafeCell<Option<PairWriter>>);
+
+// Harnesses are single-threaded.
+unsafe impl Sync for Pending {}
+
+static PENDING: Pending = Pending(UnsafeCell::new(None));
+
+/// Runs the next step of the pending write, if any.
+fn step_writer() -> bool {
+    match unsafe { &mut *PENDING.0.get() } {
+        Some(writer) => writer.step(),
+        None => false,
+    }
+}
+
+/// Starts writing `new` to `lock`. The write only progresses when a reader
+/// is preempted or yields, or through `finish_write`.
+fn start_write(lock: &SeqLock<[u64; 2]>, new: [u64; 2]) {
+    unsafe {
+        *PENDING.0.get() = Some(PairWriter {
+            lock,
+            new,
+            step: 0,
+            seq: 0,
+        })
+    };
+}
+
+/// Runs the pending write to completion.
+fn finish_write() {
+    while step_writer() {}
+    unsafe { *PENDING.0.get() = None };
+}
+
+/// Called by readers between the steps of a read, see `sync::preempt`.
+pub(crate) fn preempt() {
+    while kani::any() && step_writer() {}
+}
+
+/// Called by readers waiting for the writer, see `sync::relax`.
+pub(crate) fn yield_now() {
+    step_writer();
+}
@@ -0,0 +1,25 @@
+//! The sequence number is odd exactly while a write is in progress.
+
+use crate::SeqLock;
+
+#[kani::proof]
+#[kani::unwind(4)]
+fn writes_keep_sequence_parity() {
+    let start: usize = kani::any();
+    kani::assume(start & 1 == 0);
+    let lock = SeqLock::from_parts(0u64, start);
+
+    let writes: u8 = kani::any();
+    kani::assume(writes <= 3);
+    let mut expected = start;
+    for _ in 0..writes {
+        assert_eq!(lock.current_seq(), expected);
+        let seq = lock.begin_write(crate::location::Caller::get());
+        assert_eq!(seq, expected.wrapping_add(1));
+        assert_eq!(lock.current_seq() & 1, 1);
+        lock.end_write(seq);
+        expected = expected.wrapping_add(2);
+        assert_eq!(lock.current_seq(), expected);
+        assert_eq!(lock.current_seq() & 1, 0);
+    }
+}
@@ -0,0 +1,47 @@
+//! A validated read always returns a value which was completely written.
+
+use super::{finish_write, start_write, step_writer};
+use crate::SeqLock;
+
+/// Starts writing `new` to `lock`, and lets the write run for an arbitrary
+/// number of steps.
+fn racing_write(lock: &SeqLock<[u64; 2]>, new: [u64; 2]) {
+    start_write(lock, new);
+    let steps: u8 = kani::any();
+    kani::assume(steps <= 4);
+    for _ in 0..steps {
+        step_writer();
+    }
+}
+
+#[kani::proof]
+#[kani::unwind(8)]
+fn try_read_is_never_torn() {
+    let old = [kani::any(), kani::any()];
+    let new = [kani::any(), kani::any()];
+    let lock = SeqLock::new(old);
+    racing_write(&lock, new);
+
+    // The reader may be preempted by the rest of the write at any point.
+    match crate::try_read(&lock.seq, &lock.data) {
+        Ok((val, seq)) => assert!((val == old && seq == 0) || (val == new && seq == 2)),
+        Err(seq) => assert!(seq <= 2),
+    }
+    finish_write();
+}
+
+#[kani::proof]
+#[kani::unwind(12)]
+fn read_returns_a_complete_write() {
+    let old = [kani::any(), kani::any()];
+    let new = [kani::any(), kani::any()];
+    let lock = SeqLock::new(old);
+    racing_write(&lock, new);
+
+    let (val, seq) = lock.read_versioned();
+    assert!((val == old && seq == 0) || (val == new && seq == 2));
+
+    // Once the write is done, it is always visible.
+    finish_write();
+    assert_eq!(lock.read_versioned(), (new, 2));
+}